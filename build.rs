@@ -0,0 +1,70 @@
+//! Embeds the CPU CSV datasets from the `res/cpu` submodules into the library, so the CPU database
+//! can be built at runtime without the submodules or a writable working directory.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every CSV file below these folders is a snapshot of the dataset. The library imports them
+/// oldest first, see `cpu::history::Snapshot`.
+const INTEL_FOLDER: &str = "res/cpu/intel";
+const AMD_FOLDER: &str = "res/cpu/amd";
+
+/// The CSV files below `folder`, recursively, sorted by path.
+fn csv_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.is_dir() {
+                folders.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "csv") {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+fn sources(name: &str, root: &Path, files: &[PathBuf]) -> String {
+    let mut out = format!("pub(crate) const {}: &[EmbeddedSource] = &[\n", name);
+    for file in files {
+        let relative = file.strip_prefix(root).unwrap_or(file);
+        out.push_str(&format!(
+            "    EmbeddedSource {{ path: {:?}, contents: include_bytes!({:?}) }},\n",
+            relative.to_string_lossy().replace('\\', "/"),
+            file.to_string_lossy(),
+        ));
+    }
+    out.push_str("];\n");
+    out
+}
+
+fn main() {
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=res/cpu");
+
+    let intel_files = csv_files(&root.join(INTEL_FOLDER));
+    let amd_files = csv_files(&root.join(AMD_FOLDER));
+
+    if intel_files.is_empty() || amd_files.is_empty() {
+        println!(
+            "cargo:warning=CPU datasets not found under res/cpu, the embedded CPU database will be \
+             incomplete. Run `git submodule update --init --recursive` to fetch them."
+        );
+    }
+
+    let generated = sources("INTEL_SOURCES", &root, &intel_files)
+        + &sources("AMD_SOURCES", &root, &amd_files);
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_sources.rs");
+    fs::write(out, generated).expect("Unable to write the embedded CPU sources");
+}
@@ -1,11 +1,20 @@
-use std::fs;
-use rusqlite::{params, Connection, Error};
+use std::collections::BTreeMap;
+use rusqlite::{params, Connection, Row};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::cache::{self, CacheSpec};
 use crate::cpu::{eCPUDetails, EnumCPUData};
-use crate::cpu::intel::eIntelData;
-use crate::cpu::private::Database;
+use crate::cpu::embedded::{EmbeddedSource, AMD_SOURCES};
+use crate::cpu::launch::LaunchDate;
+use crate::cpu::memory::{self, MemorySupport};
+use crate::cpu::power::PowerSpec;
+use crate::cpu::spec::Vendor;
+use crate::cpu::import::{extra_from_json, extra_to_json, read_rows, CsvRow, CsvSource, ImportReport, ImportedRows};
+use crate::utils::{first_number, leading_number, parse_size_bytes};
 use std::str::FromStr;
 
+// The field names mirror the columns of AMDCpuData
 #[derive(Debug)]
+#[allow(non_snake_case)]
 pub struct AMDData {
     pub name: String,
     pub family: String,
@@ -13,7 +22,9 @@ pub struct AMDData {
     pub formFactor: Vec<FormFactor>,
     pub cores: usize,
     pub threads: usize,
+    /// Max boost clock in GHz
     pub boost_clock: f32,
+    /// Base clock in GHz
     pub base_clock: f32,
     pub L1Cache: String,
     pub L2Cache: String,
@@ -36,7 +47,20 @@ pub struct AMDData {
     pub graphics: Graphics,
     pub AMD_RyzenAIEnabled: Option<bool>,
     pub product_id: ProductID,
-    pub supported_technologies: Vec<String>
+    pub supported_technologies: Vec<String>,
+    /// Values of CSV columns hwisak does not know about, by header name
+    pub extra: BTreeMap<String, String>,
+    /// Parsed from `L1Cache`, `L2Cache` and `L3Cache`
+    pub caches: Vec<CacheSpec>,
+    /// Parsed from `launch_date`
+    pub launched: Option<LaunchDate>,
+    /// Parsed from `system_memory_type`, `system_memory_specification` and `memory_channels`
+    pub memory: Vec<MemorySupport>,
+    /// Parsed from `DefaultTDP` and `AMDConfigurableTDP`
+    pub power: PowerSpec,
+    /// Whether the record comes from a user overlay, see
+    /// [`CpuDatabase::add_overlay`](crate::cpu::database::CpuDatabase::add_overlay)
+    pub user_provided: bool,
 }
 
 #[derive(Debug)]
@@ -83,6 +107,20 @@ pub enum FormFactor {
     Handheld,
 }
 
+impl FormFactor {
+    /// The name used in the AMDCpuData CSV files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormFactor::Laptops => "Laptops",
+            FormFactor::Desktops => "Desktops",
+            FormFactor::BoxedProcessor => "Boxed Processor",
+            FormFactor::TinyDesktops => "1L Desktops",
+            FormFactor::MobileWorkstations => "Mobile Workstations",
+            FormFactor::Handheld => "Handheld",
+        }
+    }
+}
+
 impl FromStr for FormFactor {
     type Err = String;
 
@@ -99,6 +137,54 @@ impl FromStr for FormFactor {
     }
 }
 
+/// Column headers of the AMDCpuData CSV files.
+mod header {
+    pub(super) const NAME: &str = "Name";
+    pub(super) const FAMILY: &str = "Family";
+    pub(super) const SERIES: &str = "Series";
+    pub(super) const FORM_FACTOR: &str = "Form Factor";
+    pub(super) const CORES: &str = "# of CPU Cores";
+    pub(super) const THREADS: &str = "# of Threads";
+    pub(super) const BOOST_CLOCK: &str = "Max. Boost Clock";
+    pub(super) const BASE_CLOCK: &str = "Base Clock";
+    pub(super) const L1_CACHE: &str = "L1 Cache";
+    pub(super) const L2_CACHE: &str = "L2 Cache";
+    pub(super) const L3_CACHE: &str = "L3 Cache";
+    pub(super) const DEFAULT_TDP: &str = "Default TDP";
+    pub(super) const CONFIGURABLE_TDP: &str = "AMD Configurable TDP (cTDP)";
+    pub(super) const PROCESSOR_TECHNOLOGY: &str = "Processor Technology for CPU Cores";
+    pub(super) const UNLOCKED: &str = "Unlocked for Overclocking";
+    pub(super) const SOCKET: &str = "CPU Socket";
+    pub(super) const THERMAL_SOLUTION_PIB: &str = "Thermal Solution (PIB)";
+    pub(super) const RECOMMENDED_COOLER: &str = "Recommended Cooler";
+    pub(super) const THERMAL_SOLUTION_MPK: &str = "Thermal Solution (MPK)";
+    pub(super) const MAX_TEMPERATURE: &str = "Max. Operating Temperature (Tjmax)";
+    pub(super) const LAUNCH_DATE: &str = "Launch Date";
+    pub(super) const OS_SUPPORT: &str = "*OS Support";
+    pub(super) const PCI_EXPRESS_VERSION: &str = "PCI Express® Version";
+    pub(super) const MEMORY_TYPE: &str = "System Memory Type";
+    pub(super) const MEMORY_CHANNELS: &str = "Memory Channels";
+    pub(super) const MEMORY_SPECIFICATION: &str = "System Memory Specification";
+    pub(super) const GRAPHICS_MODEL: &str = "Graphics Model";
+    pub(super) const GRAPHICS_CORES: &str = "Graphics Core Count";
+    pub(super) const GRAPHICS_FREQUENCY: &str = "Graphics Frequency";
+    pub(super) const RYZEN_AI: &str = "AMD Ryzen™ AI";
+    pub(super) const PRODUCT_ID_BOXED: &str = "Product ID Boxed";
+    pub(super) const PRODUCT_ID_TRAY: &str = "Product ID Tray";
+    pub(super) const PRODUCT_ID_MPK: &str = "Product ID MPK";
+    pub(super) const SUPPORTED_TECHNOLOGIES: &str = "Supported Technologies";
+
+    pub(super) const ALL: &[&str] = &[
+        NAME, FAMILY, SERIES, FORM_FACTOR, CORES, THREADS, BOOST_CLOCK, BASE_CLOCK, L1_CACHE,
+        L2_CACHE, L3_CACHE, DEFAULT_TDP, CONFIGURABLE_TDP, PROCESSOR_TECHNOLOGY, UNLOCKED, SOCKET,
+        THERMAL_SOLUTION_PIB, RECOMMENDED_COOLER, THERMAL_SOLUTION_MPK, MAX_TEMPERATURE, LAUNCH_DATE,
+        OS_SUPPORT, PCI_EXPRESS_VERSION, MEMORY_TYPE, MEMORY_CHANNELS, MEMORY_SPECIFICATION,
+        GRAPHICS_MODEL, GRAPHICS_CORES, GRAPHICS_FREQUENCY, RYZEN_AI, PRODUCT_ID_BOXED,
+        PRODUCT_ID_TRAY, PRODUCT_ID_MPK, SUPPORTED_TECHNOLOGIES,
+    ];
+}
+
+#[allow(non_camel_case_types)]
 pub enum eAMDData {
     Name,
     Family,
@@ -117,10 +203,22 @@ pub enum eAMDData {
     Socket,
     LaunchDate,
     GraphicsModel,
+    L1CacheBytes,
+    L2CacheBytes,
+    L3CacheBytes,
+    TDPWatts,
+    LithographyNm,
+    /// The sortable launch date, see [`LaunchDate::key`]
+    LaunchKey,
+    /// Lowest configurable TDP in watts
+    ConfigurableTDPMinWatts,
+    /// Highest configurable TDP in watts
+    ConfigurableTDPMaxWatts,
+    UserProvided,
 }
 
 impl eAMDData {
-    fn to_string(&self) -> &str {
+    pub(crate) fn to_string(&self) -> &'static str {
         match self {
             eAMDData::Name => "name",
             eAMDData::Family => "family",
@@ -128,17 +226,26 @@ impl eAMDData {
             eAMDData::FormFactor => "form_factor",
             eAMDData::Cores => "cores",
             eAMDData::Threads => "threads",
-            eAMDData::MaxBoostClock => "max_boost_clock",
+            eAMDData::MaxBoostClock => "boost_clock",
             eAMDData::BaseClock => "base_clock",
-            eAMDData::L2Cache => "l2_cache",
-            eAMDData::L3Cache => "l3_cache",
-            eAMDData::TDP => "tdp",
-            eAMDData::L1Cache => "l1_cache",
-            eAMDData::ConfigurableTDP => "configurable_tdp",
-            eAMDData::ProcessorTechnology => "processor_technology",
-            eAMDData::Socket => "socket",
+            eAMDData::L2Cache => "L2Cache",
+            eAMDData::L3Cache => "L3Cache",
+            eAMDData::TDP => "DefaultTDP",
+            eAMDData::L1Cache => "L1Cache",
+            eAMDData::ConfigurableTDP => "AMDConfigurableTDP",
+            eAMDData::ProcessorTechnology => "lithography",
+            eAMDData::Socket => "cpu_socket",
             eAMDData::LaunchDate => "launch_date",
             eAMDData::GraphicsModel => "graphics_model",
+            eAMDData::L1CacheBytes => "l1_cache_bytes",
+            eAMDData::L2CacheBytes => "l2_cache_bytes",
+            eAMDData::L3CacheBytes => "l3_cache_bytes",
+            eAMDData::TDPWatts => "default_tdp_w",
+            eAMDData::LithographyNm => "lithography_nm",
+            eAMDData::LaunchKey => "launch_key",
+            eAMDData::ConfigurableTDPMinWatts => "ctdp_min_w",
+            eAMDData::ConfigurableTDPMaxWatts => "ctdp_max_w",
+            eAMDData::UserProvided => "user_provided",
         }
     }
 }
@@ -151,18 +258,15 @@ fn parse_form_factors(s: &str) -> Vec<FormFactor> {
         .collect()
 }
 
-fn split_quoted(s: &str) -> Vec<String> {
-    if s.starts_with('"') && s.ends_with('"') {
-        s.trim_matches('"')
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect()
-    } else {
-        vec![s.to_string()]
-    }
+/// Splits a comma separated list such as `"Windows 11 - 64-Bit Edition, RHEL x86 64-Bit"`.
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
-// Add this helper function
 fn parse_ghz(s: &str) -> Result<f32, Box<dyn std::error::Error>> {
     if s.is_empty() {
         return Ok(0.0);
@@ -173,84 +277,286 @@ fn parse_ghz(s: &str) -> Result<f32, Box<dyn std::error::Error>> {
      .map_err(|e| e.into())
 }
 
-impl AMDData {
-    pub fn parse_csv_line(line: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut fields = Vec::new();
-        let mut current_field = String::new();
-        let mut in_quotes = false;
-
-        // Parse CSV considering quoted fields
-        for c in line.chars() {
-            match c {
-                '"' => in_quotes = !in_quotes,
-                ',' if !in_quotes => {
-                    fields.push(current_field.trim().to_string());
-                    current_field.clear();
-                },
-                _ => current_field.push(c)
-            }
-        }
-        fields.push(current_field.trim().to_string());
+/// `"Yes"`/`"Available"` and `"No"`/`"Not Available"` flags, `None` if empty or unknown.
+fn parse_flag(s: Option<&str>) -> Option<bool> {
+    match s?.to_lowercase().as_str() {
+        "yes" | "available" => Some(true),
+        "no" | "not available" => Some(false),
+        _ => None,
+    }
+}
 
-        // Ensure we have enough fields by padding with empty strings
-        while fields.len() < 34 {
-            fields.push(String::new());
+/// Numeric values of the free-form cache, TDP and lithography strings, stored alongside them so
+/// SQL can compare and sort on them.
+struct NumericColumns {
+    l1_cache_bytes: Option<u64>,
+    l2_cache_bytes: Option<u64>,
+    l3_cache_bytes: Option<u64>,
+    default_tdp_w: Option<f32>,
+    ctdp_min_w: Option<f32>,
+    ctdp_max_w: Option<f32>,
+    lithography_nm: Option<u64>,
+}
+
+impl NumericColumns {
+    /// Parses the cache and lithography strings, the TDP values are already parsed into `power`.
+    fn parse(l1_cache: &str, l2_cache: &str, l3_cache: &str, power: &PowerSpec, lithography: &str) -> Self {
+        NumericColumns {
+            l1_cache_bytes: parse_size_bytes(l1_cache, 1024 * 1024),
+            l2_cache_bytes: parse_size_bytes(l2_cache, 1024 * 1024),
+            l3_cache_bytes: parse_size_bytes(l3_cache, 1024 * 1024),
+            default_tdp_w: power.default_w,
+            ctdp_min_w: power.ctdp_min_w,
+            ctdp_max_w: power.ctdp_max_w,
+            lithography_nm: first_number(lithography).map(|v| v as u64),
         }
+    }
+
+    /// The numeric columns of an imported row, remembering the values of `row` that did not parse.
+    fn from_csv_row(row: &CsvRow, amd_data: &AMDData) -> Self {
+        let numeric = Self::parse(
+            &amd_data.L1Cache,
+            &amd_data.L2Cache,
+            &amd_data.L3Cache,
+            &amd_data.power,
+            &amd_data.lithography,
+        );
+
+        row.check(header::L1_CACHE, numeric.l1_cache_bytes);
+        row.check(header::L2_CACHE, numeric.l2_cache_bytes);
+        row.check(header::L3_CACHE, numeric.l3_cache_bytes);
+        row.check(header::DEFAULT_TDP, numeric.default_tdp_w);
+        row.check(header::CONFIGURABLE_TDP, numeric.ctdp_min_w);
+        row.check(header::PROCESSOR_TECHNOLOGY, numeric.lithography_nm);
+        numeric
+    }
+}
+
+impl AMDData {
+    /// Builds the struct from a row of an AMDCpuData CSV file, `None` if it has no name.
+    pub(crate) fn from_csv_row(row: &CsvRow) -> Option<Self> {
+        let name = row.get(header::NAME)?.to_string();
+        let number = |column: &str| row.parse(column, leading_number).map(|v| v as usize);
+        let ghz = |column: &str| row.parse(column, |v| parse_ghz(v).ok()).unwrap_or(0.0);
+        let optional = |column: &str| row.get(column).map(str::to_string);
 
-        Ok(AMDData {
-            name: fields[0].clone(),
-            family: fields[1].clone(),
-            series: fields[2].clone(),
-            formFactor: if fields[3].is_empty() { Vec::new() } else { parse_form_factors(&fields[3]) },
-            cores: fields[4].parse().unwrap_or(0),
-            threads: fields[5].parse().unwrap_or(0),
-            boost_clock: parse_ghz(&fields[6]).unwrap_or(0.0),
-            base_clock: parse_ghz(&fields[7]).unwrap_or(0.0),
-            L1Cache: fields[8].clone(),
-            L2Cache: fields[9].clone(),
-            L3Cache: fields[10].clone(),
-            DefaultTDP: fields[11].clone(),
-            AMDConfigurableTDP: fields[12].clone(),
-            lithography: fields[13].clone(),
-            overclocking_enabled: Some(fields[14].to_lowercase() == "yes"),
-            cpu_socket: fields[15].clone(),
-            PIB: fields[16].clone(),
-            MPK: fields[18].clone(),
-            recommended_cooler: if fields[17].is_empty() { None } else { Some(fields[17].clone()) },
-            operating_temperature_max: fields[19].replace("°C", "").parse().unwrap_or(0),
-            launch_date: fields[20].clone(),
-            os_support: if fields[21].is_empty() { Vec::new() } else { split_quoted(&fields[21]) },
-            PCI_Express_version: fields[22].clone(),
-            system_memory_type: fields[23].clone(),
-            memory_channels: fields[24].parse().unwrap_or(0),
-            system_memory_specification: fields[25].clone(),
+        let l1_cache = row.text(header::L1_CACHE);
+        let l2_cache = row.text(header::L2_CACHE);
+        let l3_cache = row.text(header::L3_CACHE);
+
+        let system_memory_type = row.text(header::MEMORY_TYPE);
+        let system_memory_specification = row.text(header::MEMORY_SPECIFICATION);
+        let memory_channels = number(header::MEMORY_CHANNELS).unwrap_or(0);
+        let supported_technologies = split_list(&row.text(header::SUPPORTED_TECHNOLOGIES));
+        let extra = row.extra(header::ALL);
+
+        Some(AMDData {
+            name,
+            family: row.text(header::FAMILY),
+            series: row.text(header::SERIES),
+            formFactor: parse_form_factors(&row.text(header::FORM_FACTOR)),
+            cores: number(header::CORES).unwrap_or(0),
+            threads: number(header::THREADS).unwrap_or(0),
+            boost_clock: ghz(header::BOOST_CLOCK),
+            base_clock: ghz(header::BASE_CLOCK),
+            caches: CacheSpec::from_amd(&l1_cache, &l2_cache, &l3_cache),
+            L1Cache: l1_cache,
+            L2Cache: l2_cache,
+            L3Cache: l3_cache,
+            power: PowerSpec::from_amd(&row.text(header::DEFAULT_TDP), &row.text(header::CONFIGURABLE_TDP)),
+            DefaultTDP: row.text(header::DEFAULT_TDP),
+            AMDConfigurableTDP: row.text(header::CONFIGURABLE_TDP),
+            lithography: row.text(header::PROCESSOR_TECHNOLOGY),
+            overclocking_enabled: parse_flag(row.get(header::UNLOCKED)),
+            cpu_socket: row.text(header::SOCKET),
+            PIB: row.text(header::THERMAL_SOLUTION_PIB),
+            MPK: row.text(header::THERMAL_SOLUTION_MPK),
+            recommended_cooler: optional(header::RECOMMENDED_COOLER),
+            operating_temperature_max: number(header::MAX_TEMPERATURE).unwrap_or(0),
+            launch_date: row.text(header::LAUNCH_DATE),
+            launched: row.parse(header::LAUNCH_DATE, LaunchDate::parse),
+            os_support: split_list(&row.text(header::OS_SUPPORT)),
+            PCI_Express_version: row.text(header::PCI_EXPRESS_VERSION),
+            memory: memory::from_amd(
+                &system_memory_type,
+                &system_memory_specification,
+                memory_channels,
+                &supported_technologies,
+                &extra,
+            ),
+            system_memory_type,
+            memory_channels,
+            system_memory_specification,
             graphics: Graphics {
-                model: fields[26].clone(),
-                cores: fields[27].parse().unwrap_or(0),
-                frequency: fields[28].replace(" MHz", "").parse().unwrap_or(0)
+                model: row.text(header::GRAPHICS_MODEL),
+                cores: number(header::GRAPHICS_CORES).unwrap_or(0),
+                frequency: number(header::GRAPHICS_FREQUENCY).unwrap_or(0),
             },
-            AMD_RyzenAIEnabled: Some(fields[29].to_lowercase() == "available"),
+            AMD_RyzenAIEnabled: parse_flag(row.get(header::RYZEN_AI)),
             product_id: ProductID {
-                boxed: if fields[30].is_empty() { None } else { Some(fields[30].clone()) },
-                tray: if fields[31].is_empty() { None } else { Some(fields[31].clone()) },
-                mpk: if fields[32].is_empty() { None } else { Some(fields[32].clone()) }
+                boxed: optional(header::PRODUCT_ID_BOXED),
+                tray: optional(header::PRODUCT_ID_TRAY),
+                mpk: optional(header::PRODUCT_ID_MPK),
             },
-            supported_technologies: if fields[33].is_empty() { Vec::new() } else { split_quoted(&fields[33]) }
+            supported_technologies,
+            extra,
+            user_provided: row.user_provided,
         })
     }
 }
 
-impl crate::cpu::Database for AMDData {
-    fn fetch(keyword: &str, column: EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error> {
-        if !Self::check_if_db_exists() {
-            Self::gen_db()
+impl AMDData {
+    /// Builds the struct from a `SELECT * FROM amd_cpus` row.
+    pub(crate) fn from_row(row: &Row) -> Self {
+        AMDData {
+            name: row.get(0).unwrap_or_else(|e| {
+                log::warn!("Error getting name: {}", e);
+                "Unknown".to_string()
+            }),
+            family: row.get(1).unwrap_or_else(|e| {
+                log::warn!("Error getting family: {}", e);
+                "Unknown".to_string()
+            }),
+            series: row.get(2).unwrap_or_else(|e| {
+                log::warn!("Error getting series: {}", e);
+                "Unknown".to_string()
+            }),
+            // Stored as the CSV text, e.g. "Desktops, Boxed Processor"
+            formFactor: parse_form_factors(&row.get::<_, Option<String>>(3).unwrap_or_else(|e| {
+                log::warn!("Error getting formFactor: {}", e);
+                None
+            }).unwrap_or_default()),
+            cores: row.get::<_, Option<usize>>(4).unwrap_or_else(|e| {
+                log::warn!("Error getting cores: {}", e);
+                None
+            }).unwrap_or(0),
+            threads: row.get::<_, Option<usize>>(5).unwrap_or_else(|e| {
+                log::warn!("Error getting threads: {}", e);
+                None
+            }).unwrap_or(0),
+            // Clocks are stored in MHz
+            boost_clock: row.get::<_, Option<usize>>(6).unwrap_or_else(|e| {
+                log::warn!("Error getting boost_clock: {}", e);
+                None
+            }).map_or(0.0, |mhz| mhz as f32 / 1000.0),
+            base_clock: row.get::<_, Option<usize>>(7).unwrap_or_else(|e| {
+                log::warn!("Error getting base_clock: {}", e);
+                None
+            }).map_or(0.0, |mhz| mhz as f32 / 1000.0),
+            L1Cache: row.get(8).unwrap_or_else(|e| {
+                log::warn!("Error getting L1Cache: {}", e);
+                "Unknown".to_string()
+            }),
+            L2Cache: row.get(9).unwrap_or_else(|e| {
+                log::warn!("Error getting L2Cache: {}", e);
+                "Unknown".to_string()
+            }),
+            L3Cache: row.get(10).unwrap_or_else(|e| {
+                log::warn!("Error getting L3Cache: {}", e);
+                "Unknown".to_string()
+            }),
+            DefaultTDP: row.get(11).unwrap_or_else(|e| {
+                log::warn!("Error getting DefaultTDP: {}", e);
+                "Unknown".to_string()
+            }),
+            AMDConfigurableTDP: row.get(12).unwrap_or_else(|e| {
+                log::warn!("Error getting AMDConfigurableTDP: {}", e);
+                "Unknown".to_string()
+            }),
+            lithography: row.get(13).unwrap_or_else(|e| {
+                log::warn!("Error getting lithography: {}", e);
+                "Unknown".to_string()
+            }),
+            overclocking_enabled: row.get(14).ok().flatten(),
+            cpu_socket: row.get(15).unwrap_or_else(|e| {
+                log::warn!("Error getting cpu_socket: {}", e);
+                "Unknown".to_string()
+            }),
+            PIB: row.get(16).unwrap_or_else(|e| {
+                log::warn!("Error getting PIB: {}", e);
+                "Unknown".to_string()
+            }),
+            MPK: row.get(17).unwrap_or_else(|e| {
+                log::warn!("Error getting MPK: {}", e);
+                "Unknown".to_string()
+            }),
+            recommended_cooler: row.get(18).ok(),
+            operating_temperature_max: row.get::<_, Option<usize>>(19).unwrap_or_else(|e| {
+                log::warn!("Error getting operating_temperature_max: {}", e);
+                None
+            }).unwrap_or(0),
+            launch_date: row.get(20).unwrap_or_else(|e| {
+                log::warn!("Error getting launch_date: {}", e);
+                "Unknown".to_string()
+            }),
+            os_support: row.get(21).unwrap_or_else(|e| {
+                log::warn!("Error getting os_support: {}", e);
+                "".to_string()
+            }).split(',').map(|s| s.trim().to_string()).collect(),
+            PCI_Express_version: row.get(22).unwrap_or_else(|e| {
+                log::warn!("Error getting PCI_Express_version: {}", e);
+                "Unknown".to_string()
+            }),
+            system_memory_type: row.get(23).unwrap_or_else(|e| {
+                log::warn!("Error getting system_memory_type: {}", e);
+                "Unknown".to_string()
+            }),
+            memory_channels: row.get::<_, Option<usize>>(24).unwrap_or_else(|e| {
+                log::warn!("Error getting memory_channels: {}", e);
+                None
+            }).unwrap_or(0),
+            system_memory_specification: row.get(25).unwrap_or_else(|e| {
+                log::warn!("Error getting system_memory_specification: {}", e);
+                "Unknown".to_string()
+            }),
+            graphics: Graphics {
+                model: row.get(26).unwrap_or_else(|e| {
+                    log::warn!("Error getting graphics model: {}", e);
+                    "Unknown".to_string()
+                }),
+                cores: row.get::<_, Option<usize>>(27).unwrap_or_else(|e| {
+                    log::warn!("Error getting graphics cores: {}", e);
+                    None
+                }).unwrap_or(0),
+                frequency: row.get::<_, Option<usize>>(28).unwrap_or_else(|e| {
+                    log::warn!("Error getting graphics frequency: {}", e);
+                    None
+                }).unwrap_or(0),
+            },
+            AMD_RyzenAIEnabled: row.get(29).ok().flatten(),
+            product_id: ProductID {
+                boxed: row.get(30).ok(),
+                tray: row.get(31).ok(),
+                mpk: row.get(32).ok(),
+            },
+            supported_technologies: row.get(33).unwrap_or_else(|e| {
+                log::warn!("Error getting supported_technologies: {}", e);
+                "".to_string()
+            }).split(',').map(|s| s.trim().to_string()).collect(),
+            extra: extra_from_json(row.get(39).ok().flatten()),
+            caches: cache::from_json(row.get(40).ok().flatten()),
+            launched: row.get::<_, Option<u32>>(41).ok().flatten().and_then(LaunchDate::from_key),
+            memory: memory::from_json(row.get(42).ok().flatten()),
+            power: PowerSpec {
+                default_w: row.get::<_, Option<f32>>(37).ok().flatten(),
+                ctdp_min_w: row.get::<_, Option<f32>>(43).ok().flatten(),
+                ctdp_max_w: row.get::<_, Option<f32>>(44).ok().flatten(),
+            },
+            user_provided: row.get::<_, Option<bool>>(45).ok().flatten().unwrap_or(false),
         }
+    }
+}
 
+impl crate::cpu::Database for AMDData {
+    fn fetch_in(db: &CpuDatabase, keyword: &str, column: EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error> {
         let column = match column {
-            EnumCPUData::Intel(_) => panic!("Cannot use an Intel enum for an AMD query"),
+            EnumCPUData::Intel(_) => {
+                log::debug!("Cannot use an Intel column for an AMD query");
+                return Ok(None);
+            }
             EnumCPUData::AMD(amd_col) => amd_col
         };
-        let conn = Connection::open(Self::DATABASE)?;
+        let conn = db.connection();
 
         let query = format!(
             "SELECT * FROM amd_cpus WHERE {} LIKE ?1",
@@ -263,202 +569,82 @@ impl crate::cpu::Database for AMDData {
 
 
         if let Some(row) = rows.next()? {
-            let amd_data = AMDData {
-                name: row.get(0).unwrap_or_else(|e| {
-                    println!("Error getting name: {}", e);
-                    "Unknown".to_string()
-                }),
-                family: row.get(1).unwrap_or_else(|e| {
-                    println!("Error getting family: {}", e);
-                    "Unknown".to_string()
-                }),
-                series: row.get(2).unwrap_or_else(|e| {
-                    println!("Error getting series: {}", e);
-                    "Unknown".to_string()
-                }),
-                formFactor: match row.get::<_, String>(3).unwrap_or_else(|e| {
-                    println!("Error getting formFactor: {}", e);
-                    "Unknown".to_string()
-                }).as_str() {
-                    "Laptops" => vec![FormFactor::Laptops],
-                    "Desktops" => vec![FormFactor::Desktops],
-                    "BoxedProcessor" => vec![FormFactor::BoxedProcessor],
-                    "TinyDesktops" => vec![FormFactor::TinyDesktops],
-                    "MobileWorkstations" => vec![FormFactor::MobileWorkstations],
-                    "Handheld" => vec![FormFactor::Handheld],
-                    _ => {
-                        println!("Unknown FormFactor: {}", row.get::<_, String>(3).unwrap_or_else(|e| e.to_string()));
-                        vec![FormFactor::Desktops] // Default to Desktops if unknown
-                    }
-                },
-                cores: row.get::<_, String>(4).unwrap_or_else(|e| {
-                    println!("Error getting cores: {}", e);
-                    "0".to_string()
-                }).parse().unwrap_or(0), // Parse to usize, handle errors.
-                threads: row.get::<_, String>(5).unwrap_or_else(|e| {
-                    println!("Error getting threads: {}", e);
-                    "0".to_string()
-                }).parse().unwrap_or(0),
-                boost_clock: row.get::<_, String>(6).unwrap_or_else(|e| {
-                    println!("Error getting boost_clock: {}", e);
-                    "0".to_string()
-                }).parse::<f32>().unwrap_or(0.0) ,
-                base_clock: row.get::<_, String>(7).unwrap_or_else(|e| {
-                    println!("Error getting base_clock: {}", e);
-                    "0".to_string()
-                }).parse::<f32>().unwrap_or(0.0),
-                L1Cache: row.get(8).unwrap_or_else(|e| {
-                    println!("Error getting L1Cache: {}", e);
-                    "Unknown".to_string()
-                }),
-                L2Cache: row.get(9).unwrap_or_else(|e| {
-                    println!("Error getting L2Cache: {}", e);
-                    "Unknown".to_string()
-                }),
-                L3Cache: row.get(10).unwrap_or_else(|e| {
-                    println!("Error getting L3Cache: {}", e);
-                    "Unknown".to_string()
-                }),
-                DefaultTDP: row.get(11).unwrap_or_else(|e| {
-                    println!("Error getting DefaultTDP: {}", e);
-                    "Unknown".to_string()
-                }),
-                AMDConfigurableTDP: row.get(12).unwrap_or_else(|e| {
-                    println!("Error getting AMDConfigurableTDP: {}", e);
-                    "Unknown".to_string()
-                }),
-                lithography: row.get(13).unwrap_or_else(|e| {
-                    println!("Error getting lithography: {}", e);
-                    "Unknown".to_string()
-                }),
-                overclocking_enabled: row.get(14).ok(),
-                cpu_socket: row.get(15).unwrap_or_else(|e| {
-                    println!("Error getting cpu_socket: {}", e);
-                    "Unknown".to_string()
-                }),
-                PIB: row.get(16).unwrap_or_else(|e| {
-                    println!("Error getting PIB: {}", e);
-                    "Unknown".to_string()
-                }),
-                MPK: row.get(17).unwrap_or_else(|e| {
-                    println!("Error getting MPK: {}", e);
-                    "Unknown".to_string()
-                }),
-                recommended_cooler: row.get(18).ok(),
-                operating_temperature_max: row.get::<_, String>(19).unwrap_or_else(|e| {
-                    println!("Error getting operating_temperature_max: {}", e);
-                    "0".to_string()
-                }).parse().unwrap_or(0),
-                launch_date: row.get(20).unwrap_or_else(|e| {
-                    println!("Error getting launch_date: {}", e);
-                    "Unknown".to_string()
-                }),
-                os_support: row.get(21).unwrap_or_else(|e| {
-                    println!("Error getting os_support: {}", e);
-                    "".to_string()
-                }).split(',').map(|s| s.trim().to_string()).collect(),
-                PCI_Express_version: row.get(22).unwrap_or_else(|e| {
-                    println!("Error getting PCI_Express_version: {}", e);
-                    "Unknown".to_string()
-                }),
-                system_memory_type: row.get(23).unwrap_or_else(|e| {
-                    println!("Error getting system_memory_type: {}", e);
-                    "Unknown".to_string()
-                }),
-                memory_channels: row.get::<_, String>(24).unwrap_or_else(|e| {
-                    println!("Error getting memory_channels: {}", e);
-                    "0".to_string()
-                }).parse().unwrap_or(0),
-                system_memory_specification: row.get(25).unwrap_or_else(|e| {
-                    println!("Error getting system_memory_specification: {}", e);
-                    "Unknown".to_string()
-                }),
-                graphics: Graphics {
-                    model: row.get(26).unwrap_or_else(|e| {
-                        println!("Error getting graphics model: {}", e);
-                        "Unknown".to_string()
-                    }),
-                    cores: row.get::<_, String>(27).unwrap_or_else(|e| {
-                        println!("Error getting graphics cores: {}", e);
-                        "0".to_string()
-                    }).parse().unwrap_or(0),
-                    frequency: row.get::<_, String>(28).unwrap_or_else(|e| {
-                        println!("Error getting graphics frequency: {}", e);
-                        "0".to_string()
-                    }).parse().unwrap_or(0),
-                },
-                AMD_RyzenAIEnabled: row.get(29).ok(),
-                product_id: ProductID {
-                    boxed: row.get(30).ok(),
-                    tray: row.get(31).ok(),
-                    mpk: row.get(32).ok(),
-                },
-                supported_technologies: row.get(33).unwrap_or_else(|e| {
-                    println!("Error getting supported_technologies: {}", e);
-                    "".to_string()
-                }).split(',').map(|s| s.trim().to_string()).collect(),
-            };
+            let amd_data = AMDData::from_row(row);
 
             Ok(Some(eCPUDetails::AMD(amd_data)))
         } else {
-            println!("No rows found of keyword [{}] in column [{}]", keyword, column.to_string());
+            log::debug!("No rows found of keyword [{}] in column [{}]", keyword, column.to_string());
             Ok(None)
         }
     }
-
-    fn gen_db() {
-        let file = format!("{}/amdProcessorInformation_4-12-24.csv", Self::CPU_INFO_FOLDER);
-
-        Self::save_to_database(vec![file]).expect("Unable to save data to database");
-    }
 }
 
 impl crate::cpu::private::Database for AMDData {
-    const DATABASE: &'static str = "res/db/cpu.db";
-    const CPU_INFO_FOLDER: &'static str = "res/cpu/amd/AMDCpuData";
-    fn save_to_database(files: Vec<String>) -> Result<(), rusqlite::Error> {
-        let mut conn = Connection::open(Self::DATABASE)?;
-    
+    const SOURCES: &'static [EmbeddedSource] = AMD_SOURCES;
+    const TABLE: &'static str = "amd_cpus";
+    const VENDOR: Vendor = Vendor::AMD;
+
+    fn create_table(conn: &Connection) -> Result<(), rusqlite::Error> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS amd_cpus (
                 name TEXT PRIMARY KEY,
                 family TEXT,
                 series TEXT,
                 form_factor TEXT,
-                cores TEXT,
-                threads TEXT,
-                boost_clock TEXT,
-                base_clock TEXT,
+                cores INTEGER,
+                threads INTEGER,
+                boost_clock INTEGER,
+                base_clock INTEGER,
                 L1Cache TEXT,
                 L2Cache TEXT,
                 L3Cache TEXT,
                 DefaultTDP TEXT,
                 AMDConfigurableTDP TEXT,
                 lithography TEXT,
-                overclocking_enabled TEXT,
+                overclocking_enabled INTEGER,
                 cpu_socket TEXT,
                 PIB TEXT,
                 MPK TEXT,
                 recommended_cooler TEXT,
-                operating_temperature_max TEXT,
+                operating_temperature_max INTEGER,
                 launch_date TEXT,
                 os_support TEXT,
                 PCI_Express_version TEXT,
                 system_memory_type TEXT,
-                memory_channels TEXT,
+                memory_channels INTEGER,
                 system_memory_specification TEXT,
                 graphics_model TEXT,
-                graphics_cores TEXT,
-                graphics_frequency TEXT,
-                AMD_RyzenAIEnabled TEXT,
+                graphics_cores INTEGER,
+                graphics_frequency INTEGER,
+                AMD_RyzenAIEnabled INTEGER,
                 product_id_boxed TEXT,
                 product_id_tray TEXT,
                 product_id_mpk TEXT,
-                supported_technologies TEXT
+                supported_technologies TEXT,
+                l1_cache_bytes INTEGER,
+                l2_cache_bytes INTEGER,
+                l3_cache_bytes INTEGER,
+                default_tdp_w REAL,
+                lithography_nm INTEGER,
+                extra TEXT,
+                caches TEXT,
+                launch_key INTEGER,
+                memory TEXT,
+                ctdp_min_w REAL,
+                ctdp_max_w REAL,
+                user_provided INTEGER
             )",
             [],
         )?;
-    
+        Ok(())
+    }
+
+    fn save_to_database(conn: &mut Connection, sources: &[CsvSource]) -> Result<ImportReport, rusqlite::Error> {
+        Self::prepare_schema(conn)?;
+
+        let mut report = ImportReport::default();
+        let mut imported = ImportedRows::default();
+
         let tx = conn.transaction()?;
         {
             let mut stmt = tx.prepare(r#"
@@ -466,63 +652,128 @@ impl crate::cpu::private::Database for AMDData {
                 ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
                 ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
                 ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
-                ?31, ?32, ?33, ?34
+                ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38, ?39, ?40,
+                ?41, ?42, ?43, ?44, ?45, ?46
             )
             "#)?;
-    
-            for file in files {
-                let content = fs::read_to_string(&file).map_err(|e| {
-                    eprintln!("Error reading file {}: {}", file, e);
-                    rusqlite::Error::InvalidParameterName(e.to_string())
-                })?;
-    
-                for line in content.lines().skip(1) {
-                    if let Ok(amd_data) = Self::parse_csv_line(line) {
-                        stmt.execute(params![
-                            amd_data.name,
-                            amd_data.family,
-                            amd_data.series,
-                            format!("{:?}", amd_data.formFactor),
-                            amd_data.cores,
-                            amd_data.threads,
-                            amd_data.boost_clock.to_string(),
-                            amd_data.base_clock.to_string(),
-                            amd_data.L1Cache,
-                            amd_data.L2Cache,
-                            amd_data.L3Cache,
-                            amd_data.DefaultTDP,
-                            amd_data.AMDConfigurableTDP,
-                            amd_data.lithography,
-                            amd_data.overclocking_enabled.map_or("N/A".to_string(), |b| b.to_string()),
-                            amd_data.cpu_socket,
-                            amd_data.PIB,
-                            amd_data.MPK,
-                            amd_data.recommended_cooler.unwrap_or_default(),
-                            amd_data.operating_temperature_max,
-                            amd_data.launch_date,
-                            amd_data.os_support.join(","),
-                            amd_data.PCI_Express_version,
-                            amd_data.system_memory_type,
-                            amd_data.memory_channels,
-                            amd_data.system_memory_specification,
-                            amd_data.graphics.model,
-                            amd_data.graphics.cores,
-                            amd_data.graphics.frequency,
-                            amd_data.AMD_RyzenAIEnabled.map_or("N/A".to_string(), |b| b.to_string()),
-                            amd_data.product_id.boxed.unwrap_or_default(),
-                            amd_data.product_id.tray.unwrap_or_default(),
-                            amd_data.product_id.mpk.unwrap_or_default(),
-                            amd_data.supported_technologies.join(",")
-                        ])?;
-                    } else {
-                        eprintln!("Skipping invalid line: {}", line);
+
+            for source in sources {
+                for row in read_rows(source, header::ALL, &mut report) {
+                    let row = imported.merge_overlay(header::NAME, row);
+                    let Some(amd_data) = Self::from_csv_row(&row) else {
+                        report.skip(&source.path, row.line, format!("no \"{}\" value", header::NAME));
                         continue;
-                    }
+                    };
+
+                    let numeric = NumericColumns::from_csv_row(&row, &amd_data);
+                    imported.add(&amd_data.name, &row, &mut report);
+
+                    stmt.execute(params![
+                        amd_data.name,
+                        amd_data.family,
+                        amd_data.series,
+                        amd_data.formFactor.iter().map(FormFactor::as_str).collect::<Vec<_>>().join(", "),
+                        amd_data.cores,
+                        amd_data.threads,
+                        (amd_data.boost_clock * 1000.0).round() as usize,
+                        (amd_data.base_clock * 1000.0).round() as usize,
+                        amd_data.L1Cache,
+                        amd_data.L2Cache,
+                        amd_data.L3Cache,
+                        amd_data.DefaultTDP,
+                        amd_data.AMDConfigurableTDP,
+                        amd_data.lithography,
+                        amd_data.overclocking_enabled,
+                        amd_data.cpu_socket,
+                        amd_data.PIB,
+                        amd_data.MPK,
+                        amd_data.recommended_cooler.unwrap_or_default(),
+                        amd_data.operating_temperature_max,
+                        amd_data.launch_date,
+                        amd_data.os_support.join(","),
+                        amd_data.PCI_Express_version,
+                        amd_data.system_memory_type,
+                        amd_data.memory_channels,
+                        amd_data.system_memory_specification,
+                        amd_data.graphics.model,
+                        amd_data.graphics.cores,
+                        amd_data.graphics.frequency,
+                        amd_data.AMD_RyzenAIEnabled,
+                        amd_data.product_id.boxed.unwrap_or_default(),
+                        amd_data.product_id.tray.unwrap_or_default(),
+                        amd_data.product_id.mpk.unwrap_or_default(),
+                        amd_data.supported_technologies.join(","),
+                        numeric.l1_cache_bytes,
+                        numeric.l2_cache_bytes,
+                        numeric.l3_cache_bytes,
+                        numeric.default_tdp_w,
+                        numeric.lithography_nm,
+                        extra_to_json(&amd_data.extra),
+                        cache::to_json(&amd_data.caches),
+                        amd_data.launched.map(|date| date.key()),
+                        memory::to_json(&amd_data.memory),
+                        numeric.ctdp_min_w,
+                        numeric.ctdp_max_w,
+                        amd_data.user_provided
+                    ])?;
                 }
             }
         }
         tx.commit()?;
-    
-        Ok(())
+
+        Ok(report)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::intel::eIntelData;
+    use crate::cpu::private::Database as _;
+    use crate::cpu::Database;
+    use crate::tests::source;
+
+    const CSV: &str = "\
+Name,L1 Cache,L2 Cache,L3 Cache,Default TDP,AMD Configurable TDP (cTDP),Processor Technology for CPU Cores
+AMD Ryzen™ 7 7840HS,512KB,8MB,16MB,35-54W,35-54W,TSMC 4nm FinFET
+AMD Ryzen™ 5 5600X,384KB,3MB,32MB,65W,,TSMC 7nm FinFET
+AMD Ryzen™ 9 Test,lots,1MB,,unknown,,
+";
+
+    #[test]
+    fn fetch_with_an_intel_column() {
+        let db = CpuDatabase::in_memory().unwrap();
+        assert!(AMDData::fetch_in(&db, "Ryzen", EnumCPUData::Intel(eIntelData::Name)).unwrap().is_none());
+    }
+
+    #[test]
+    fn numeric_columns() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let report = AMDData::save_to_database(&mut conn, &[source("test.csv", CSV, false)]).unwrap();
+        assert_eq!(report.rows_imported(), 3);
+
+        let numeric = |name: &str| {
+            conn.query_row(
+                "SELECT l1_cache_bytes, l3_cache_bytes, default_tdp_w, ctdp_min_w, ctdp_max_w, lithography_nm FROM amd_cpus WHERE name = ?1",
+                [name],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<u64>>(0)?,
+                        row.get::<_, Option<u64>>(1)?,
+                        row.get::<_, Option<f32>>(2)?,
+                        row.get::<_, Option<f32>>(3)?,
+                        row.get::<_, Option<f32>>(4)?,
+                        row.get::<_, Option<u64>>(5)?,
+                    ))
+                },
+            ).unwrap()
+        };
+
+        assert_eq!(numeric("AMD Ryzen™ 7 7840HS"), (Some(512 * 1024), Some(16 * 1024 * 1024), Some(35.0), Some(35.0), Some(54.0), Some(4)));
+        assert_eq!(numeric("AMD Ryzen™ 5 5600X"), (Some(384 * 1024), Some(32 * 1024 * 1024), Some(65.0), None, None, Some(7)));
+        assert_eq!(numeric("AMD Ryzen™ 9 Test"), (None, None, None, None, None, None));
+
+        let failures = report.parse_failures.iter().map(|failure| failure.column.as_str()).collect::<Vec<_>>();
+        assert_eq!(failures, vec![header::L1_CACHE, header::DEFAULT_TDP]);
+    }
+}
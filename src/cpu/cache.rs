@@ -0,0 +1,455 @@
+//! Structured cache sizes parsed from the free-form cache descriptions of the datasets, and the
+//! live cache hierarchy of the machine read from Linux sysfs.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::cpu::spec::CpuSpec;
+use crate::utils::{parse_cpu_list, parse_size_bytes, read_sysfs, read_sysfs_number, sysfs_cpus, SYSFS_CPU_DIR, SYSFS_ROOT};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CacheKind {
+    Data,
+    Instruction,
+    /// Holds both instructions and data
+    Unified,
+    /// Separate instruction and data caches listed with their combined size, like the L1 caches
+    /// in the AMD dataset
+    Split,
+}
+
+/// A cache level of a CPU.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSpec {
+    pub level: u8,
+    /// Total size of this level across the package, e.g. 512 KiB for eight 64 KiB L1 caches
+    pub size_bytes: u64,
+    pub kind: CacheKind,
+    /// Whether the cache is shared between cores rather than private to each core
+    pub shared: bool,
+}
+
+impl CacheSpec {
+    /// Caches of an AMDCpuData row from its `L1 Cache`, `L2 Cache` and `L3 Cache` values,
+    /// e.g. `"512KB"`, `"8MB"` and `"96MB"`.
+    pub(crate) fn from_amd(l1: &str, l2: &str, l3: &str) -> Vec<CacheSpec> {
+        let levels = [
+            (1, l1, CacheKind::Split, false),
+            (2, l2, CacheKind::Unified, false),
+            (3, l3, CacheKind::Unified, true),
+        ];
+
+        levels
+            .into_iter()
+            .filter_map(|(level, size, kind, shared)| {
+                let size_bytes = parse_size_bytes(size, 1024 * 1024).filter(|&bytes| bytes > 0)?;
+                Some(CacheSpec { level, size_bytes, kind, shared })
+            })
+            .collect()
+    }
+
+    /// The cache of an intel-processors row from its size and `Cache Info`, e.g. `"Intel Smart
+    /// Cache"` or `"L2 Cache"`. The dataset only lists the last level cache, which is L3 unless the
+    /// description says otherwise.
+    pub(crate) fn from_intel(size_bytes: Option<u64>, info: &str) -> Vec<CacheSpec> {
+        let Some(size_bytes) = size_bytes.filter(|&bytes| bytes > 0) else {
+            return Vec::new();
+        };
+
+        let info = info.to_uppercase();
+        let level = if info.contains("L2") {
+            2
+        } else if info.contains("L1") {
+            1
+        } else {
+            3
+        };
+
+        vec![CacheSpec { level, size_bytes, kind: CacheKind::Unified, shared: true }]
+    }
+}
+
+/// Total size of the caches of `level`, `None` if there are none.
+pub fn total_bytes(caches: &[CacheSpec], level: u8) -> Option<u64> {
+    caches
+        .iter()
+        .filter(|cache| cache.level == level)
+        .map(|cache| cache.size_bytes)
+        .reduce(|a, b| a + b)
+}
+
+/// Serializes caches for the `caches` column.
+pub(crate) fn to_json(caches: &[CacheSpec]) -> String {
+    serde_json::to_string(caches).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Reads the `caches` column back, treating `NULL` or invalid JSON as no caches.
+pub(crate) fn from_json(json: Option<String>) -> Vec<CacheSpec> {
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+/// One physical cache of the machine, shared by the logical CPUs in `shared_cpus`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheInstance {
+    pub level: u8,
+    /// [`CacheKind::Data`], [`CacheKind::Instruction`] or [`CacheKind::Unified`]
+    pub kind: CacheKind,
+    pub size_bytes: u64,
+    pub line_bytes: Option<u32>,
+    pub ways: Option<u32>,
+    pub sets: Option<u32>,
+    /// The logical CPUs using this cache, from `shared_cpu_list`
+    pub shared_cpus: Vec<usize>,
+    /// The package of the CPUs using this cache
+    pub package: Option<u32>,
+}
+
+/// The detected size of a cache level that differs from the database record of the CPU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheMismatch {
+    pub level: u8,
+    /// Total size of the level in one package of this machine
+    pub detected_bytes: u64,
+    /// Total size of the level according to the database
+    pub expected_bytes: u64,
+}
+
+/// The caches of the machine, read from `devices/system/cpu/cpu*/cache/index*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHierarchy {
+    /// Every cache once, sorted by level, kind and first CPU
+    pub caches: Vec<CacheInstance>,
+}
+
+/// Parses a sysfs cache size like `"48K"` or `"32M"`.
+fn parse_sysfs_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().last()? {
+        (i, 'K') => (&s[..i], 1024),
+        (i, 'M') => (&s[..i], 1024 * 1024),
+        (i, 'G') => (&s[..i], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    Some(number.parse::<u64>().ok()? * unit)
+}
+
+fn sysfs_cache_kind(s: &str) -> Option<CacheKind> {
+    match s {
+        "Data" => Some(CacheKind::Data),
+        "Instruction" => Some(CacheKind::Instruction),
+        "Unified" => Some(CacheKind::Unified),
+        _ => None,
+    }
+}
+
+impl CacheHierarchy {
+    /// Reads the caches from `/sys`, `None` if they are not available, e.g. on other operating
+    /// systems.
+    pub fn read() -> Option<Self> {
+        Self::read_from(SYSFS_ROOT)
+    }
+
+    /// Reads the caches from the sysfs tree at `sysfs`, e.g. a copy of `/sys` recorded on another
+    /// machine. Every logical CPU lists the caches it uses, so caches shared by several CPUs are
+    /// only kept once.
+    pub fn read_from(sysfs: impl AsRef<Path>) -> Option<Self> {
+        let cpu_dir = sysfs.as_ref().join(SYSFS_CPU_DIR);
+        let mut caches: Vec<CacheInstance> = Vec::new();
+
+        for (id, path) in sysfs_cpus(&cpu_dir) {
+            let Ok(entries) = std::fs::read_dir(path.join("cache")) else {
+                continue;
+            };
+            let package = read_sysfs_number(path.join("topology/physical_package_id"));
+
+            for index in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                let (Some(level), Some(kind), Some(size_bytes)) = (
+                    read_sysfs_number(index.join("level")),
+                    read_sysfs(index.join("type")).and_then(|kind| sysfs_cache_kind(&kind)),
+                    read_sysfs(index.join("size")).and_then(|size| parse_sysfs_size(&size)),
+                ) else {
+                    continue;
+                };
+
+                let shared_cpus = read_sysfs(index.join("shared_cpu_list"))
+                    .map(|list| parse_cpu_list(&list))
+                    .filter(|cpus| cpus.contains(&id))
+                    .unwrap_or_else(|| vec![id]);
+
+                let known = caches
+                    .iter()
+                    .any(|cache| cache.level == level && cache.kind == kind && cache.shared_cpus == shared_cpus);
+                if !known {
+                    caches.push(CacheInstance {
+                        level,
+                        kind,
+                        size_bytes,
+                        line_bytes: read_sysfs_number(index.join("coherency_line_size")),
+                        ways: read_sysfs_number(index.join("ways_of_associativity")),
+                        sets: read_sysfs_number(index.join("number_of_sets")),
+                        shared_cpus,
+                        package,
+                    });
+                }
+            }
+        }
+
+        if caches.is_empty() {
+            return None;
+        }
+
+        caches.sort_by_key(|cache| (cache.level, cache.kind as u8, cache.shared_cpus[0]));
+        Some(CacheHierarchy { caches })
+    }
+
+    /// The caches used by the logical CPU `cpu`, from L1 to the last level.
+    pub fn caches_of(&self, cpu: usize) -> impl Iterator<Item = &CacheInstance> {
+        self.caches.iter().filter(move |cache| cache.shared_cpus.contains(&cpu))
+    }
+
+    /// Total size of the caches of `level` in the package of the first CPU, `None` if there are
+    /// none. Split L1 caches are added up, like in the AMD dataset.
+    pub fn package_bytes(&self, level: u8) -> Option<u64> {
+        let package = self.caches.first()?.package;
+        self.caches
+            .iter()
+            .filter(|cache| cache.level == level && cache.package == package)
+            .map(|cache| cache.size_bytes)
+            .reduce(|a, b| a + b)
+    }
+
+    /// The caches as [`CacheSpec`]s with package totals, like the database records. A cache is
+    /// shared if more CPUs use it than the L1 data cache of its first CPU, i.e. than one core.
+    pub fn to_specs(&self) -> Vec<CacheSpec> {
+        let mut specs: Vec<CacheSpec> = Vec::new();
+        let package = self.caches.first().and_then(|cache| cache.package);
+
+        for cache in self.caches.iter().filter(|cache| cache.package == package) {
+            let core_threads = self
+                .caches_of(cache.shared_cpus[0])
+                .find(|l1| l1.level == 1 && l1.kind != CacheKind::Instruction)
+                .map_or(1, |l1| l1.shared_cpus.len());
+            let shared = cache.shared_cpus.len() > core_threads;
+
+            match specs.iter_mut().find(|spec| spec.level == cache.level && spec.kind == cache.kind) {
+                Some(spec) => spec.size_bytes += cache.size_bytes,
+                None => specs.push(CacheSpec { level: cache.level, size_bytes: cache.size_bytes, kind: cache.kind, shared }),
+            }
+        }
+
+        specs
+    }
+
+    /// The levels whose size in one package differs from the database record of the CPU, e.g.
+    /// because the machine is a virtual machine or the record is wrong. Levels the record does not
+    /// list are not compared.
+    pub fn check_against(&self, spec: &CpuSpec) -> Vec<CacheMismatch> {
+        let mut levels = spec.caches.iter().map(|cache| cache.level).collect::<Vec<_>>();
+        levels.sort_unstable();
+        levels.dedup();
+
+        levels
+            .into_iter()
+            .filter_map(|level| {
+                let expected_bytes = total_bytes(&spec.caches, level)?;
+                let detected_bytes = self.package_bytes(level)?;
+                (detected_bytes != expected_bytes).then_some(CacheMismatch { level, detected_bytes, expected_bytes })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::power::PowerSpec;
+    use crate::cpu::spec::Vendor;
+    use crate::tests::TempDir;
+
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn amd_caches() {
+        let caches = CacheSpec::from_amd("512KB", "8MB", "96MB");
+
+        assert_eq!(caches, vec![
+            CacheSpec { level: 1, size_bytes: 512 * KIB, kind: CacheKind::Split, shared: false },
+            CacheSpec { level: 2, size_bytes: 8 * MIB, kind: CacheKind::Unified, shared: false },
+            CacheSpec { level: 3, size_bytes: 96 * MIB, kind: CacheKind::Unified, shared: true },
+        ]);
+        assert_eq!(total_bytes(&caches, 3), Some(96 * MIB));
+        assert_eq!(total_bytes(&caches, 4), None);
+    }
+
+    #[test]
+    fn amd_caches_skip_empty_levels() {
+        // Bare numbers are megabytes
+        let caches = CacheSpec::from_amd("", "4", "0MB");
+
+        assert_eq!(caches, vec![CacheSpec { level: 2, size_bytes: 4 * MIB, kind: CacheKind::Unified, shared: false }]);
+    }
+
+    #[test]
+    fn intel_cache_levels() {
+        let cases = [
+            ("Intel® Smart Cache", 3),
+            ("L2 Cache", 2),
+            ("l1 cache", 1),
+            ("", 3),
+        ];
+
+        for (info, level) in cases {
+            let caches = CacheSpec::from_intel(Some(12 * MIB), info);
+            assert_eq!(caches, vec![CacheSpec { level, size_bytes: 12 * MIB, kind: CacheKind::Unified, shared: true }], "{}", info);
+        }
+
+        assert!(CacheSpec::from_intel(None, "Intel® Smart Cache").is_empty());
+        assert!(CacheSpec::from_intel(Some(0), "Intel® Smart Cache").is_empty());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let caches = CacheSpec::from_amd("512KB", "8MB", "96MB");
+
+        assert_eq!(from_json(Some(to_json(&caches))), caches);
+        assert!(from_json(None).is_empty());
+        assert!(from_json(Some("not json".to_string())).is_empty());
+    }
+
+    /// Writes cache `index` of logical CPU `cpu` below `sysfs`.
+    fn write_cache(sysfs: &TempDir, cpu: usize, index: usize, (level, kind, size): (u8, &str, &str), shared_cpu_list: &str) {
+        let dir = format!("{}/cpu{}/cache/index{}", SYSFS_CPU_DIR, cpu, index);
+        sysfs.write(format!("{}/level", dir), &format!("{}\n", level));
+        sysfs.write(format!("{}/type", dir), &format!("{}\n", kind));
+        sysfs.write(format!("{}/size", dir), &format!("{}\n", size));
+        sysfs.write(format!("{}/shared_cpu_list", dir), &format!("{}\n", shared_cpu_list));
+    }
+
+    /// One package with two cores of two threads each, a private L1 and L2 per core and a shared L3.
+    fn two_core_sysfs() -> TempDir {
+        let sysfs = TempDir::new();
+        for cpu in 0..4 {
+            let core = if cpu < 2 { "0-1" } else { "2-3" };
+            sysfs.write(format!("{}/cpu{}/topology/physical_package_id", SYSFS_CPU_DIR, cpu), "0\n");
+            write_cache(&sysfs, cpu, 0, (1, "Data", "48K"), core);
+            write_cache(&sysfs, cpu, 1, (1, "Instruction", "32K"), core);
+            write_cache(&sysfs, cpu, 2, (2, "Unified", "1280K"), core);
+            write_cache(&sysfs, cpu, 3, (3, "Unified", "30M"), "0-3");
+
+            let l3 = format!("{}/cpu{}/cache/index3", SYSFS_CPU_DIR, cpu);
+            sysfs.write(format!("{}/coherency_line_size", l3), "64\n");
+            sysfs.write(format!("{}/ways_of_associativity", l3), "12\n");
+            sysfs.write(format!("{}/number_of_sets", l3), "40960\n");
+        }
+        sysfs
+    }
+
+    fn spec_with_caches(caches: Vec<CacheSpec>) -> CpuSpec {
+        CpuSpec {
+            vendor: Vendor::Intel,
+            name: "Core i5-12600K".to_string(),
+            cores: None,
+            threads: None,
+            base_clock_mhz: None,
+            boost_clock_mhz: None,
+            l1_cache_bytes: None,
+            l2_cache_bytes: None,
+            l3_cache_bytes: None,
+            caches,
+            power: PowerSpec::default(),
+            lithography_nm: None,
+            socket: None,
+            launch_date: None,
+            memory: Vec::new(),
+            integrated_graphics: None,
+            user_provided: false,
+        }
+    }
+
+    #[test]
+    fn sysfs_sizes() {
+        let cases = [
+            ("48K", Some(48 * KIB)),
+            ("1280K\n", Some(1280 * KIB)),
+            ("32M", Some(32 * MIB)),
+            ("1G", Some(1024 * MIB)),
+            ("512", Some(512)),
+            ("", None),
+            ("K", None),
+            ("1.5M", None),
+        ];
+
+        for (s, size) in cases {
+            assert_eq!(parse_sysfs_size(s), size, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn shared_caches_once() {
+        let sysfs = two_core_sysfs();
+        let hierarchy = CacheHierarchy::read_from(sysfs.path()).unwrap();
+
+        let caches = hierarchy
+            .caches
+            .iter()
+            .map(|cache| (cache.level, cache.kind, cache.size_bytes, cache.shared_cpus.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(caches, vec![
+            (1, CacheKind::Data, 48 * KIB, vec![0, 1]),
+            (1, CacheKind::Data, 48 * KIB, vec![2, 3]),
+            (1, CacheKind::Instruction, 32 * KIB, vec![0, 1]),
+            (1, CacheKind::Instruction, 32 * KIB, vec![2, 3]),
+            (2, CacheKind::Unified, 1280 * KIB, vec![0, 1]),
+            (2, CacheKind::Unified, 1280 * KIB, vec![2, 3]),
+            (3, CacheKind::Unified, 30 * MIB, vec![0, 1, 2, 3]),
+        ]);
+
+        let l3 = &hierarchy.caches[6];
+        assert_eq!((l3.line_bytes, l3.ways, l3.sets, l3.package), (Some(64), Some(12), Some(40960), Some(0)));
+        assert_eq!(hierarchy.caches[0].line_bytes, None);
+        assert_eq!(hierarchy.caches_of(3).count(), 4);
+
+        assert_eq!(hierarchy.package_bytes(1), Some(160 * KIB));
+        assert_eq!(hierarchy.package_bytes(3), Some(30 * MIB));
+        assert_eq!(hierarchy.package_bytes(4), None);
+
+        assert_eq!(hierarchy.to_specs(), vec![
+            CacheSpec { level: 1, size_bytes: 96 * KIB, kind: CacheKind::Data, shared: false },
+            CacheSpec { level: 1, size_bytes: 64 * KIB, kind: CacheKind::Instruction, shared: false },
+            CacheSpec { level: 2, size_bytes: 2560 * KIB, kind: CacheKind::Unified, shared: false },
+            CacheSpec { level: 3, size_bytes: 30 * MIB, kind: CacheKind::Unified, shared: true },
+        ]);
+    }
+
+    #[test]
+    fn missing_shared_cpu_list() {
+        let sysfs = TempDir::new();
+        // A list without the CPU itself is as good as none
+        write_cache(&sysfs, 0, 0, (2, "Unified", "2M"), "");
+        write_cache(&sysfs, 1, 0, (2, "Unified", "2M"), "0");
+        write_cache(&sysfs, 2, 0, (2, "Bogus", "2M"), "2");
+
+        let hierarchy = CacheHierarchy::read_from(sysfs.path()).unwrap();
+        let shared_cpus = hierarchy.caches.iter().map(|cache| cache.shared_cpus.clone()).collect::<Vec<_>>();
+        assert_eq!(shared_cpus, vec![vec![0], vec![1]]);
+        assert_eq!(hierarchy.caches[0].package, None);
+
+        assert_eq!(CacheHierarchy::read_from(TempDir::new().path()), None);
+    }
+
+    #[test]
+    fn mismatches() {
+        let sysfs = two_core_sysfs();
+        let hierarchy = CacheHierarchy::read_from(sysfs.path()).unwrap();
+
+        // Only the levels of the record are compared
+        let spec = spec_with_caches(CacheSpec::from_intel(Some(20 * MIB), "Intel® Smart Cache"));
+        assert_eq!(hierarchy.check_against(&spec), vec![CacheMismatch { level: 3, detected_bytes: 30 * MIB, expected_bytes: 20 * MIB }]);
+
+        // The split L1 of the AMD dataset is compared against data and instruction caches
+        let spec = spec_with_caches(CacheSpec::from_amd("160KB", "2560KB", "30MB"));
+        assert!(hierarchy.check_against(&spec).is_empty());
+
+        assert!(hierarchy.check_against(&spec_with_caches(Vec::new())).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+//! Side-by-side comparison of two CPUs from the database.
+
+use std::fmt;
+use crate::cpu::amd::AMDData;
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::intel::IntelData;
+use crate::cpu::matcher;
+use crate::cpu::spec::CpuSpec;
+
+/// Names matching a record with a lower confidence are treated as not found.
+const MIN_CONFIDENCE: f32 = 0.5;
+
+/// Which side of a [`Comparison`] is better for a field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Winner {
+    A,
+    B,
+    Tie,
+    /// At least one side does not have the value
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Count,
+    MHz,
+    Bytes,
+    Watts,
+    /// Memory transfers per second, in millions
+    MTs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldComparison {
+    pub field: &'static str,
+    pub unit: Unit,
+    pub a: Option<f64>,
+    pub b: Option<f64>,
+    /// `b - a`
+    pub delta: Option<f64>,
+    /// `delta` relative to `a`, in percent. `None` if `a` is zero.
+    pub delta_percent: Option<f64>,
+    pub winner: Winner,
+}
+
+/// Field-by-field comparison of two CPUs, see [`compare`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub a: CpuSpec,
+    pub b: CpuSpec,
+    pub fields: Vec<FieldComparison>,
+}
+
+fn field(name: &'static str, unit: Unit, a: Option<f64>, b: Option<f64>, higher_is_better: bool) -> FieldComparison {
+    let (delta, delta_percent, winner) = match (a, b) {
+        (Some(a), Some(b)) => {
+            let delta = b - a;
+            let delta_percent = (a != 0.0).then(|| delta / a * 100.0);
+            let winner = if delta == 0.0 {
+                Winner::Tie
+            } else if (delta > 0.0) == higher_is_better {
+                Winner::B
+            } else {
+                Winner::A
+            };
+            (Some(delta), delta_percent, winner)
+        }
+        _ => (None, None, Winner::Unknown),
+    };
+
+    FieldComparison { field: name, unit, a, b, delta, delta_percent, winner }
+}
+
+impl Comparison {
+    /// Compares two specifications. Higher is better for every field except the TDP.
+    pub fn new(a: CpuSpec, b: CpuSpec) -> Self {
+        let count = |v: Option<usize>| v.map(|v| v as f64);
+        let bytes = |v: Option<u64>| v.map(|v| v as f64);
+
+        let fields = vec![
+            field("Cores", Unit::Count, count(a.cores), count(b.cores), true),
+            field("Threads", Unit::Count, count(a.threads), count(b.threads), true),
+            field("Base clock", Unit::MHz, count(a.base_clock_mhz), count(b.base_clock_mhz), true),
+            field("Boost clock", Unit::MHz, count(a.boost_clock_mhz), count(b.boost_clock_mhz), true),
+            field("L1 cache", Unit::Bytes, bytes(a.l1_cache_bytes), bytes(b.l1_cache_bytes), true),
+            field("L2 cache", Unit::Bytes, bytes(a.l2_cache_bytes), bytes(b.l2_cache_bytes), true),
+            field("L3 cache", Unit::Bytes, bytes(a.l3_cache_bytes), bytes(b.l3_cache_bytes), true),
+            field("TDP", Unit::Watts, a.power.default_w.map(f64::from), b.power.default_w.map(f64::from), false),
+            field(
+                "Memory speed",
+                Unit::MTs,
+                a.max_memory_speed_mts().map(f64::from),
+                b.max_memory_speed_mts().map(f64::from),
+                true,
+            ),
+        ];
+
+        Comparison { a, b, fields }
+    }
+
+    /// How many fields each side wins, `(a, b)`.
+    pub fn wins(&self) -> (usize, usize) {
+        let count = |winner| self.fields.iter().filter(|f| f.winner == winner).count();
+        (count(Winner::A), count(Winner::B))
+    }
+}
+
+fn format_value(value: Option<f64>, unit: Unit) -> String {
+    let Some(value) = value else {
+        return "-".to_string();
+    };
+
+    match unit {
+        Unit::Count => format!("{}", value),
+        Unit::MHz => format!("{} MHz", value),
+        Unit::Watts => format!("{} W", value),
+        Unit::MTs => format!("{} MT/s", value),
+        Unit::Bytes if value.abs() >= 1024.0 * 1024.0 => format!("{} MiB", value / (1024.0 * 1024.0)),
+        Unit::Bytes => format!("{} KiB", value / 1024.0),
+    }
+}
+
+pub(crate) fn format_delta(delta: f64, unit: Unit) -> String {
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    format!("{}{}", sign, format_value(Some(delta.abs()), unit))
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.a.name.chars().count().max(self.b.name.chars().count()).max(12);
+
+        writeln!(f, "{:<14} {:>width$} {:>width$} {:>20}", "", "A", "B", "B - A")?;
+        writeln!(f, "{:<14} {:>width$} {:>width$}", "", self.a.name, self.b.name)?;
+
+        for field in &self.fields {
+            let delta = match (field.delta, field.delta_percent) {
+                (Some(delta), Some(percent)) => format!("{} ({:+.1}%)", format_delta(delta, field.unit), percent),
+                (Some(delta), None) => format_delta(delta, field.unit),
+                _ => "-".to_string(),
+            };
+            let marker = match field.winner {
+                Winner::A => "< A",
+                Winner::B => "> B",
+                Winner::Tie => "=",
+                Winner::Unknown => "",
+            };
+
+            let line = format!(
+                "{:<14} {:>width$} {:>width$} {:>20}  {}",
+                field.field,
+                format_value(field.a, field.unit),
+                format_value(field.b, field.unit),
+                delta,
+                marker
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        let (a, b) = self.wins();
+        write!(f, "A wins {} field(s), B wins {} field(s)", a, b)
+    }
+}
+
+/// Looks a CPU up by name in `db`, in both the Intel and the AMD records.
+pub fn lookup_in(db: &CpuDatabase, name: &str) -> Result<Option<CpuSpec>, rusqlite::Error> {
+    let intel = matcher::best_match_in::<IntelData>(db, name)?
+        .map(|m| (m.best.confidence, CpuSpec::from(&m.best.record)));
+    let amd = matcher::best_match_in::<AMDData>(db, name)?
+        .map(|m| (m.best.confidence, CpuSpec::from(&m.best.record)));
+
+    let best = match (intel, amd) {
+        (Some(intel), Some(amd)) => Some(if amd.0 > intel.0 { amd } else { intel }),
+        (intel, amd) => intel.or(amd),
+    };
+
+    Ok(best.filter(|(confidence, _)| *confidence >= MIN_CONFIDENCE).map(|(_, spec)| spec))
+}
+
+/// Compares two CPUs of the default [`CpuDatabase`] by name, e.g.
+/// `compare("Core i9-13900K", "Ryzen 9 7950X")`. Returns `None` if either is not found.
+pub fn compare(a: &str, b: &str) -> Result<Option<Comparison>, rusqlite::Error> {
+    compare_in(&CpuDatabase::open_default()?, a, b)
+}
+
+/// Like [`compare`], but looks the CPUs up in `db`.
+pub fn compare_in(db: &CpuDatabase, a: &str, b: &str) -> Result<Option<Comparison>, rusqlite::Error> {
+    let (Some(a), Some(b)) = (lookup_in(db, a)?, lookup_in(db, b)?) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Comparison::new(a, b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const X3D: &str = "AMD Ryzen 7 7800X3D 8-Core Processor";
+
+    fn lookup(db: &CpuDatabase, name: &str) -> CpuSpec {
+        lookup_in(db, name).unwrap().unwrap()
+    }
+
+    fn get<'a>(comparison: &'a Comparison, name: &str) -> &'a FieldComparison {
+        comparison.fields.iter().find(|field| field.field == name).unwrap()
+    }
+
+    #[test]
+    fn winners_and_deltas() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let comparison = Comparison::new(lookup(&db, X3D), lookup(&db, "AMD Ryzen 9 7950X 16-Core Processor"));
+        assert_eq!(comparison.b.name, "AMD Ryzen™ 9 7950X");
+
+        let expected = [
+            ("Cores", 8.0, 16.0, 100.0, Winner::B),
+            ("Threads", 16.0, 32.0, 100.0, Winner::B),
+            ("Base clock", 4200.0, 4500.0, 300.0 / 42.0, Winner::B),
+            ("Boost clock", 5000.0, 5700.0, 14.0, Winner::B),
+            ("L1 cache", 512.0 * 1024.0, 1024.0 * 1024.0, 100.0, Winner::B),
+            ("L2 cache", 8.0 * 1024.0 * 1024.0, 16.0 * 1024.0 * 1024.0, 100.0, Winner::B),
+            ("L3 cache", 96.0 * 1024.0 * 1024.0, 64.0 * 1024.0 * 1024.0, -100.0 / 3.0, Winner::A),
+            // Lower is better for the TDP
+            ("TDP", 120.0, 170.0, 50.0 / 1.2, Winner::A),
+            ("Memory speed", 5200.0, 5200.0, 0.0, Winner::Tie),
+        ];
+        assert_eq!(comparison.fields.len(), expected.len());
+        for (field, (name, a, b, percent, winner)) in comparison.fields.iter().zip(expected) {
+            assert_eq!(field.field, name);
+            assert_eq!((field.a, field.b), (Some(a), Some(b)), "{}", name);
+            assert_eq!(field.delta, Some(b - a), "{}", name);
+            assert!((field.delta_percent.unwrap() - percent).abs() < 1e-9, "{}", name);
+            assert_eq!(field.winner, winner, "{}", name);
+        }
+        assert_eq!(comparison.wins(), (2, 6));
+
+        // Swapping the sides swaps the winners
+        let swapped = Comparison::new(comparison.b.clone(), comparison.a.clone());
+        assert_eq!(get(&swapped, "Cores").winner, Winner::A);
+        assert_eq!(get(&swapped, "TDP").winner, Winner::B);
+        assert_eq!(swapped.wins(), (6, 2));
+    }
+
+    #[test]
+    fn missing_and_zero_values() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let comparison = Comparison::new(lookup(&db, X3D), lookup(&db, "13th Gen Intel(R) Core(TM) i9-13900K"));
+
+        // The Intel data only has the total cache size, which is not split by level
+        let l1 = get(&comparison, "L1 cache");
+        assert_eq!((l1.a, l1.b, l1.delta, l1.delta_percent), (Some(512.0 * 1024.0), None, None, None));
+        assert_eq!(l1.winner, Winner::Unknown);
+        assert_eq!(get(&comparison, "L3 cache").winner, Winner::A);
+        assert_eq!(comparison.wins(), (3, 4));
+
+        let zero = field("Cores", Unit::Count, Some(0.0), Some(4.0), true);
+        assert_eq!((zero.delta, zero.delta_percent, zero.winner), (Some(4.0), None, Winner::B));
+        let both_missing = field("TDP", Unit::Watts, None, None, false);
+        assert_eq!((both_missing.delta, both_missing.winner), (None, Winner::Unknown));
+    }
+
+    #[test]
+    fn lookup_cutoff() {
+        let db = CpuDatabase::in_memory().unwrap();
+
+        assert_eq!(lookup(&db, "Ryzen 7").name, "AMD Ryzen™ 7 7800X3D");
+        // These resemble a record, but with a confidence below MIN_CONFIDENCE
+        assert_eq!(lookup_in(&db, "Ryzen").unwrap(), None);
+        assert_eq!(lookup_in(&db, "Core").unwrap(), None);
+        assert_eq!(lookup_in(&db, "Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz").unwrap(), None);
+        assert_eq!(lookup_in(&db, "AMD EPYC 7763 64-Core Processor").unwrap(), None);
+
+        assert_eq!(compare_in(&db, X3D, "AMD EPYC 7763 64-Core Processor").unwrap(), None);
+    }
+
+    #[test]
+    fn display() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let comparison = compare_in(&db, X3D, "13th Gen Intel(R) Core(TM) i9-13900K").unwrap().unwrap();
+
+        let expected = [
+            "                                  A                    B                B - A",
+            "               AMD Ryzen™ 7 7800X3D       Core i9-13900K",
+            "Cores                             8                   24        +16 (+200.0%)  > B",
+            "Threads                          16                   32        +16 (+100.0%)  > B",
+            "Base clock                 4200 MHz             3000 MHz   -1200 MHz (-28.6%)  < A",
+            "Boost clock                5000 MHz             5800 MHz    +800 MHz (+16.0%)  > B",
+            "L1 cache                    512 KiB                    -                    -",
+            "L2 cache                      8 MiB                    -                    -",
+            "L3 cache                     96 MiB               36 MiB     -60 MiB (-62.5%)  < A",
+            "TDP                           120 W                125 W         +5 W (+4.2%)  < A",
+            "Memory speed              5200 MT/s            5600 MT/s    +400 MT/s (+7.7%)  > B",
+            "A wins 3 field(s), B wins 4 field(s)",
+        ];
+        assert_eq!(comparison.to_string(), expected.join("\n"));
+
+        assert_eq!(format_delta(0.0, Unit::Count), "+0");
+        assert_eq!(format_delta(-1536.0, Unit::Bytes), "-1.5 KiB");
+        assert_eq!(format_delta(1.5, Unit::Watts), "+1.5 W");
+    }
+}
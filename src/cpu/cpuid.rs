@@ -0,0 +1,955 @@
+//! Native CPUID decoding for x86 and x86_64.
+//!
+//! The leaves are first recorded into a [`CpuIdDump`], which [`CpuIdInfo::decode`] turns into
+//! vendor, family/model/stepping, features, brand string, caches and TLBs. Dumps can also be parsed
+//! from the raw output of the `cpuid -r` tool, so CPUs other than the current one can be decoded.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::cpu::cache::CacheKind;
+
+/// First leaf of the extended range.
+const EXTENDED: u32 = 0x8000_0000;
+
+/// First leaf of the hypervisor range.
+const HYPERVISOR: u32 = 0x4000_0000;
+
+/// Leaves with sub-leaves that are recorded.
+const SUBLEAF_LEAVES: &[u32] = &[4, 7, 0xB, 0x18, 0x8000_001D];
+
+/// Most sub-leaves recorded per leaf.
+const MAX_SUBLEAVES: u32 = 16;
+
+/// Most leaves recorded per range, in case a CPU reports a bogus maximum leaf.
+const MAX_LEAVES: u32 = 0x100;
+
+/// EAX, EBX, ECX and EDX after executing CPUID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuIdRegisters {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+/// The registers of every recorded leaf and sub-leaf. Leaves that were not recorded read as zero,
+/// like leaves above the maximum leaf of a CPU.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuIdDump {
+    leaves: BTreeMap<(u32, u32), CpuIdRegisters>,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn execute(leaf: u32, subleaf: u32) -> CpuIdRegisters {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::__cpuid_count;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::__cpuid_count;
+
+    // CPUID is available on every x86_64 CPU and every x86 CPU Rust supports
+    #[allow(unused_unsafe)]
+    let result = unsafe { __cpuid_count(leaf, subleaf) };
+    CpuIdRegisters { eax: result.eax, ebx: result.ebx, ecx: result.ecx, edx: result.edx }
+}
+
+/// Whether `subleaf` of `leaf` is past the last sub-leaf. Leaves 4, 0xB and 0x8000001D end with a
+/// null type field, leaves 7 and 0x18 report their last sub-leaf in sub-leaf 0.
+fn is_past_last_subleaf(leaf: u32, subleaf: u32, registers: &CpuIdRegisters, first: &CpuIdRegisters) -> bool {
+    match leaf {
+        4 | 0x8000_001D => registers.eax & 0x1F == 0,
+        0xB => registers.ecx & 0xFF00 == 0,
+        _ => subleaf > first.eax,
+    }
+}
+
+impl CpuIdDump {
+    /// Records every standard, hypervisor and extended leaf of the current CPU, `None` if it is
+    /// not an x86 CPU.
+    pub fn read() -> Option<Self> {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            Some(Self::record(execute))
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        {
+            None
+        }
+    }
+
+    /// Records the leaves returned by `cpuid`, called with a leaf and a sub-leaf.
+    pub fn record(mut cpuid: impl FnMut(u32, u32) -> CpuIdRegisters) -> Self {
+        let mut dump = CpuIdDump::default();
+
+        for base in [0, HYPERVISOR, EXTENDED] {
+            // The hypervisor range only exists if leaf 1 says so
+            if base == HYPERVISOR && dump.get(1, 0).ecx & (1 << 31) == 0 {
+                continue;
+            }
+
+            // Without the range, its first leaf returns the data of the highest standard leaf
+            let max = cpuid(base, 0).eax;
+            let max = if base != 0 && !(base..=base + 0xFFFF).contains(&max) { base } else { max };
+
+            for leaf in base..=max.min(base + MAX_LEAVES) {
+                let first = cpuid(leaf, 0);
+                dump.insert(leaf, 0, first);
+                if !SUBLEAF_LEAVES.contains(&leaf) {
+                    continue;
+                }
+
+                for subleaf in 1..MAX_SUBLEAVES {
+                    let registers = cpuid(leaf, subleaf);
+                    if is_past_last_subleaf(leaf, subleaf, &registers, &first) {
+                        break;
+                    }
+                    dump.insert(leaf, subleaf, registers);
+                }
+            }
+        }
+
+        dump
+    }
+
+    /// Parses the output of `cpuid -r` for one CPU, lines like
+    /// `0x00000000 0x00: eax=0x00000016 ebx=0x756e6547 ecx=0x6c65746e edx=0x49656e69`.
+    /// Other lines are ignored, and only the first CPU of a dump of several CPUs is read.
+    pub fn parse(text: &str) -> Self {
+        let mut dump = CpuIdDump::default();
+        let mut cpus = 0;
+
+        for line in text.lines() {
+            // `cpuid -r` starts every CPU with a line like `CPU 0:`
+            if line.trim_start().starts_with("CPU ") {
+                cpus += 1;
+                if cpus > 1 {
+                    break;
+                }
+                continue;
+            }
+
+            let Some((location, registers)) = line.split_once(':') else {
+                continue;
+            };
+            let mut location = location.split_whitespace().map(parse_hex);
+            let (Some(Some(leaf)), Some(Some(subleaf))) = (location.next(), location.next()) else {
+                continue;
+            };
+
+            let mut values = CpuIdRegisters::default();
+            let mut found = 0;
+            for (name, value) in registers.split_whitespace().filter_map(|word| word.split_once('=')) {
+                let Some(value) = parse_hex(value) else {
+                    continue;
+                };
+                let register = match name {
+                    "eax" => &mut values.eax,
+                    "ebx" => &mut values.ebx,
+                    "ecx" => &mut values.ecx,
+                    "edx" => &mut values.edx,
+                    _ => continue,
+                };
+                *register = value;
+                found += 1;
+            }
+
+            if found == 4 {
+                dump.insert(leaf, subleaf, values);
+            }
+        }
+
+        dump
+    }
+
+    pub fn insert(&mut self, leaf: u32, subleaf: u32, registers: CpuIdRegisters) {
+        self.leaves.insert((leaf, subleaf), registers);
+    }
+
+    /// The registers of a leaf, zero if it was not recorded.
+    pub fn get(&self, leaf: u32, subleaf: u32) -> CpuIdRegisters {
+        self.leaves.get(&(leaf, subleaf)).copied().unwrap_or_default()
+    }
+
+    /// The highest leaf of the range starting at `base`, 0 if the range is not supported.
+    fn max_leaf(&self, base: u32) -> u32 {
+        let eax = self.get(base, 0).eax;
+        if base == 0 || (base..=base + 0xFFFF).contains(&eax) { eax } else { 0 }
+    }
+
+    fn has_leaf(&self, leaf: u32) -> bool {
+        let base = leaf & 0xFFFF_0000;
+        self.max_leaf(base) >= leaf
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    let s = s.trim();
+    u32::from_str_radix(s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s), 16).ok()
+}
+
+/// Writes the dump in the format read by [`CpuIdDump::parse`].
+impl fmt::Display for CpuIdDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((leaf, subleaf), registers) in &self.leaves {
+            writeln!(
+                f,
+                "   0x{:08x} 0x{:02x}: eax=0x{:08x} ebx=0x{:08x} ecx=0x{:08x} edx=0x{:08x}",
+                leaf, subleaf, registers.eax, registers.ebx, registers.ecx, registers.edx
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A register of a feature leaf.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FeatureRegister {
+    /// Leaf 1 ECX
+    Leaf1Ecx,
+    /// Leaf 1 EDX
+    Leaf1Edx,
+    /// Leaf 7 sub-leaf 0 EBX
+    Leaf7Ebx,
+    /// Leaf 7 sub-leaf 0 ECX
+    Leaf7Ecx,
+    /// Leaf 7 sub-leaf 0 EDX
+    Leaf7Edx,
+    /// Leaf 0x80000001 ECX
+    ExtEcx,
+    /// Leaf 0x80000001 EDX
+    ExtEdx,
+}
+
+use FeatureRegister::*;
+
+/// Feature flags and their bits, named like the flags in `/proc/cpuinfo`.
+const FEATURES: &[(FeatureRegister, u8, &str)] = &[
+    (Leaf1Edx, 0, "fpu"),
+    (Leaf1Edx, 4, "tsc"),
+    (Leaf1Edx, 5, "msr"),
+    (Leaf1Edx, 6, "pae"),
+    (Leaf1Edx, 8, "cx8"),
+    (Leaf1Edx, 9, "apic"),
+    (Leaf1Edx, 15, "cmov"),
+    (Leaf1Edx, 19, "clflush"),
+    (Leaf1Edx, 23, "mmx"),
+    (Leaf1Edx, 24, "fxsr"),
+    (Leaf1Edx, 25, "sse"),
+    (Leaf1Edx, 26, "sse2"),
+    (Leaf1Edx, 28, "ht"),
+    (Leaf1Ecx, 0, "pni"),
+    (Leaf1Ecx, 1, "pclmulqdq"),
+    (Leaf1Ecx, 5, "vmx"),
+    (Leaf1Ecx, 6, "smx"),
+    (Leaf1Ecx, 7, "est"),
+    (Leaf1Ecx, 9, "ssse3"),
+    (Leaf1Ecx, 12, "fma"),
+    (Leaf1Ecx, 13, "cx16"),
+    (Leaf1Ecx, 19, "sse4_1"),
+    (Leaf1Ecx, 20, "sse4_2"),
+    (Leaf1Ecx, 21, "x2apic"),
+    (Leaf1Ecx, 22, "movbe"),
+    (Leaf1Ecx, 23, "popcnt"),
+    (Leaf1Ecx, 25, "aes"),
+    (Leaf1Ecx, 26, "xsave"),
+    (Leaf1Ecx, 27, "osxsave"),
+    (Leaf1Ecx, 28, "avx"),
+    (Leaf1Ecx, 29, "f16c"),
+    (Leaf1Ecx, 30, "rdrand"),
+    (Leaf1Ecx, 31, "hypervisor"),
+    (Leaf7Ebx, 0, "fsgsbase"),
+    (Leaf7Ebx, 2, "sgx"),
+    (Leaf7Ebx, 3, "bmi1"),
+    (Leaf7Ebx, 4, "hle"),
+    (Leaf7Ebx, 5, "avx2"),
+    (Leaf7Ebx, 7, "smep"),
+    (Leaf7Ebx, 8, "bmi2"),
+    (Leaf7Ebx, 9, "erms"),
+    (Leaf7Ebx, 10, "invpcid"),
+    (Leaf7Ebx, 11, "rtm"),
+    (Leaf7Ebx, 16, "avx512f"),
+    (Leaf7Ebx, 17, "avx512dq"),
+    (Leaf7Ebx, 18, "rdseed"),
+    (Leaf7Ebx, 19, "adx"),
+    (Leaf7Ebx, 20, "smap"),
+    (Leaf7Ebx, 21, "avx512ifma"),
+    (Leaf7Ebx, 23, "clflushopt"),
+    (Leaf7Ebx, 24, "clwb"),
+    (Leaf7Ebx, 28, "avx512cd"),
+    (Leaf7Ebx, 29, "sha_ni"),
+    (Leaf7Ebx, 30, "avx512bw"),
+    (Leaf7Ebx, 31, "avx512vl"),
+    (Leaf7Ecx, 1, "avx512vbmi"),
+    (Leaf7Ecx, 2, "umip"),
+    (Leaf7Ecx, 3, "pku"),
+    (Leaf7Ecx, 6, "avx512_vbmi2"),
+    (Leaf7Ecx, 7, "shstk"),
+    (Leaf7Ecx, 8, "gfni"),
+    (Leaf7Ecx, 9, "vaes"),
+    (Leaf7Ecx, 10, "vpclmulqdq"),
+    (Leaf7Ecx, 11, "avx512_vnni"),
+    (Leaf7Ecx, 12, "avx512_bitalg"),
+    (Leaf7Ecx, 14, "avx512_vpopcntdq"),
+    (Leaf7Ecx, 16, "la57"),
+    (Leaf7Ecx, 22, "rdpid"),
+    (Leaf7Ecx, 27, "movdiri"),
+    (Leaf7Ecx, 28, "movdir64b"),
+    (Leaf7Edx, 4, "fsrm"),
+    (Leaf7Edx, 10, "md_clear"),
+    (Leaf7Edx, 14, "serialize"),
+    (Leaf7Edx, 15, "hybrid_cpu"),
+    (Leaf7Edx, 20, "ibt"),
+    (Leaf7Edx, 22, "amx_bf16"),
+    (Leaf7Edx, 24, "amx_tile"),
+    (Leaf7Edx, 25, "amx_int8"),
+    (Leaf7Edx, 26, "spec_ctrl"),
+    (Leaf7Edx, 27, "intel_stibp"),
+    (Leaf7Edx, 29, "arch_capabilities"),
+    (Leaf7Edx, 31, "ssbd"),
+    (ExtEcx, 0, "lahf_lm"),
+    (ExtEcx, 2, "svm"),
+    (ExtEcx, 5, "abm"),
+    (ExtEcx, 6, "sse4a"),
+    (ExtEcx, 8, "3dnowprefetch"),
+    (ExtEcx, 16, "fma4"),
+    (ExtEcx, 22, "topoext"),
+    (ExtEdx, 11, "syscall"),
+    (ExtEdx, 20, "nx"),
+    (ExtEdx, 26, "pdpe1gb"),
+    (ExtEdx, 27, "rdtscp"),
+    (ExtEdx, 29, "lm"),
+];
+
+/// Leaf 4 and 0x8000001D cache types.
+fn cache_kind(cache_type: u32) -> Option<CacheKind> {
+    match cache_type {
+        1 => Some(CacheKind::Data),
+        2 => Some(CacheKind::Instruction),
+        3 => Some(CacheKind::Unified),
+        _ => None,
+    }
+}
+
+/// A cache reported by CPUID. Unlike [`CacheSpec`](crate::cpu::cache::CacheSpec), sizes are per
+/// cache, not per package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuIdCache {
+    pub level: u8,
+    pub kind: CacheKind,
+    pub size_bytes: u64,
+    /// `None` if the cache is fully associative
+    pub ways: Option<u32>,
+    pub line_bytes: u32,
+    /// How many logical processors share the cache at most, `None` if CPUID does not say
+    pub shared_by_threads: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TlbKind {
+    Data,
+    Instruction,
+    /// Translates both instruction and data addresses
+    Unified,
+}
+
+/// A translation lookaside buffer reported by CPUID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuIdTlb {
+    pub level: u8,
+    pub kind: TlbKind,
+    /// Page sizes the TLB holds, in bytes
+    pub page_sizes: Vec<u64>,
+    pub entries: u32,
+    /// `None` if the TLB is fully associative or the descriptor does not say
+    pub ways: Option<u32>,
+}
+
+const KIB_4: u64 = 4 * 1024;
+const MIB_2: u64 = 2 * 1024 * 1024;
+const MIB_4: u64 = 4 * 1024 * 1024;
+const GIB_1: u64 = 1024 * 1024 * 1024;
+
+/// The TLB descriptors of leaf 2 as descriptor, level, kind, page sizes, entries and ways. Some
+/// descriptors describe two TLBs. Cache descriptors are not listed, leaf 4 describes the caches of
+/// every CPU that has it.
+#[allow(clippy::type_complexity)]
+const TLB_DESCRIPTORS: &[(u8, u8, TlbKind, &[u64], u32, Option<u32>)] = &[
+    (0x01, 1, TlbKind::Instruction, &[KIB_4], 32, Some(4)),
+    (0x02, 1, TlbKind::Instruction, &[MIB_4], 2, None),
+    (0x03, 1, TlbKind::Data, &[KIB_4], 64, Some(4)),
+    (0x04, 1, TlbKind::Data, &[MIB_4], 8, Some(4)),
+    (0x05, 1, TlbKind::Data, &[MIB_4], 32, Some(4)),
+    (0x0B, 1, TlbKind::Instruction, &[MIB_4], 4, Some(4)),
+    (0x4F, 1, TlbKind::Instruction, &[KIB_4], 32, None),
+    (0x50, 1, TlbKind::Instruction, &[KIB_4, MIB_2, MIB_4], 64, None),
+    (0x51, 1, TlbKind::Instruction, &[KIB_4, MIB_2, MIB_4], 128, None),
+    (0x52, 1, TlbKind::Instruction, &[KIB_4, MIB_2, MIB_4], 256, None),
+    (0x55, 1, TlbKind::Instruction, &[MIB_2, MIB_4], 7, None),
+    (0x56, 1, TlbKind::Data, &[MIB_4], 16, Some(4)),
+    (0x57, 1, TlbKind::Data, &[KIB_4], 16, Some(4)),
+    (0x59, 1, TlbKind::Data, &[KIB_4], 16, None),
+    (0x5A, 1, TlbKind::Data, &[MIB_2, MIB_4], 32, Some(4)),
+    (0x5B, 1, TlbKind::Data, &[KIB_4, MIB_4], 64, None),
+    (0x5C, 1, TlbKind::Data, &[KIB_4, MIB_4], 128, None),
+    (0x5D, 1, TlbKind::Data, &[KIB_4, MIB_4], 256, None),
+    (0x61, 1, TlbKind::Instruction, &[KIB_4], 48, None),
+    (0x63, 1, TlbKind::Data, &[MIB_2, MIB_4], 32, Some(4)),
+    (0x63, 1, TlbKind::Data, &[GIB_1], 4, Some(4)),
+    (0x6A, 1, TlbKind::Data, &[KIB_4], 64, Some(8)),
+    (0x6B, 1, TlbKind::Data, &[KIB_4], 256, Some(8)),
+    (0x6C, 1, TlbKind::Data, &[MIB_2, MIB_4], 128, Some(8)),
+    (0x6D, 1, TlbKind::Data, &[GIB_1], 16, None),
+    (0x76, 1, TlbKind::Instruction, &[MIB_2, MIB_4], 8, None),
+    (0xA0, 1, TlbKind::Data, &[KIB_4], 32, None),
+    (0xB0, 1, TlbKind::Instruction, &[KIB_4], 128, Some(4)),
+    (0xB1, 1, TlbKind::Instruction, &[MIB_2], 8, Some(4)),
+    (0xB2, 1, TlbKind::Instruction, &[KIB_4], 64, Some(4)),
+    (0xB3, 1, TlbKind::Data, &[KIB_4], 128, Some(4)),
+    (0xB4, 1, TlbKind::Data, &[KIB_4], 256, Some(4)),
+    (0xB5, 1, TlbKind::Instruction, &[KIB_4], 64, Some(8)),
+    (0xB6, 1, TlbKind::Instruction, &[KIB_4], 128, Some(8)),
+    (0xBA, 1, TlbKind::Data, &[KIB_4], 64, Some(4)),
+    (0xC0, 1, TlbKind::Data, &[KIB_4, MIB_4], 8, Some(4)),
+    (0xC1, 2, TlbKind::Unified, &[KIB_4, MIB_2], 1024, Some(8)),
+    (0xC2, 1, TlbKind::Data, &[KIB_4, MIB_2], 16, Some(4)),
+    (0xC3, 2, TlbKind::Unified, &[KIB_4, MIB_2], 1536, Some(6)),
+    (0xC3, 2, TlbKind::Unified, &[GIB_1], 16, Some(4)),
+    (0xC4, 1, TlbKind::Data, &[MIB_2, MIB_4], 32, Some(4)),
+    (0xCA, 2, TlbKind::Unified, &[KIB_4], 512, Some(4)),
+];
+
+/// Ways of the AMD leaf 0x80000006 associativity encoding, `Some(None)` for fully associative and
+/// `None` for disabled or unknown.
+fn amd_l2_ways(encoded: u32) -> Option<Option<u32>> {
+    match encoded {
+        1 => Some(Some(1)),
+        2 => Some(Some(2)),
+        3 => Some(Some(3)),
+        4 => Some(Some(4)),
+        5 => Some(Some(6)),
+        6 => Some(Some(8)),
+        8 => Some(Some(16)),
+        0xA => Some(Some(32)),
+        0xB => Some(Some(48)),
+        0xC => Some(Some(64)),
+        0xD => Some(Some(96)),
+        0xE => Some(Some(128)),
+        0xF => Some(None),
+        _ => None,
+    }
+}
+
+/// Ways of the AMD leaf 0x80000005 associativity byte, `None` for fully associative.
+fn amd_l1_ways(encoded: u32) -> Option<u32> {
+    (encoded != 0xFF).then_some(encoded)
+}
+
+/// What CPUID reports about a CPU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuIdInfo {
+    /// The vendor ID, e.g. `"GenuineIntel"` or `"AuthenticAMD"`
+    pub vendor: String,
+    /// The brand string, e.g. `"AMD Ryzen 7 7800X3D 8-Core Processor"`, `None` on CPUs without one
+    pub brand: Option<String>,
+    /// Family including the extended family, e.g. 0x19 for Zen 4
+    pub family: u32,
+    /// Model including the extended model, e.g. 0xB7 for Raptor Lake
+    pub model: u32,
+    pub stepping: u32,
+    pub max_leaf: u32,
+    pub max_extended_leaf: u32,
+    /// Feature flags named like in `/proc/cpuinfo`, e.g. `"avx2"`, in the order of their leaves
+    pub features: Vec<&'static str>,
+    /// Whether the CPU runs under a hypervisor
+    pub hypervisor: bool,
+    /// The hypervisor vendor ID, e.g. `"KVMKVMKVM"` or `"Microsoft Hv"`
+    pub hypervisor_vendor: Option<String>,
+    /// The raw leaf 2 descriptor bytes, empty on CPUs without leaf 2
+    pub descriptors: Vec<u8>,
+    pub caches: Vec<CpuIdCache>,
+    pub tlbs: Vec<CpuIdTlb>,
+}
+
+/// The ASCII text of `registers`, without padding NULs and spaces.
+fn register_text(registers: &[u32]) -> String {
+    let bytes = registers.iter().flat_map(|register| register.to_le_bytes()).collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string()
+}
+
+impl CpuIdInfo {
+    /// Executes CPUID on the current CPU, `None` if it is not an x86 CPU.
+    pub fn read() -> Option<Self> {
+        CpuIdDump::read().map(|dump| Self::decode(&dump))
+    }
+
+    /// Decodes the leaves of `dump`, e.g. one recorded with [`CpuIdDump::parse`].
+    pub fn decode(dump: &CpuIdDump) -> Self {
+        let leaf0 = dump.get(0, 0);
+        let vendor = register_text(&[leaf0.ebx, leaf0.edx, leaf0.ecx]);
+        let max_leaf = dump.max_leaf(0);
+        let max_extended_leaf = dump.max_leaf(EXTENDED);
+
+        let signature = dump.get(1, 0).eax;
+        let base_family = (signature >> 8) & 0xF;
+        let base_model = (signature >> 4) & 0xF;
+        let family = if base_family == 0xF { base_family + ((signature >> 20) & 0xFF) } else { base_family };
+        let model = if base_family == 0x6 || base_family == 0xF {
+            (((signature >> 16) & 0xF) << 4) | base_model
+        } else {
+            base_model
+        };
+
+        let brand = dump.has_leaf(0x8000_0004).then(|| {
+            let registers = (0x8000_0002..=0x8000_0004)
+                .map(|leaf| dump.get(leaf, 0))
+                .flat_map(|r| [r.eax, r.ebx, r.ecx, r.edx])
+                .collect::<Vec<_>>();
+            register_text(&registers)
+        }).filter(|brand| !brand.is_empty());
+
+        let features = Self::decode_features(dump);
+        let hypervisor = features.contains(&"hypervisor");
+        let hypervisor_vendor = hypervisor.then(|| {
+            let leaf = dump.get(HYPERVISOR, 0);
+            register_text(&[leaf.ebx, leaf.ecx, leaf.edx])
+        }).filter(|vendor| !vendor.is_empty());
+
+        let descriptors = if max_leaf >= 2 { Self::descriptors(dump) } else { Vec::new() };
+        let is_amd = vendor == "AuthenticAMD" || vendor == "HygonGenuine";
+
+        let mut info = CpuIdInfo {
+            vendor,
+            brand,
+            family,
+            model,
+            stepping: signature & 0xF,
+            max_leaf,
+            max_extended_leaf,
+            features,
+            hypervisor,
+            hypervisor_vendor,
+            descriptors,
+            caches: Vec::new(),
+            tlbs: Vec::new(),
+        };
+
+        info.caches = if is_amd && info.has_feature("topoext") && dump.has_leaf(0x8000_001D) {
+            Self::deterministic_caches(dump, 0x8000_001D)
+        } else if is_amd {
+            Self::amd_legacy_caches(dump)
+        } else if max_leaf >= 4 {
+            Self::deterministic_caches(dump, 4)
+        } else {
+            Vec::new()
+        };
+
+        info.tlbs = if is_amd {
+            Self::amd_tlbs(dump)
+        } else if info.descriptors.contains(&0xFE) && max_leaf >= 0x18 {
+            Self::deterministic_tlbs(dump)
+        } else {
+            Self::descriptor_tlbs(&info.descriptors)
+        };
+
+        info
+    }
+
+    /// Whether CPUID reports the feature `name`, e.g. `"avx2"`.
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.contains(&name)
+    }
+
+    fn decode_features(dump: &CpuIdDump) -> Vec<&'static str> {
+        let leaf1 = dump.get(1, 0);
+        let leaf7 = if dump.has_leaf(7) { dump.get(7, 0) } else { CpuIdRegisters::default() };
+        let ext = if dump.has_leaf(0x8000_0001) { dump.get(0x8000_0001, 0) } else { CpuIdRegisters::default() };
+
+        FEATURES
+            .iter()
+            .filter(|(register, bit, _)| {
+                let value = match register {
+                    Leaf1Ecx => leaf1.ecx,
+                    Leaf1Edx => leaf1.edx,
+                    Leaf7Ebx => leaf7.ebx,
+                    Leaf7Ecx => leaf7.ecx,
+                    Leaf7Edx => leaf7.edx,
+                    ExtEcx => ext.ecx,
+                    ExtEdx => ext.edx,
+                };
+                value & (1 << bit) != 0
+            })
+            .map(|(_, _, name)| *name)
+            .collect()
+    }
+
+    /// The descriptor bytes of leaf 2, skipping the iteration count and registers with bit 31 set,
+    /// which hold no descriptors.
+    fn descriptors(dump: &CpuIdDump) -> Vec<u8> {
+        let leaf = dump.get(2, 0);
+
+        [leaf.eax, leaf.ebx, leaf.ecx, leaf.edx]
+            .into_iter()
+            .enumerate()
+            .filter(|(_, register)| register & (1 << 31) == 0)
+            .flat_map(|(i, register)| {
+                let bytes = register.to_le_bytes();
+                // The low byte of EAX is the iteration count
+                bytes.into_iter().skip(if i == 0 { 1 } else { 0 }).collect::<Vec<_>>()
+            })
+            .filter(|&byte| byte != 0)
+            .collect()
+    }
+
+    /// Caches from leaf 4 (Intel) or 0x8000001D (AMD), which share their layout.
+    fn deterministic_caches(dump: &CpuIdDump, leaf: u32) -> Vec<CpuIdCache> {
+        (0..MAX_SUBLEAVES)
+            .map(|subleaf| dump.get(leaf, subleaf))
+            .map_while(|registers| {
+                let kind = cache_kind(registers.eax & 0x1F)?;
+                let fully_associative = registers.eax & (1 << 9) != 0;
+                let ways = ((registers.ebx >> 22) & 0x3FF) + 1;
+                let partitions = ((registers.ebx >> 12) & 0x3FF) + 1;
+                let line_bytes = (registers.ebx & 0xFFF) + 1;
+                let sets = registers.ecx as u64 + 1;
+
+                Some(CpuIdCache {
+                    level: ((registers.eax >> 5) & 0x7) as u8,
+                    kind,
+                    size_bytes: ways as u64 * partitions as u64 * line_bytes as u64 * sets,
+                    ways: (!fully_associative).then_some(ways),
+                    line_bytes,
+                    shared_by_threads: Some(((registers.eax >> 14) & 0xFFF) + 1),
+                })
+            })
+            .collect()
+    }
+
+    /// Caches from the AMD leaves 0x80000005 and 0x80000006, for CPUs without leaf 0x8000001D.
+    fn amd_legacy_caches(dump: &CpuIdDump) -> Vec<CpuIdCache> {
+        let mut caches = Vec::new();
+
+        if dump.has_leaf(0x8000_0005) {
+            let l1 = dump.get(0x8000_0005, 0);
+            for (register, kind) in [(l1.ecx, CacheKind::Data), (l1.edx, CacheKind::Instruction)] {
+                let size_kib = register >> 24;
+                if size_kib > 0 {
+                    caches.push(CpuIdCache {
+                        level: 1,
+                        kind,
+                        size_bytes: size_kib as u64 * 1024,
+                        ways: amd_l1_ways((register >> 16) & 0xFF),
+                        line_bytes: register & 0xFF,
+                        shared_by_threads: None,
+                    });
+                }
+            }
+        }
+
+        if dump.has_leaf(0x8000_0006) {
+            let l2 = dump.get(0x8000_0006, 0);
+            let levels = [
+                (2, (l2.ecx >> 16) as u64 * 1024, (l2.ecx >> 12) & 0xF, l2.ecx & 0xFF),
+                (3, (l2.edx >> 18) as u64 * 512 * 1024, (l2.edx >> 12) & 0xF, l2.edx & 0xFF),
+            ];
+            for (level, size_bytes, ways, line_bytes) in levels {
+                if let (true, Some(ways)) = (size_bytes > 0, amd_l2_ways(ways)) {
+                    caches.push(CpuIdCache {
+                        level,
+                        kind: CacheKind::Unified,
+                        size_bytes,
+                        ways,
+                        line_bytes,
+                        shared_by_threads: None,
+                    });
+                }
+            }
+        }
+
+        caches
+    }
+
+    /// TLBs from the AMD leaves 0x80000005 (L1) and 0x80000006 (L2).
+    fn amd_tlbs(dump: &CpuIdDump) -> Vec<CpuIdTlb> {
+        let mut tlbs = Vec::new();
+
+        if dump.has_leaf(0x8000_0005) {
+            let l1 = dump.get(0x8000_0005, 0);
+            for (register, pages) in [(l1.ebx, &[KIB_4][..]), (l1.eax, &[MIB_2, MIB_4][..])] {
+                for (shift, kind) in [(16, TlbKind::Data), (0, TlbKind::Instruction)] {
+                    let entries = (register >> shift) & 0xFF;
+                    if entries > 0 {
+                        tlbs.push(CpuIdTlb {
+                            level: 1,
+                            kind,
+                            page_sizes: pages.to_vec(),
+                            entries,
+                            ways: amd_l1_ways((register >> (shift + 8)) & 0xFF),
+                        });
+                    }
+                }
+            }
+        }
+
+        if dump.has_leaf(0x8000_0006) {
+            let l2 = dump.get(0x8000_0006, 0);
+            for (register, pages) in [(l2.ebx, &[KIB_4][..]), (l2.eax, &[MIB_2, MIB_4][..])] {
+                for (shift, kind) in [(16, TlbKind::Data), (0, TlbKind::Instruction)] {
+                    let entries = (register >> shift) & 0xFFF;
+                    if let (true, Some(ways)) = (entries > 0, amd_l2_ways((register >> (shift + 12)) & 0xF)) {
+                        tlbs.push(CpuIdTlb { level: 2, kind, page_sizes: pages.to_vec(), entries, ways });
+                    }
+                }
+            }
+        }
+
+        tlbs
+    }
+
+    /// TLBs from the Intel leaf 0x18, which leaf 2 points to with descriptor 0xFE.
+    fn deterministic_tlbs(dump: &CpuIdDump) -> Vec<CpuIdTlb> {
+        let max_subleaf = dump.get(0x18, 0).eax;
+
+        (0..=max_subleaf.min(MAX_SUBLEAVES))
+            .map(|subleaf| dump.get(0x18, subleaf))
+            .filter_map(|registers| {
+                let kind = match registers.edx & 0x1F {
+                    // Load-only and store-only TLBs translate data addresses
+                    1 | 4 | 5 => TlbKind::Data,
+                    2 => TlbKind::Instruction,
+                    3 => TlbKind::Unified,
+                    _ => return None,
+                };
+                let page_sizes = [KIB_4, MIB_2, MIB_4, GIB_1]
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(bit, _)| registers.ebx & (1 << bit) != 0)
+                    .map(|(_, size)| size)
+                    .collect();
+                let ways = registers.ebx >> 16;
+                let fully_associative = registers.edx & (1 << 8) != 0;
+                // Bogus ways and sets, e.g. from a hand-edited dump, could overflow the entry count
+                let Some(entries) = ways.checked_mul(registers.ecx) else {
+                    log::debug!("Skipping leaf 0x18 TLB with {} ways and {} sets", ways, registers.ecx);
+                    return None;
+                };
+
+                Some(CpuIdTlb {
+                    level: ((registers.edx >> 5) & 0x7) as u8,
+                    kind,
+                    page_sizes,
+                    entries,
+                    ways: (!fully_associative).then_some(ways),
+                })
+            })
+            .collect()
+    }
+
+    /// TLBs from the leaf 2 descriptors.
+    fn descriptor_tlbs(descriptors: &[u8]) -> Vec<CpuIdTlb> {
+        TLB_DESCRIPTORS
+            .iter()
+            .filter(|(descriptor, ..)| descriptors.contains(descriptor))
+            .map(|(_, level, kind, page_sizes, entries, ways)| CpuIdTlb {
+                level: *level,
+                kind: *kind,
+                page_sizes: page_sizes.to_vec(),
+                entries: *entries,
+                ways: *ways,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * 1024;
+
+    /// `cpuid -r` of a Core i9-12900K (Alder Lake, hybrid) on bare metal.
+    const ALDER_LAKE: &str = "\
+CPU 0:
+   0x00000000 0x00: eax=0x00000020 ebx=0x756e6547 ecx=0x6c65746e edx=0x49656e69
+   0x00000001 0x00: eax=0x00090672 ebx=0x00800800 ecx=0x7ffafbff edx=0xbfebfbff
+   0x00000002 0x00: eax=0x00feff01 ebx=0x000000f0 ecx=0x00000000 edx=0x00000000
+   0x00000004 0x00: eax=0xfc004121 ebx=0x02c0003f ecx=0x0000003f edx=0x00000000
+   0x00000004 0x01: eax=0xfc004122 ebx=0x01c0003f ecx=0x0000003f edx=0x00000000
+   0x00000004 0x02: eax=0xfc004143 ebx=0x0240003f ecx=0x000007ff edx=0x00000000
+   0x00000004 0x03: eax=0xfc07c163 ebx=0x02c0003f ecx=0x00009fff edx=0x00000004
+   0x00000004 0x04: eax=0x00000000 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+   0x00000007 0x00: eax=0x00000000 ebx=0x239c27eb ecx=0x98c027ac edx=0xfc1cc410
+   0x00000018 0x00: eax=0x00000006 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+   0x00000018 0x01: eax=0x00000000 ebx=0x00080007 ecx=0x00000020 edx=0x00004022
+   0x00000018 0x02: eax=0x00000000 ebx=0x00040001 ecx=0x00000020 edx=0x00004021
+   0x00000018 0x03: eax=0x00000000 ebx=0x00040006 ecx=0x00000008 edx=0x00004021
+   0x00000018 0x04: eax=0x00000000 ebx=0x00100008 ecx=0x00000001 edx=0x00004121
+   0x00000018 0x05: eax=0x00000000 ebx=0x0010000f ecx=0x00000001 edx=0x00004124
+   0x00000018 0x06: eax=0x00000000 ebx=0x00080007 ecx=0x00000080 edx=0x00004043
+   0x80000000 0x00: eax=0x80000008 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+   0x80000001 0x00: eax=0x00000000 ebx=0x00000000 ecx=0x00000121 edx=0x2c100800
+   0x80000002 0x00: eax=0x68743231 ebx=0x6e654720 ecx=0x746e4920 edx=0x52286c65
+   0x80000003 0x00: eax=0x6f432029 ebx=0x54286572 ecx=0x6920294d edx=0x32312d39
+   0x80000004 0x00: eax=0x4b303039 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+";
+
+    /// `cpuid -r` of a Ryzen 7 7800X3D (Zen 4) in a KVM guest.
+    const ZEN_4: &str = "\
+CPU 0:
+   0x00000000 0x00: eax=0x00000010 ebx=0x68747541 ecx=0x444d4163 edx=0x69746e65
+   0x00000001 0x00: eax=0x00a60f12 ebx=0x00100800 ecx=0xfef8320b edx=0x178bfbff
+   0x00000007 0x00: eax=0x00000000 ebx=0xf1bf97a9 ecx=0x00405fce edx=0x10000010
+   0x40000000 0x00: eax=0x40000001 ebx=0x4b4d564b ecx=0x564b4d56 edx=0x0000004d
+   0x80000000 0x00: eax=0x80000028 ebx=0x68747541 ecx=0x444d4163 edx=0x69746e65
+   0x80000001 0x00: eax=0x00a60f12 ebx=0x00000000 ecx=0x75c237ff edx=0x2fd3fbff
+   0x80000002 0x00: eax=0x20444d41 ebx=0x657a7952 ecx=0x2037206e edx=0x30303837
+   0x80000003 0x00: eax=0x20443358 ebx=0x6f432d38 ecx=0x50206572 edx=0x65636f72
+   0x80000004 0x00: eax=0x726f7373 ebx=0x20202020 ecx=0x20202020 edx=0x00202020
+   0x80000005 0x00: eax=0xff48ff40 ebx=0xff48ff40 ecx=0x20080140 edx=0x20080140
+   0x80000006 0x00: eax=0x6c002200 ebx=0x6c004200 ecx=0x04006140 edx=0x03009140
+   0x8000001d 0x00: eax=0x00004121 ebx=0x01c0003f ecx=0x0000003f edx=0x00000000
+   0x8000001d 0x01: eax=0x00004122 ebx=0x01c0003f ecx=0x0000003f edx=0x00000000
+   0x8000001d 0x02: eax=0x00004143 ebx=0x01c0003f ecx=0x000007ff edx=0x00000002
+   0x8000001d 0x03: eax=0x0003c163 ebx=0x03c0003f ecx=0x00017fff edx=0x00000001
+   0x8000001d 0x04: eax=0x00000000 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+CPU 1:
+   0x00000000 0x00: eax=0x00000000 ebx=0x00000000 ecx=0x00000000 edx=0x00000000
+";
+
+    fn cache(level: u8, kind: CacheKind, size_bytes: u64, ways: u32, shared_by_threads: u32) -> CpuIdCache {
+        CpuIdCache { level, kind, size_bytes, ways: Some(ways), line_bytes: 64, shared_by_threads: Some(shared_by_threads) }
+    }
+
+    fn tlb(level: u8, kind: TlbKind, page_sizes: &[u64], entries: u32, ways: Option<u32>) -> CpuIdTlb {
+        CpuIdTlb { level, kind, page_sizes: page_sizes.to_vec(), entries, ways }
+    }
+
+    #[test]
+    fn intel_hybrid() {
+        let info = CpuIdInfo::decode(&CpuIdDump::parse(ALDER_LAKE));
+
+        assert_eq!(info.vendor, "GenuineIntel");
+        assert_eq!((info.family, info.model, info.stepping), (6, 0x97, 2));
+        assert_eq!(info.brand.as_deref(), Some("12th Gen Intel(R) Core(TM) i9-12900K"));
+        assert_eq!((info.max_leaf, info.max_extended_leaf), (0x20, 0x8000_0008));
+        assert!(!info.hypervisor);
+        assert_eq!(info.hypervisor_vendor, None);
+
+        for feature in ["sse4_2", "avx2", "sha_ni", "hybrid_cpu", "lm"] {
+            assert!(info.has_feature(feature), "{}", feature);
+        }
+        assert!(!info.has_feature("avx512f"));
+        assert!(!info.has_feature("topoext"));
+
+        assert_eq!(info.descriptors, vec![0xFF, 0xFE, 0xF0]);
+        assert_eq!(info.caches, vec![
+            cache(1, CacheKind::Data, 48 * KIB, 12, 2),
+            cache(1, CacheKind::Instruction, 32 * KIB, 8, 2),
+            cache(2, CacheKind::Unified, 1280 * KIB, 10, 2),
+            cache(3, CacheKind::Unified, 30 * MIB, 12, 32),
+        ]);
+
+        // Descriptor 0xFE points to leaf 0x18, whose sub-leaf 0 holds no TLB
+        assert_eq!(info.tlbs, vec![
+            tlb(1, TlbKind::Instruction, &[KIB_4, MIB_2, MIB_4], 256, Some(8)),
+            tlb(1, TlbKind::Data, &[KIB_4], 128, Some(4)),
+            tlb(1, TlbKind::Data, &[MIB_2, MIB_4], 32, Some(4)),
+            tlb(1, TlbKind::Data, &[GIB_1], 16, None),
+            tlb(1, TlbKind::Data, &[KIB_4, MIB_2, MIB_4, GIB_1], 16, None),
+            tlb(2, TlbKind::Unified, &[KIB_4, MIB_2, MIB_4], 1024, Some(8)),
+        ]);
+    }
+
+    #[test]
+    fn amd_zen() {
+        let info = CpuIdInfo::decode(&CpuIdDump::parse(ZEN_4));
+
+        assert_eq!(info.vendor, "AuthenticAMD");
+        assert_eq!((info.family, info.model, info.stepping), (0x19, 0x61, 2));
+        assert_eq!(info.brand.as_deref(), Some("AMD Ryzen 7 7800X3D 8-Core Processor"));
+        assert_eq!((info.max_leaf, info.max_extended_leaf), (0x10, 0x8000_0028));
+        assert!(info.hypervisor);
+        assert_eq!(info.hypervisor_vendor.as_deref(), Some("KVMKVMKVM"));
+
+        for feature in ["avx2", "avx512f", "topoext", "svm", "hypervisor"] {
+            assert!(info.has_feature(feature), "{}", feature);
+        }
+        assert!(!info.has_feature("hybrid_cpu"));
+
+        // AMD has no leaf 2 and reports its caches in 0x8000001D with topoext
+        assert!(info.descriptors.is_empty());
+        assert_eq!(info.caches, vec![
+            cache(1, CacheKind::Data, 32 * KIB, 8, 2),
+            cache(1, CacheKind::Instruction, 32 * KIB, 8, 2),
+            cache(2, CacheKind::Unified, MIB, 8, 2),
+            cache(3, CacheKind::Unified, 96 * MIB, 16, 16),
+        ]);
+
+        assert_eq!(info.tlbs, vec![
+            tlb(1, TlbKind::Data, &[KIB_4], 72, None),
+            tlb(1, TlbKind::Instruction, &[KIB_4], 64, None),
+            tlb(1, TlbKind::Data, &[MIB_2, MIB_4], 72, None),
+            tlb(1, TlbKind::Instruction, &[MIB_2, MIB_4], 64, None),
+            tlb(2, TlbKind::Data, &[KIB_4], 3072, Some(8)),
+            tlb(2, TlbKind::Instruction, &[KIB_4], 512, Some(4)),
+            tlb(2, TlbKind::Data, &[MIB_2, MIB_4], 3072, Some(8)),
+            tlb(2, TlbKind::Instruction, &[MIB_2, MIB_4], 512, Some(2)),
+        ]);
+    }
+
+    #[test]
+    fn amd_without_topoext() {
+        let mut dump = CpuIdDump::parse(ZEN_4);
+        let ext = dump.get(0x8000_0001, 0);
+        dump.insert(0x8000_0001, 0, CpuIdRegisters { ecx: ext.ecx & !(1 << 22), ..ext });
+
+        // The L3 associativity 0x9 means "see leaf 0x8000001D", so the legacy leaves have no L3
+        assert_eq!(CpuIdInfo::decode(&dump).caches, vec![
+            CpuIdCache { level: 1, kind: CacheKind::Data, size_bytes: 32 * KIB, ways: Some(8), line_bytes: 64, shared_by_threads: None },
+            CpuIdCache { level: 1, kind: CacheKind::Instruction, size_bytes: 32 * KIB, ways: Some(8), line_bytes: 64, shared_by_threads: None },
+            CpuIdCache { level: 2, kind: CacheKind::Unified, size_bytes: MIB, ways: Some(8), line_bytes: 64, shared_by_threads: None },
+        ]);
+    }
+
+    #[test]
+    fn overflowing_tlb_is_skipped() {
+        let mut dump = CpuIdDump::default();
+        dump.insert(0, 0, CpuIdRegisters { eax: 0x18, ebx: 0x756e6547, ecx: 0x6c65746e, edx: 0x49656e69 });
+        dump.insert(2, 0, CpuIdRegisters { eax: 0x00fe0001, ..Default::default() });
+        dump.insert(0x18, 0, CpuIdRegisters { eax: 2, ..Default::default() });
+        dump.insert(0x18, 1, CpuIdRegisters { eax: 0, ebx: 0xffff0001, ecx: 0x0010_0000, edx: 0x21 });
+        dump.insert(0x18, 2, CpuIdRegisters { eax: 0, ebx: 0x00040001, ecx: 0x10, edx: 0x21 });
+
+        assert_eq!(CpuIdInfo::decode(&dump).tlbs, vec![tlb(1, TlbKind::Data, &[KIB_4], 64, Some(4))]);
+    }
+
+    #[test]
+    fn descriptor_tlbs() {
+        let mut dump = CpuIdDump::default();
+        dump.insert(0, 0, CpuIdRegisters { eax: 2, ebx: 0x756e6547, ecx: 0x6c65746e, edx: 0x49656e69 });
+        // Bit 31 of EDX marks a register without descriptors
+        dump.insert(2, 0, CpuIdRegisters { eax: 0x00b0_5b01, ebx: 0, ecx: 0x0000_00c1, edx: 0x8000_00ff });
+
+        let info = CpuIdInfo::decode(&dump);
+        assert_eq!(info.descriptors, vec![0x5B, 0xB0, 0xC1]);
+        assert_eq!(info.tlbs, vec![
+            tlb(1, TlbKind::Data, &[KIB_4, MIB_4], 64, None),
+            tlb(1, TlbKind::Instruction, &[KIB_4], 128, Some(4)),
+            tlb(2, TlbKind::Unified, &[KIB_4, MIB_2], 1024, Some(8)),
+        ]);
+    }
+
+    #[test]
+    fn dump_round_trip() {
+        let dump = CpuIdDump::parse(ZEN_4);
+
+        // Only the first CPU is read
+        assert_eq!(dump.get(0, 0).eax, 0x10);
+        assert_eq!(CpuIdDump::parse(&dump.to_string()), dump);
+
+        // Recording the leaves of the dump decodes to the same CPU
+        for dump in [dump, CpuIdDump::parse(ALDER_LAKE)] {
+            let recorded = CpuIdDump::record(|leaf, subleaf| dump.get(leaf, subleaf));
+            assert_eq!(CpuIdInfo::decode(&recorded), CpuIdInfo::decode(&dump));
+        }
+    }
+}
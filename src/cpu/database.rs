@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+use rusqlite::Connection;
+use crate::cpu::amd::AMDData;
+use crate::cpu::import::ImportReport;
+use crate::cpu::intel::IntelData;
+use crate::cpu::metadata::{DatabaseMetadata, SCHEMA_VERSION};
+use crate::cpu::overlay::{self, Overlay};
+use crate::cpu::private::Database;
+use crate::cpu::search;
+use crate::cpu::spec::Vendor;
+
+/// Environment variable holding the path of the CPU database file.
+pub const DATABASE_ENV: &str = "HWISAK_CPU_DB";
+
+/// Where a [`CpuDatabase`] keeps its data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseLocation {
+    File(PathBuf),
+    InMemory,
+}
+
+/// Handle to the CPU database. Every lookup (`Database::fetch_in`, `CpuQuery::fetch_in`, the matcher)
+/// goes through one of these.
+///
+/// Newly created databases are filled with the CPU data embedded into the library, so any location
+/// works without the `res/cpu` submodules. Existing databases generated from other CSV files, with
+/// another schema version or by another crate version are regenerated when opened, see
+/// [`CpuDatabase::metadata`].
+pub struct CpuDatabase {
+    conn: Connection,
+    location: DatabaseLocation,
+}
+
+impl std::fmt::Debug for CpuDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CpuDatabase")
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl CpuDatabase {
+    /// Opens the database file at `path`, creating and filling it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                log::warn!("Unable to create {}: {}", parent.display(), e);
+                rusqlite::Error::InvalidPath(path.to_path_buf())
+            })?;
+        }
+
+        let conn = Connection::open(path)?;
+        Self::init(conn, DatabaseLocation::File(path.to_path_buf()))
+    }
+
+    /// Opens the database file named by the `HWISAK_CPU_DB` environment variable, if it is set.
+    pub fn from_env() -> Result<Option<Self>, rusqlite::Error> {
+        match std::env::var_os(DATABASE_ENV) {
+            Some(path) if !path.is_empty() => Self::open(PathBuf::from(path)).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The database file in the user cache directory: `$XDG_CACHE_HOME/hwisak/cpu.db`, falling
+    /// back to `~/.cache/hwisak/cpu.db` (or `%LOCALAPPDATA%\hwisak\cpu.db` on Windows).
+    pub fn cache_dir_path() -> Option<PathBuf> {
+        let non_empty = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+        let cache_dir = non_empty("XDG_CACHE_HOME")
+            .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+            .or_else(|| non_empty("LOCALAPPDATA"))?;
+
+        Some(cache_dir.join("hwisak").join("cpu.db"))
+    }
+
+    /// Opens the database file in the user cache directory, see [`CpuDatabase::cache_dir_path`].
+    pub fn open_cache_dir() -> Result<Self, rusqlite::Error> {
+        match Self::cache_dir_path() {
+            Some(path) => Self::open(path),
+            None => Err(rusqlite::Error::InvalidPath(PathBuf::from("~/.cache/hwisak/cpu.db"))),
+        }
+    }
+
+    /// Builds the database fully in memory. Nothing is written to disk.
+    pub fn in_memory() -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open_in_memory()?;
+        Self::init(conn, DatabaseLocation::InMemory)
+    }
+
+    /// Opens the database named by `HWISAK_CPU_DB` if set, otherwise the one in the user cache
+    /// directory. Falls back to an in-memory database if the cache directory is not writable.
+    pub fn open_default() -> Result<Self, rusqlite::Error> {
+        if let Some(db) = Self::from_env()? {
+            return Ok(db);
+        }
+
+        match Self::open_cache_dir() {
+            Ok(db) => Ok(db),
+            Err(err) => {
+                log::warn!("Unable to open the cached CPU database ({}), using an in-memory one", err);
+                Self::in_memory()
+            }
+        }
+    }
+
+    pub fn location(&self) -> &DatabaseLocation {
+        &self.location
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub(crate) fn connection_mut(&mut self) -> &mut Connection {
+        &mut self.conn
+    }
+
+    /// Reads the schema version and provenance of the data in this database.
+    pub fn metadata(&self) -> Result<DatabaseMetadata, rusqlite::Error> {
+        DatabaseMetadata::read(&self.conn)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    /// Registers the `vendor` CPU records of a user CSV file, or a JSON file if the extension is
+    /// `.json`, e.g. for engineering samples or OEM-only SKUs. CSV files use the headers of the
+    /// upstream dataset of the vendor, JSON files are an array of objects keyed by those headers.
+    ///
+    /// The values of the records replace those of database records with the same name, columns the
+    /// file leaves empty keep the database values. Every replaced record is reported as a
+    /// collision. The records are marked as `user_provided` and are kept when the database is
+    /// regenerated. Adding the same path again replaces its records.
+    pub fn add_overlay(&mut self, vendor: Vendor, path: impl AsRef<Path>) -> Result<ImportReport, rusqlite::Error> {
+        let mut report = ImportReport::default();
+        let Some(source) = overlay::read_file(path.as_ref(), &mut report)? else {
+            return Ok(report);
+        };
+
+        // Reimporting the vendor merges the overlay over the upstream rows and the earlier overlays
+        overlay::store(&self.conn, vendor, &source)?;
+        let mut imported = match vendor {
+            Vendor::Intel => IntelData::reimport(&mut self.conn)?,
+            Vendor::AMD => AMDData::reimport(&mut self.conn)?,
+        };
+        imported.retain_file(&source.path);
+        report.merge(imported);
+        search::rebuild_index(&self.conn)?;
+
+        Ok(report)
+    }
+
+    /// Removes the records of the overlay registered from `path`, restoring the records they
+    /// replaced. Returns `false` if no overlay was registered from `path`.
+    pub fn remove_overlay(&mut self, path: impl AsRef<Path>) -> Result<bool, rusqlite::Error> {
+        let path = path.as_ref().display().to_string();
+        let removed = match overlay::remove(&self.conn, &path)? {
+            Some(Vendor::Intel) => IntelData::reimport(&mut self.conn).map(|_| true)?,
+            Some(Vendor::AMD) => AMDData::reimport(&mut self.conn).map(|_| true)?,
+            None => false,
+        };
+
+        if removed {
+            search::rebuild_index(&self.conn)?;
+        }
+        Ok(removed)
+    }
+
+    /// The user files registered with [`CpuDatabase::add_overlay`], in the order they were added.
+    pub fn overlays(&self) -> Result<Vec<Overlay>, rusqlite::Error> {
+        overlay::list(&self.conn)
+    }
+
+    /// Creates the tables with the current schema and regenerates the data if it was built from
+    /// other CSV files or by another version of the crate. Databases from before the typed schema
+    /// have no metadata and are rebuilt from scratch, their rows are not migrated.
+    fn init(conn: Connection, location: DatabaseLocation) -> Result<Self, rusqlite::Error> {
+        let mut db = Self { conn, location };
+        log::debug!("Opening CPU database at {:?}", db.location);
+
+        let stored = DatabaseMetadata::read(&db.conn)?;
+        let stale_reason = match &stored {
+            Some(metadata) => metadata.stale_reason(),
+            None => Some("no metadata".to_string()),
+        };
+
+        // Tables of an older typed schema can't be migrated in place, the data is reimported anyway
+        if stored.as_ref().is_some_and(|m| m.schema_version != SCHEMA_VERSION) {
+            IntelData::drop_table(&db.conn)?;
+            AMDData::drop_table(&db.conn)?;
+        }
+
+        IntelData::prepare_schema(&db.conn)?;
+        AMDData::prepare_schema(&db.conn)?;
+
+        if let Some(reason) = stale_reason {
+            log::info!("Generating CPU database at {:?} ({})", db.location, reason);
+            let mut report = IntelData::reimport(&mut db.conn)?;
+            report.merge(AMDData::reimport(&mut db.conn)?);
+            search::rebuild_index(&db.conn)?;
+            DatabaseMetadata::write_current(&mut db.conn)?;
+
+            log::info!(
+                "Imported {} CPU(s): {} row(s) skipped, {} value(s) not parsed, {} duplicate(s), {} change(s) between snapshots",
+                report.rows_imported(),
+                report.skipped.len(),
+                report.parse_failures.len(),
+                report.collisions.len(),
+                report.history.len()
+            );
+        }
+
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[test]
+    fn legacy_text_tables_are_rebuilt() {
+        let dir = TempDir::new();
+        let path = dir.path().join("cpu.db");
+        {
+            // The all-TEXT schema of databases generated before the typed columns
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE intel_cpus (
+                    product TEXT PRIMARY KEY, status TEXT, release_date TEXT, code_name TEXT,
+                    cores TEXT, threads TEXT, lithography TEXT, max_turbo_freq TEXT, base_freq TEXT,
+                    thermal_design_power TEXT, cache TEXT, cache_info TEXT, max_memory_size TEXT,
+                    memory_types TEXT, max_memory_speed TEXT, graphics TEXT
+                );
+                INSERT INTO intel_cpus (product, cores) VALUES ('Legacy CPU', '24');
+                CREATE TABLE amd_cpus (name TEXT PRIMARY KEY, cores TEXT);"
+            ).unwrap();
+
+            // Compared as text, '24' sorts before '8'
+            let count: u32 = conn.query_row("SELECT COUNT(*) FROM intel_cpus WHERE cores > 8", [], |row| row.get(0)).unwrap();
+            assert_eq!(count, 0);
+        }
+
+        let db = CpuDatabase::open(&path).unwrap();
+        let conn = db.connection();
+        for table in ["intel_cpus", "amd_cpus"] {
+            let cores_type: String = conn
+                .query_row("SELECT type FROM pragma_table_info(?1) WHERE name = 'cores'", [table], |row| row.get(0))
+                .unwrap();
+            assert_eq!(cores_type, "INTEGER", "{}", table);
+        }
+
+        let many_cores = |table: &str, name: &str| -> Vec<String> {
+            let mut stmt = conn.prepare(&format!("SELECT {} FROM {} WHERE cores > 8", name, table)).unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<Result<_, _>>().unwrap()
+        };
+        let intel = many_cores("intel_cpus", "product");
+        assert!(intel.contains(&"Core i9-13900K".to_string()));
+        assert!(!intel.contains(&"Legacy CPU".to_string()));
+        assert!(many_cores("amd_cpus", "name").iter().any(|name| name.contains("7950X")));
+
+        assert!(db.metadata().unwrap().stale_reason().is_none());
+    }
+}
@@ -0,0 +1,10 @@
+//! CPU datasets embedded at compile time by `build.rs`.
+
+/// A CSV file from the `res/cpu` submodules, embedded into the library.
+pub struct EmbeddedSource {
+    /// Path relative to the crate root, e.g. `res/cpu/amd/AMDCpuData/amdProcessorInformation_4-12-24.csv`
+    pub path: &'static str,
+    pub contents: &'static [u8],
+}
+
+include!(concat!(env!("OUT_DIR"), "/embedded_sources.rs"));
@@ -0,0 +1,379 @@
+//! Exports CPUs to CSV, JSON Lines or an SQL dump, for spreadsheets, notebooks and other databases.
+//!
+//! Both vendors are exported with the same normalized columns, see [`COLUMNS`], taken from
+//! [`CpuSpec`].
+
+use std::io::{self, Write};
+use std::path::Path;
+use csv::WriterBuilder;
+use serde_json::{Map, Value};
+use crate::cpu::amd::AMDData;
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::intel::IntelData;
+use crate::cpu::query::{CpuQuery, Queryable};
+use crate::cpu::spec::{CpuSpec, Vendor};
+
+/// Name of the table created by [`ExportFormat::Sql`].
+pub const SQL_TABLE: &str = "cpus";
+
+/// The exported columns and their SQL types, which SQLite and PostgreSQL both understand.
+pub const COLUMNS: &[(&str, &str)] = &[
+    ("vendor", "TEXT"),
+    ("name", "TEXT"),
+    ("cores", "INTEGER"),
+    ("threads", "INTEGER"),
+    ("base_clock_mhz", "INTEGER"),
+    ("boost_clock_mhz", "INTEGER"),
+    ("l1_cache_bytes", "BIGINT"),
+    ("l2_cache_bytes", "BIGINT"),
+    ("l3_cache_bytes", "BIGINT"),
+    ("default_tdp_w", "REAL"),
+    ("ctdp_min_w", "REAL"),
+    ("ctdp_max_w", "REAL"),
+    ("lithography_nm", "INTEGER"),
+    ("socket", "TEXT"),
+    ("launch_date", "TEXT"),
+    ("launch_key", "INTEGER"),
+    ("memory_types", "TEXT"),
+    ("max_memory_speed_mts", "INTEGER"),
+    ("memory_channels", "INTEGER"),
+    ("max_memory_bytes", "BIGINT"),
+    ("integrated_graphics", "TEXT"),
+    ("user_provided", "BOOLEAN"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// A header row followed by one row per CPU, missing values are empty
+    Csv,
+    /// One JSON object per line, missing values are `null`
+    JsonLines,
+    /// A `CREATE TABLE` statement followed by one `INSERT` per CPU, in a transaction
+    Sql,
+}
+
+impl ExportFormat {
+    /// The format for a file name, from its extension: `.csv`, `.jsonl`/`.ndjson` or `.sql`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Some(ExportFormat::JsonLines),
+            "sql" => Some(ExportFormat::Sql),
+            _ => None,
+        }
+    }
+}
+
+/// A value of an exported column.
+enum Field {
+    Null,
+    Integer(u64),
+    Real(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl Field {
+    fn csv(&self) -> String {
+        match self {
+            Field::Null => String::new(),
+            Field::Integer(n) => n.to_string(),
+            Field::Real(n) => n.to_string(),
+            Field::Text(s) => s.clone(),
+            Field::Bool(b) => b.to_string(),
+        }
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            Field::Null => Value::Null,
+            Field::Integer(n) => Value::from(*n),
+            Field::Real(n) => Value::from(*n),
+            Field::Text(s) => Value::from(s.as_str()),
+            Field::Bool(b) => Value::from(*b),
+        }
+    }
+
+    fn sql(&self) -> String {
+        match self {
+            Field::Null => "NULL".to_string(),
+            Field::Integer(n) => n.to_string(),
+            Field::Real(n) => n.to_string(),
+            Field::Text(s) => format!("'{}'", s.replace('\'', "''")),
+            Field::Bool(true) => "TRUE".to_string(),
+            Field::Bool(false) => "FALSE".to_string(),
+        }
+    }
+}
+
+impl<T: Into<u64>> From<Option<T>> for Field {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Field::Null, |n| Field::Integer(n.into()))
+    }
+}
+
+fn integer(value: Option<usize>) -> Field {
+    value.map_or(Field::Null, |n| Field::Integer(n as u64))
+}
+
+/// Watts rounded to hundredths, so `f32` values like 4.2 are not written as 4.199999809265137.
+fn watts(value: Option<f32>) -> Field {
+    value.map_or(Field::Null, |w| Field::Real((w as f64 * 100.0).round() / 100.0))
+}
+
+fn text(value: Option<&str>) -> Field {
+    value.map_or(Field::Null, |s| Field::Text(s.to_string()))
+}
+
+/// The values of `spec` in the order of [`COLUMNS`].
+fn fields(spec: &CpuSpec) -> Vec<Field> {
+    let memory_types = spec.memory.iter().map(|support| support.kind.as_str()).collect::<Vec<_>>();
+
+    vec![
+        Field::Text(match spec.vendor {
+            Vendor::Intel => "Intel".to_string(),
+            Vendor::AMD => "AMD".to_string(),
+        }),
+        Field::Text(spec.name.clone()),
+        integer(spec.cores),
+        integer(spec.threads),
+        integer(spec.base_clock_mhz),
+        integer(spec.boost_clock_mhz),
+        spec.l1_cache_bytes.into(),
+        spec.l2_cache_bytes.into(),
+        spec.l3_cache_bytes.into(),
+        watts(spec.power.default_w),
+        watts(spec.power.ctdp_min_w),
+        watts(spec.power.ctdp_max_w),
+        integer(spec.lithography_nm),
+        text(spec.socket.as_deref()),
+        spec.launch_date.map_or(Field::Null, |date| Field::Text(date.to_string())),
+        spec.launch_date.map(|date| date.key()).into(),
+        if memory_types.is_empty() { Field::Null } else { Field::Text(memory_types.join(", ")) },
+        spec.max_memory_speed_mts().into(),
+        spec.memory.iter().filter_map(|support| support.channels).max().into(),
+        spec.memory.iter().filter_map(|support| support.max_capacity_bytes).max().into(),
+        text(spec.integrated_graphics.as_deref()),
+        Field::Bool(spec.user_provided),
+    ]
+}
+
+/// Writes `specs` to `writer` in `format` and returns how many were written.
+pub fn write_specs<'a, W: Write>(
+    specs: impl IntoIterator<Item = &'a CpuSpec>,
+    format: ExportFormat,
+    mut writer: W,
+) -> io::Result<usize> {
+    let mut count = 0;
+
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = WriterBuilder::new().from_writer(writer);
+            csv.write_record(COLUMNS.iter().map(|(name, _)| name))?;
+            for spec in specs {
+                csv.write_record(fields(spec).iter().map(Field::csv))?;
+                count += 1;
+            }
+            csv.flush()?;
+        }
+        ExportFormat::JsonLines => {
+            for spec in specs {
+                let object = COLUMNS
+                    .iter()
+                    .zip(fields(spec))
+                    .map(|((name, _), field)| (name.to_string(), field.json()))
+                    .collect::<Map<_, _>>();
+                serde_json::to_writer(&mut writer, &object)?;
+                writeln!(writer)?;
+                count += 1;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Sql => {
+            let names = COLUMNS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ");
+            let definitions = COLUMNS
+                .iter()
+                .map(|(name, sql_type)| format!("    {} {}", name, sql_type))
+                .collect::<Vec<_>>()
+                .join(",\n");
+
+            writeln!(writer, "BEGIN;")?;
+            writeln!(writer, "CREATE TABLE {} (\n{}\n);", SQL_TABLE, definitions)?;
+            for spec in specs {
+                let values = fields(spec).iter().map(Field::sql).collect::<Vec<_>>().join(", ");
+                writeln!(writer, "INSERT INTO {} ({}) VALUES ({});", SQL_TABLE, names, values)?;
+                count += 1;
+            }
+            writeln!(writer, "COMMIT;")?;
+            writer.flush()?;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Writes every CPU of the default [`CpuDatabase`] to `writer`, Intel first, and returns how many
+/// were written.
+pub fn export_database<W: Write>(format: ExportFormat, writer: W) -> io::Result<usize> {
+    export_database_in(&CpuDatabase::open_default().map_err(io::Error::other)?, format, writer)
+}
+
+/// Like [`export_database`], but reads `db`.
+pub fn export_database_in<W: Write>(db: &CpuDatabase, format: ExportFormat, writer: W) -> io::Result<usize> {
+    let mut specs = CpuQuery::<IntelData>::new()
+        .fetch_in(db)
+        .map_err(io::Error::other)?
+        .iter()
+        .map(CpuSpec::from)
+        .collect::<Vec<_>>();
+    specs.extend(
+        CpuQuery::<AMDData>::new()
+            .fetch_in(db)
+            .map_err(io::Error::other)?
+            .iter()
+            .map(CpuSpec::from),
+    );
+
+    write_specs(&specs, format, writer)
+}
+
+impl<T: Queryable> CpuQuery<T>
+where
+    for<'a> CpuSpec: From<&'a T>,
+{
+    /// Runs the query against `db` and writes the matching CPUs to `writer`, see [`write_specs`].
+    pub fn export_in<W: Write>(self, db: &CpuDatabase, format: ExportFormat, writer: W) -> io::Result<usize> {
+        let specs = self.fetch_in(db).map_err(io::Error::other)?;
+        write_specs(&specs.iter().map(CpuSpec::from).collect::<Vec<_>>(), format, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::launch::LaunchDate;
+    use crate::cpu::memory::{MemoryKind, MemorySupport};
+    use crate::cpu::power::PowerSpec;
+
+    /// A spec with a name that needs quoting and every optional field empty.
+    fn sparse() -> CpuSpec {
+        CpuSpec {
+            vendor: Vendor::Intel,
+            name: "Core i9-13900K, \"Raptor Lake\" O'Brien".to_string(),
+            cores: None,
+            threads: None,
+            base_clock_mhz: None,
+            boost_clock_mhz: None,
+            l1_cache_bytes: None,
+            l2_cache_bytes: None,
+            l3_cache_bytes: None,
+            caches: Vec::new(),
+            power: PowerSpec::default(),
+            lithography_nm: None,
+            socket: None,
+            launch_date: None,
+            memory: Vec::new(),
+            integrated_graphics: None,
+            user_provided: false,
+        }
+    }
+
+    fn full() -> CpuSpec {
+        CpuSpec {
+            vendor: Vendor::AMD,
+            name: "AMD Ryzen™ 7 7800X3D".to_string(),
+            cores: Some(8),
+            threads: Some(16),
+            base_clock_mhz: Some(4200),
+            boost_clock_mhz: Some(5000),
+            l1_cache_bytes: Some(512 * 1024),
+            l2_cache_bytes: Some(8 * 1024 * 1024),
+            l3_cache_bytes: Some(96 * 1024 * 1024),
+            caches: Vec::new(),
+            power: PowerSpec { default_w: Some(120.0), ctdp_min_w: Some(4.2), ctdp_max_w: None },
+            lithography_nm: Some(5),
+            socket: Some("AM5".to_string()),
+            launch_date: LaunchDate::month(2023, 4),
+            memory: vec![MemorySupport { kind: MemoryKind::DDR5, max_mts: Some(5200), channels: Some(2), max_capacity_bytes: None, ecc: None }],
+            integrated_graphics: Some("AMD Radeon™ Graphics".to_string()),
+            user_provided: true,
+        }
+    }
+
+    fn export(format: ExportFormat) -> String {
+        let mut out = Vec::new();
+        assert_eq!(write_specs(&[sparse(), full()], format, &mut out).unwrap(), 2);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats_from_paths() {
+        assert_eq!(ExportFormat::from_path("cpus.CSV"), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_path("out/cpus.ndjson"), Some(ExportFormat::JsonLines));
+        assert_eq!(ExportFormat::from_path("cpus.jsonl"), Some(ExportFormat::JsonLines));
+        assert_eq!(ExportFormat::from_path("cpus.sql"), Some(ExportFormat::Sql));
+        assert_eq!(ExportFormat::from_path("cpus.txt"), None);
+        assert_eq!(ExportFormat::from_path("cpus"), None);
+    }
+
+    #[test]
+    fn csv() {
+        let csv = export(ExportFormat::Csv);
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+
+        let headers = reader.headers().unwrap().iter().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(headers, COLUMNS.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>());
+
+        let records = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records.len(), 2);
+
+        // Quoted commas and quotes read back unchanged, missing values are empty
+        assert_eq!(&records[0][1], "Core i9-13900K, \"Raptor Lake\" O'Brien");
+        assert!(records[0].iter().skip(2).take(COLUMNS.len() - 3).all(str::is_empty));
+        assert_eq!(&records[0][COLUMNS.len() - 1], "false");
+
+        assert_eq!(&records[1][0], "AMD");
+        assert_eq!(&records[1][10], "4.2");
+        assert_eq!(&records[1][14], "2023-04");
+        assert_eq!(&records[1][16], "DDR5");
+    }
+
+    #[test]
+    fn json_lines() {
+        let json = export(ExportFormat::JsonLines);
+        let lines = json.lines().map(|line| serde_json::from_str::<Value>(line).unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["name"], "Core i9-13900K, \"Raptor Lake\" O'Brien");
+        assert_eq!(lines[0]["cores"], Value::Null);
+        assert_eq!(lines[0]["socket"], Value::Null);
+        assert_eq!(lines[1]["cores"], 8);
+        assert_eq!(lines[1]["ctdp_min_w"], 4.2);
+        assert_eq!(lines[1]["user_provided"], true);
+    }
+
+    #[test]
+    fn sql_dump() {
+        let sql = export(ExportFormat::Sql);
+
+        assert!(sql.starts_with("BEGIN;\nCREATE TABLE cpus (\n    vendor TEXT,\n"));
+        assert!(sql.ends_with("COMMIT;\n"));
+        assert!(sql.contains("VALUES ('Intel', 'Core i9-13900K, \"Raptor Lake\" O''Brien', NULL, NULL,"));
+        assert!(sql.contains(", NULL, FALSE);\n"));
+        assert!(sql.contains("'AMD Radeon™ Graphics', TRUE);\n"));
+
+        // The dump runs as is and reads back the same values
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(&sql).unwrap();
+
+        let (name, cores): (String, Option<u32>) = conn
+            .query_row("SELECT name, cores FROM cpus WHERE vendor = 'Intel'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(name, "Core i9-13900K, \"Raptor Lake\" O'Brien");
+        assert_eq!(cores, None);
+
+        let count: u32 = conn.query_row("SELECT COUNT(*) FROM cpus WHERE user_provided", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,294 @@
+//! Per-core clock speeds, frequency limits and scaling settings, read from Linux cpufreq sysfs.
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::utils::{read_sysfs, read_sysfs_number, sysfs_cpus, SYSFS_CPU_DIR, SYSFS_ROOT};
+
+/// The clock speeds and scaling settings of a logical CPU. cpufreq reports kHz, the values are
+/// rounded down to MHz like the clock speeds of the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoreFrequency {
+    pub cpu: usize,
+    pub current_mhz: Option<u32>,
+    /// The lowest speed the governor may pick, `scaling_min_freq`
+    pub min_mhz: Option<u32>,
+    /// The highest speed the governor may pick, `scaling_max_freq`
+    pub max_mhz: Option<u32>,
+    /// The lowest speed of the hardware, `cpuinfo_min_freq`
+    pub hardware_min_mhz: Option<u32>,
+    /// The highest speed of the hardware including boost, `cpuinfo_max_freq`
+    pub hardware_max_mhz: Option<u32>,
+    /// The scaling driver, e.g. `"intel_pstate"`, `"amd-pstate-epp"` or `"acpi-cpufreq"`
+    pub driver: Option<String>,
+    /// The scaling governor, e.g. `"powersave"` or `"schedutil"`
+    pub governor: Option<String>,
+    pub available_governors: Vec<String>,
+    /// The energy performance preference of drivers that have one, e.g. `"balance_performance"`
+    pub energy_performance_preference: Option<String>,
+}
+
+/// The frequencies of every logical CPU with cpufreq support.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuFrequencies {
+    /// Sorted by CPU
+    pub cores: Vec<CoreFrequency>,
+    /// Whether boost (Turbo Boost, Core Performance Boost) is enabled, `None` if the driver does
+    /// not say
+    pub boost: Option<bool>,
+}
+
+fn read_mhz(path: impl AsRef<Path>) -> Option<u32> {
+    read_sysfs_number::<u32>(path).map(|khz| khz / 1000)
+}
+
+/// The current speed of the CPU whose cpufreq directory is `cpufreq`.
+fn current_mhz(cpufreq: &Path) -> Option<u32> {
+    read_mhz(cpufreq.join("scaling_cur_freq")).or_else(|| read_mhz(cpufreq.join("cpuinfo_cur_freq")))
+}
+
+/// Whether boost is enabled. `intel_pstate` reports it inverted as `no_turbo`, other drivers
+/// as `cpufreq/boost`.
+fn boost(cpu_dir: &Path) -> Option<bool> {
+    read_sysfs_number::<u8>(cpu_dir.join("intel_pstate/no_turbo"))
+        .map(|no_turbo| no_turbo == 0)
+        .or_else(|| read_sysfs_number::<u8>(cpu_dir.join("cpufreq/boost")).map(|boost| boost == 1))
+}
+
+/// The cpufreq directories of the logical CPUs below `sysfs`.
+fn cpufreq_dirs(sysfs: &Path) -> Vec<(usize, PathBuf)> {
+    sysfs_cpus(&sysfs.join(SYSFS_CPU_DIR))
+        .into_iter()
+        .map(|(cpu, path)| (cpu, path.join("cpufreq")))
+        .filter(|(_, cpufreq)| cpufreq.is_dir())
+        .collect()
+}
+
+impl CpuFrequencies {
+    /// Reads the frequencies from `/sys`, `None` if cpufreq is not available, e.g. in most
+    /// virtual machines or on other operating systems.
+    pub fn read() -> Option<Self> {
+        Self::read_from(SYSFS_ROOT)
+    }
+
+    /// Reads the frequencies from the sysfs tree at `sysfs`, e.g. a copy of `/sys` recorded on
+    /// another machine.
+    pub fn read_from(sysfs: impl AsRef<Path>) -> Option<Self> {
+        let sysfs = sysfs.as_ref();
+
+        let cores = cpufreq_dirs(sysfs)
+            .into_iter()
+            .map(|(cpu, cpufreq)| CoreFrequency {
+                cpu,
+                current_mhz: current_mhz(&cpufreq),
+                min_mhz: read_mhz(cpufreq.join("scaling_min_freq")),
+                max_mhz: read_mhz(cpufreq.join("scaling_max_freq")),
+                hardware_min_mhz: read_mhz(cpufreq.join("cpuinfo_min_freq")),
+                hardware_max_mhz: read_mhz(cpufreq.join("cpuinfo_max_freq")),
+                driver: read_sysfs(cpufreq.join("scaling_driver")),
+                governor: read_sysfs(cpufreq.join("scaling_governor")),
+                available_governors: read_sysfs(cpufreq.join("scaling_available_governors"))
+                    .map(|governors| governors.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default(),
+                energy_performance_preference: read_sysfs(cpufreq.join("energy_performance_preference")),
+            })
+            .collect::<Vec<_>>();
+
+        if cores.is_empty() {
+            return None;
+        }
+
+        Some(CpuFrequencies { cores, boost: boost(&sysfs.join(SYSFS_CPU_DIR)) })
+    }
+
+    /// The scaling driver of the first CPU, every CPU normally uses the same one.
+    pub fn driver(&self) -> Option<&str> {
+        self.cores.iter().find_map(|core| core.driver.as_deref())
+    }
+
+    /// The fastest current speed of any CPU.
+    pub fn max_current_mhz(&self) -> Option<u32> {
+        self.cores.iter().filter_map(|core| core.current_mhz).max()
+    }
+}
+
+/// The current speeds of the CPUs at one point in time, see [`FrequencySampler`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencySample {
+    /// Time since the sampler was created
+    pub elapsed: Duration,
+    /// The CPU and its speed in MHz, for every CPU whose speed could be read
+    pub mhz: Vec<(usize, u32)>,
+}
+
+impl FrequencySample {
+    pub fn average_mhz(&self) -> Option<f64> {
+        (!self.mhz.is_empty()).then(|| self.mhz.iter().map(|(_, mhz)| *mhz as f64).sum::<f64>() / self.mhz.len() as f64)
+    }
+
+    pub fn max_mhz(&self) -> Option<u32> {
+        self.mhz.iter().map(|(_, mhz)| *mhz).max()
+    }
+}
+
+/// Records the current speed of every CPU at a fixed interval. As an iterator, it returns the
+/// first sample right away and sleeps for the interval before every following one, e.g.
+/// `FrequencySampler::new(Duration::from_millis(100)).take(50).collect::<Vec<_>>()` records
+/// five seconds.
+#[derive(Debug, Clone)]
+pub struct FrequencySampler {
+    interval: Duration,
+    cpufreq_dirs: Vec<(usize, PathBuf)>,
+    started: Instant,
+    sampled: bool,
+}
+
+impl FrequencySampler {
+    /// Samples the CPUs of `/sys` every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self::with_sysfs(SYSFS_ROOT, interval)
+    }
+
+    /// Samples the CPUs of the sysfs tree at `sysfs` every `interval`.
+    pub fn with_sysfs(sysfs: impl AsRef<Path>, interval: Duration) -> Self {
+        FrequencySampler {
+            interval,
+            cpufreq_dirs: cpufreq_dirs(sysfs.as_ref()),
+            started: Instant::now(),
+            sampled: false,
+        }
+    }
+
+    /// Reads the current speeds without waiting for the interval.
+    pub fn sample(&mut self) -> FrequencySample {
+        self.sampled = true;
+
+        FrequencySample {
+            elapsed: self.started.elapsed(),
+            mhz: self
+                .cpufreq_dirs
+                .iter()
+                .filter_map(|(cpu, cpufreq)| Some((*cpu, current_mhz(cpufreq)?)))
+                .collect(),
+        }
+    }
+}
+
+impl Iterator for FrequencySampler {
+    type Item = FrequencySample;
+
+    /// Never returns `None`, limit the samples with e.g. [`Iterator::take`].
+    fn next(&mut self) -> Option<FrequencySample> {
+        if self.sampled {
+            thread::sleep(self.interval);
+        }
+        Some(self.sample())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    fn write_cpufreq(sysfs: &TempDir, cpu: usize, file: &str, contents: &str) -> PathBuf {
+        sysfs.write(format!("{}/cpu{}/cpufreq/{}", SYSFS_CPU_DIR, cpu, file), &format!("{}\n", contents))
+    }
+
+    #[test]
+    fn scaling_settings() {
+        let sysfs = TempDir::new();
+        for (file, contents) in [
+            ("scaling_cur_freq", "4712345"),
+            ("cpuinfo_cur_freq", "1000000"),
+            ("scaling_min_freq", "800000"),
+            ("scaling_max_freq", "5100000"),
+            ("cpuinfo_min_freq", "800000"),
+            ("cpuinfo_max_freq", "5400000"),
+            ("scaling_driver", "intel_pstate"),
+            ("scaling_governor", "powersave"),
+            ("scaling_available_governors", "performance powersave"),
+            ("energy_performance_preference", "balance_performance"),
+        ] {
+            write_cpufreq(&sysfs, 0, file, contents);
+        }
+        // Without scaling_* files, the current speed comes from cpuinfo_cur_freq
+        write_cpufreq(&sysfs, 1, "cpuinfo_cur_freq", "3600000");
+        write_cpufreq(&sysfs, 1, "cpuinfo_max_freq", "5400000");
+        // CPUs without cpufreq are left out
+        sysfs.create_dir(format!("{}/cpu2", SYSFS_CPU_DIR));
+
+        let frequencies = CpuFrequencies::read_from(sysfs.path()).unwrap();
+        assert_eq!(frequencies.cores, vec![
+            CoreFrequency {
+                cpu: 0,
+                current_mhz: Some(4712),
+                min_mhz: Some(800),
+                max_mhz: Some(5100),
+                hardware_min_mhz: Some(800),
+                hardware_max_mhz: Some(5400),
+                driver: Some("intel_pstate".to_string()),
+                governor: Some("powersave".to_string()),
+                available_governors: vec!["performance".to_string(), "powersave".to_string()],
+                energy_performance_preference: Some("balance_performance".to_string()),
+            },
+            CoreFrequency {
+                cpu: 1,
+                current_mhz: Some(3600),
+                min_mhz: None,
+                max_mhz: None,
+                hardware_min_mhz: None,
+                hardware_max_mhz: Some(5400),
+                driver: None,
+                governor: None,
+                available_governors: Vec::new(),
+                energy_performance_preference: None,
+            },
+        ]);
+        assert_eq!(frequencies.driver(), Some("intel_pstate"));
+        assert_eq!(frequencies.max_current_mhz(), Some(4712));
+        assert_eq!(frequencies.boost, None);
+
+        assert_eq!(CpuFrequencies::read_from(TempDir::new().path()), None);
+    }
+
+    #[test]
+    fn boost_settings() {
+        let sysfs = TempDir::new();
+        write_cpufreq(&sysfs, 0, "scaling_driver", "acpi-cpufreq");
+        let boost = || CpuFrequencies::read_from(sysfs.path()).unwrap().boost;
+
+        sysfs.write(format!("{}/cpufreq/boost", SYSFS_CPU_DIR), "1\n");
+        assert_eq!(boost(), Some(true));
+        sysfs.write(format!("{}/cpufreq/boost", SYSFS_CPU_DIR), "0\n");
+        assert_eq!(boost(), Some(false));
+
+        // no_turbo is inverted and wins over cpufreq/boost
+        sysfs.write(format!("{}/intel_pstate/no_turbo", SYSFS_CPU_DIR), "0\n");
+        assert_eq!(boost(), Some(true));
+        sysfs.write(format!("{}/intel_pstate/no_turbo", SYSFS_CPU_DIR), "1\n");
+        assert_eq!(boost(), Some(false));
+    }
+
+    #[test]
+    fn sampler() {
+        let sysfs = TempDir::new();
+        let cpu0 = write_cpufreq(&sysfs, 0, "scaling_cur_freq", "2000000");
+        write_cpufreq(&sysfs, 1, "scaling_cur_freq", "3000999");
+        // A CPU whose speed cannot be read is left out of the samples
+        write_cpufreq(&sysfs, 2, "scaling_governor", "performance");
+
+        let mut sampler = FrequencySampler::with_sysfs(sysfs.path(), Duration::ZERO);
+        let sample = sampler.sample();
+        assert_eq!(sample.mhz, vec![(0, 2000), (1, 3000)]);
+        assert_eq!(sample.average_mhz(), Some(2500.0));
+        assert_eq!(sample.max_mhz(), Some(3000));
+
+        std::fs::write(&cpu0, "4000000\n").unwrap();
+        let sample = sampler.next().unwrap();
+        assert_eq!(sample.mhz, vec![(0, 4000), (1, 3000)]);
+
+        let empty = FrequencySample { elapsed: Duration::ZERO, mhz: Vec::new() };
+        assert_eq!((empty.average_mhz(), empty.max_mhz()), (None, None));
+    }
+}
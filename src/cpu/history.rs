@@ -0,0 +1,263 @@
+//! Snapshots of the upstream datasets and the spec changes between them.
+//!
+//! Every embedded CSV file is a snapshot of its dataset. Snapshots are imported oldest first, so the
+//! newest snapshot of a product wins, and every field that changed between two snapshots is recorded
+//! in the `cpu_history` table.
+
+use std::fmt;
+use std::path::Path;
+use rusqlite::{params, Connection};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::spec::Vendor;
+
+/// The release of a dataset a CSV file belongs to, taken from its path. Orders from oldest to
+/// newest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Snapshot {
+    /// Files without a version or date in their path, imported before every other snapshot
+    Unknown,
+    /// An intel-processors release, e.g. `v1_8` in `intel_core_processors_v1_8.csv` or a `v1_2`
+    /// folder
+    Version(u32, u32),
+    /// An AMDCpuData export as year, month and day, e.g. `4-12-24` (month-day-year) in
+    /// `amdProcessorInformation_4-12-24.csv`
+    Date(u16, u8, u8),
+}
+
+/// `major` and `minor` of a `v<major>_<minor>` or `v<major>.<minor>` version in `s`.
+fn parse_version(s: &str) -> Option<(u32, u32)> {
+    let lower = s.to_lowercase();
+
+    lower.match_indices('v').find_map(|(i, _)| {
+        // Skip the "v" of words, e.g. "processors_v1_8" is fine but "advanced" is not
+        if lower[..i].chars().next_back().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        let rest = &lower[i + 1..];
+        let major_len = rest.chars().take_while(char::is_ascii_digit).count();
+        let separator = rest[major_len..].chars().next()?;
+        if major_len == 0 || !matches!(separator, '_' | '.') {
+            return None;
+        }
+
+        let minor = rest[major_len + 1..].chars().take_while(char::is_ascii_digit).collect::<String>();
+        Some((rest[..major_len].parse().ok()?, minor.parse().ok()?))
+    })
+}
+
+/// Year, month and day of a `<month>-<day>-<year>` date in `s`, with two or four digit years.
+fn parse_date(s: &str) -> Option<(u16, u8, u8)> {
+    s.split(['_', ' ']).find_map(|word| {
+        let parts = word.split('-').map(|part| part.parse::<u16>().ok()).collect::<Option<Vec<_>>>()?;
+        let [month, day, year] = parts.as_slice() else {
+            return None;
+        };
+
+        let year = if *year < 100 { 2000 + year } else { *year };
+        ((1..=12).contains(month) && (1..=31).contains(day)).then_some((year, *month as u8, *day as u8))
+    })
+}
+
+impl Snapshot {
+    /// The snapshot of a CSV file, from its file name or else the names of its folders.
+    pub fn from_path(path: &str) -> Self {
+        let path = Path::new(path);
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        let folders = path
+            .ancestors()
+            .skip(1)
+            .filter_map(|folder| folder.file_name())
+            .map(|name| name.to_string_lossy().to_string());
+
+        for name in stem.into_iter().chain(folders) {
+            if let Some((major, minor)) = parse_version(&name) {
+                return Snapshot::Version(major, minor);
+            }
+            if let Some((year, month, day)) = parse_date(&name) {
+                return Snapshot::Date(year, month, day);
+            }
+        }
+
+        Snapshot::Unknown
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Snapshot::Unknown => write!(f, "unknown"),
+            Snapshot::Version(major, minor) => write!(f, "v{}.{}", major, minor),
+            Snapshot::Date(year, month, day) => write!(f, "{}-{:02}-{:02}", year, month, day),
+        }
+    }
+}
+
+/// A field of a product that changed between two snapshots, e.g. `Status` from `"Launched"` to
+/// `"Discontinued"`. Only fields with a value in both snapshots are compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecChange {
+    pub product: String,
+    /// The CSV header of the field in the newer snapshot
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub old_snapshot: String,
+    pub new_snapshot: String,
+}
+
+/// Replaces the recorded changes of `vendor` with `changes`.
+pub(crate) fn write(conn: &mut Connection, vendor: Vendor, changes: &[SpecChange]) -> Result<(), rusqlite::Error> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cpu_history (
+            vendor TEXT NOT NULL,
+            product TEXT NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT NOT NULL,
+            new_value TEXT NOT NULL,
+            old_snapshot TEXT NOT NULL,
+            new_snapshot TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS cpu_history_product ON cpu_history (product);"
+    )?;
+    tx.execute("DELETE FROM cpu_history WHERE vendor = ?1", [vendor.key()])?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO cpu_history (vendor, product, field, old_value, new_value, old_snapshot, new_snapshot)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        )?;
+        for change in changes {
+            stmt.execute(params![
+                vendor.key(),
+                change.product,
+                change.field,
+                change.old_value,
+                change.new_value,
+                change.old_snapshot,
+                change.new_snapshot
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// The changes of the product named `product` in the default [`CpuDatabase`], oldest first.
+pub fn history(product: &str) -> Result<Vec<SpecChange>, rusqlite::Error> {
+    history_in(&CpuDatabase::open_default()?, product)
+}
+
+/// Like [`history`], but reads `db`.
+pub fn history_in(db: &CpuDatabase, product: &str) -> Result<Vec<SpecChange>, rusqlite::Error> {
+    let conn = db.connection();
+    let table_exists = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'cpu_history'")?
+        .exists([])?;
+    if !table_exists {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT product, field, old_value, new_value, old_snapshot, new_snapshot
+        FROM cpu_history WHERE product = ?1 ORDER BY rowid"
+    )?;
+    let changes = stmt.query_map([product], |row| {
+        Ok(SpecChange {
+            product: row.get(0)?,
+            field: row.get(1)?,
+            old_value: row.get(2)?,
+            new_value: row.get(3)?,
+            old_snapshot: row.get(4)?,
+            new_snapshot: row.get(5)?,
+        })
+    })?;
+
+    changes.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::intel::IntelData;
+    use crate::cpu::private::Database;
+    use crate::tests::source;
+
+    #[test]
+    fn snapshots_from_paths() {
+        let cases = [
+            ("res/cpu/intel/intel-processors/intel_core_processors_v1_8.csv", Snapshot::Version(1, 8)),
+            ("res/cpu/intel/intel-processors/v1_2/intel_core_processors.csv", Snapshot::Version(1, 2)),
+            ("data/processors_v2.10.csv", Snapshot::Version(2, 10)),
+            ("res/cpu/amd/AMDCpuData/amdProcessorInformation_4-12-24.csv", Snapshot::Date(2024, 4, 12)),
+            ("amd_12-31-2023.csv", Snapshot::Date(2023, 12, 31)),
+            ("res/cpu/amd/advanced/cpus.csv", Snapshot::Unknown),
+            ("amd_13-40-24.csv", Snapshot::Unknown),
+            ("", Snapshot::Unknown),
+        ];
+
+        for (path, snapshot) in cases {
+            assert_eq!(Snapshot::from_path(path), snapshot, "{}", path);
+        }
+    }
+
+    #[test]
+    fn snapshot_order() {
+        assert!(Snapshot::Unknown < Snapshot::Version(1, 2));
+        assert!(Snapshot::Version(1, 2) < Snapshot::Version(1, 10));
+        assert!(Snapshot::Date(2023, 12, 31) < Snapshot::Date(2024, 4, 12));
+        assert_eq!(Snapshot::Version(1, 8).to_string(), "v1.8");
+        assert_eq!(Snapshot::Date(2024, 4, 12).to_string(), "2024-04-12");
+    }
+
+    #[test]
+    fn changes_between_snapshots() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let sources = [
+            source("v1_1/intel.csv", "Product,Status,Cores\nCore i7-8700,Launched,6\n", false),
+            source("v1_2/intel.csv", "Product,Status,Cores,Threads\nCore i7-8700,Discontinued,6,12\n", false),
+        ];
+
+        let report = IntelData::save_to_database(&mut conn, &sources).unwrap();
+
+        assert!(report.collisions.is_empty());
+        assert_eq!(report.history, vec![SpecChange {
+            product: "Core i7-8700".to_string(),
+            field: "Status".to_string(),
+            old_value: "Launched".to_string(),
+            new_value: "Discontinued".to_string(),
+            old_snapshot: "v1.1".to_string(),
+            new_snapshot: "v1.2".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn same_snapshot_collides() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let sources = [source("v1_2/intel.csv", "Product,Status\nCore i7-8700,Launched\nCore i7-8700,Discontinued\n", false)];
+
+        let report = IntelData::save_to_database(&mut conn, &sources).unwrap();
+
+        assert!(report.history.is_empty());
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!((report.collisions[0].line, report.collisions[0].previous_line), (3, 2));
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut db = CpuDatabase::in_memory().unwrap();
+        let change = SpecChange {
+            product: "Core i5-99999X".to_string(),
+            field: "Status".to_string(),
+            old_value: "Launched".to_string(),
+            new_value: "Discontinued".to_string(),
+            old_snapshot: "v1.1".to_string(),
+            new_snapshot: "v1.2".to_string(),
+        };
+
+        write(db.connection_mut(), Vendor::Intel, std::slice::from_ref(&change)).unwrap();
+        assert_eq!(history_in(&db, "Core i5-99999X").unwrap(), vec![change]);
+
+        write(db.connection_mut(), Vendor::Intel, &[]).unwrap();
+        assert!(history_in(&db, "Core i5-99999X").unwrap().is_empty());
+    }
+}
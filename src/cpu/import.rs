@@ -0,0 +1,571 @@
+//! Reads the embedded and user-provided CSV files by header name instead of by column position, so reordered or
+//! added columns in the upstream datasets don't shift values into the wrong fields, and reports
+//! what happened to every row.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+use csv::{ReaderBuilder, StringRecord};
+use crate::cpu::embedded::EmbeddedSource;
+use crate::cpu::history::{Snapshot, SpecChange};
+
+/// A CSV file to import, either embedded into the library or registered by the user as an
+/// overlay, see [`CpuDatabase::add_overlay`](crate::cpu::database::CpuDatabase::add_overlay).
+pub struct CsvSource<'a> {
+    pub(crate) path: Cow<'a, str>,
+    pub(crate) text: Cow<'a, str>,
+    /// Whether the rows come from a user overlay rather than the upstream datasets
+    pub(crate) user_provided: bool,
+    /// [`Snapshot::Unknown`] for overlays
+    pub(crate) snapshot: Snapshot,
+}
+
+impl EmbeddedSource {
+    pub(crate) fn to_csv_source(&self) -> CsvSource<'static> {
+        CsvSource {
+            path: Cow::Borrowed(self.path),
+            text: String::from_utf8_lossy(self.contents),
+            user_provided: false,
+            snapshot: Snapshot::from_path(self.path),
+        }
+    }
+}
+
+/// Lowercase ASCII alphanumeric form of a header, so that `"Lithography (nm)"`,
+/// `"Lithography(nm)"` and `"lithography nm"` are the same column.
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+struct Header {
+    name: String,
+    normalized: String,
+}
+
+/// Outcome of importing CSV files into the CPU database, returned by
+/// [`Database::gen_db`](crate::cpu::Database::gen_db).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub files: Vec<FileReport>,
+    pub skipped: Vec<SkippedRow>,
+    pub parse_failures: Vec<ParseFailure>,
+    pub collisions: Vec<Collision>,
+    /// Fields that changed between two snapshots of a product, also kept in the database, see
+    /// [`history`](crate::cpu::history::history)
+    pub history: Vec<SpecChange>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub path: String,
+    pub rows_imported: usize,
+    /// Expected columns missing from the header, their values are empty for every row
+    pub missing_columns: Vec<String>,
+}
+
+/// A row that was not imported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRow {
+    pub path: String,
+    /// Line number in the CSV file, starting at 1 for the header
+    pub line: u64,
+    pub reason: String,
+}
+
+/// A value of a numeric column that could not be parsed. The row is imported with the value
+/// left empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    pub path: String,
+    pub line: u64,
+    pub column: String,
+    pub value: String,
+}
+
+/// A row whose primary key (the product name) was already imported from an earlier row of the same
+/// snapshot or from an overlay, which it replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub key: String,
+    pub path: String,
+    pub line: u64,
+    pub previous_path: String,
+    pub previous_line: u64,
+    /// Values of the replaced row that differ from the new one, by header. Columns the new row
+    /// leaves empty are listed with their old value as well.
+    pub overwritten: BTreeMap<String, String>,
+}
+
+impl ImportReport {
+    pub fn rows_imported(&self) -> usize {
+        self.files.iter().map(|f| f.rows_imported).sum()
+    }
+
+    /// Whether every row was imported without skipped rows, parse failures or collisions.
+    pub fn is_clean(&self) -> bool {
+        self.skipped.is_empty() && self.parse_failures.is_empty() && self.collisions.is_empty()
+    }
+
+    pub(crate) fn merge(&mut self, other: ImportReport) {
+        self.files.extend(other.files);
+        self.skipped.extend(other.skipped);
+        self.parse_failures.extend(other.parse_failures);
+        self.collisions.extend(other.collisions);
+        self.history.extend(other.history);
+    }
+
+    pub(crate) fn skip(&mut self, path: &str, line: u64, reason: impl Into<String>) {
+        let reason = reason.into();
+        log::warn!("{}:{}: skipping row, {}", path, line, reason);
+        self.skipped.push(SkippedRow { path: path.to_string(), line, reason });
+    }
+
+    /// Drops everything that is not about the file at `path`, including the history, which only
+    /// comes from the embedded snapshots.
+    pub(crate) fn retain_file(&mut self, path: &str) {
+        self.files.retain(|file| file.path == path);
+        self.skipped.retain(|row| row.path == path);
+        self.parse_failures.retain(|failure| failure.path == path);
+        self.collisions.retain(|collision| collision.path == path);
+        self.history.clear();
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            writeln!(f, "{}: {} row(s) imported", file.path, file.rows_imported)?;
+            for column in &file.missing_columns {
+                writeln!(f, "  missing column \"{}\"", column)?;
+            }
+        }
+        for row in &self.skipped {
+            writeln!(f, "{}:{}: skipped, {}", row.path, row.line, row.reason)?;
+        }
+        for failure in &self.parse_failures {
+            writeln!(
+                f,
+                "{}:{}: \"{}\" is not a valid {} value",
+                failure.path, failure.line, failure.value, failure.column
+            )?;
+        }
+        for collision in &self.collisions {
+            writeln!(
+                f,
+                "{}:{}: {} replaces the row from {}:{}",
+                collision.path, collision.line, collision.key, collision.previous_path, collision.previous_line
+            )?;
+            for (column, value) in &collision.overwritten {
+                writeln!(f, "  {}: \"{}\"", column, value)?;
+            }
+        }
+        if !self.history.is_empty() {
+            writeln!(f, "{} field(s) changed between snapshots", self.history.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// A data row of a CSV file, with its values accessible by header name.
+pub(crate) struct CsvRow {
+    pub(crate) path: Rc<str>,
+    /// Line number in the CSV file, starting at 1 for the header
+    pub(crate) line: u64,
+    pub(crate) user_provided: bool,
+    snapshot: Snapshot,
+    headers: Rc<Vec<Header>>,
+    record: StringRecord,
+    parse_failures: RefCell<Vec<ParseFailure>>,
+}
+
+impl CsvRow {
+    /// The trimmed value of the column named `header`, `None` if the column is missing or empty.
+    pub(crate) fn get(&self, header: &str) -> Option<&str> {
+        let normalized = normalize_header(header);
+        let index = self.headers.iter().position(|h| h.normalized == normalized)?;
+
+        self.record
+            .get(index)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Like [`CsvRow::get`], but returns an empty string for missing values.
+    pub(crate) fn text(&self, header: &str) -> String {
+        self.get(header).unwrap_or_default().to_string()
+    }
+
+    /// Parses the value of the column named `header`. Values that `parse` rejects are remembered as
+    /// [`ParseFailure`]s, except for `"N/A"`.
+    pub(crate) fn parse<T>(&self, header: &str, parse: impl FnOnce(&str) -> Option<T>) -> Option<T> {
+        let parsed = parse(self.get(header)?);
+        self.check(header, parsed)
+    }
+
+    /// Remembers a [`ParseFailure`] if the column named `header` has a value but `parsed`, the
+    /// value as parsed elsewhere, is `None`.
+    pub(crate) fn check<T>(&self, header: &str, parsed: Option<T>) -> Option<T> {
+        match self.get(header) {
+            Some(value) if parsed.is_none() && value != "N/A" => {
+                self.parse_failures.borrow_mut().push(ParseFailure {
+                    path: self.path.to_string(),
+                    line: self.line,
+                    column: header.to_string(),
+                    value: value.to_string(),
+                });
+            }
+            _ => {}
+        }
+        parsed
+    }
+
+    pub(crate) fn take_parse_failures(&self) -> Vec<ParseFailure> {
+        self.parse_failures.take()
+    }
+
+    /// The non-empty values of every column, by their original header.
+    fn values(&self) -> BTreeMap<String, String> {
+        self.extra(&[])
+    }
+
+    /// The non-empty values of every column that is not in `known`, by their original header.
+    pub(crate) fn extra(&self, known: &[&str]) -> BTreeMap<String, String> {
+        let known = known.iter().map(|h| normalize_header(h)).collect::<Vec<_>>();
+
+        self.headers
+            .iter()
+            .zip(self.record.iter())
+            .filter(|(header, _)| !header.normalized.is_empty() && !known.contains(&header.normalized))
+            .map(|(header, value)| (header.name.trim().to_string(), value.trim().to_string()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect()
+    }
+}
+
+/// Reads every row of `source`. Rows the CSV reader can't parse are skipped, as are files without a
+/// header. Adds the file to `report`, `rows_imported` is left for the caller.
+pub(crate) fn read_rows(source: &CsvSource, expected: &[&str], report: &mut ImportReport) -> Vec<CsvRow> {
+    let path: Rc<str> = Rc::from(source.path.as_ref());
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(source.text.as_bytes());
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers
+            .iter()
+            .map(|name| Header { name: name.to_string(), normalized: normalize_header(name) })
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            report.skip(&path, 1, format!("unable to read the CSV header: {}", err));
+            return Vec::new();
+        }
+    };
+
+    let missing_columns = expected
+        .iter()
+        .filter(|column| {
+            let normalized = normalize_header(column);
+            !headers.iter().any(|h| h.normalized == normalized)
+        })
+        .map(|column| column.to_string())
+        .collect::<Vec<_>>();
+
+    // Overlays usually only fill in a few columns
+    let level = if source.user_provided { log::Level::Debug } else { log::Level::Warn };
+    for column in &missing_columns {
+        log::log!(level, "{}: no \"{}\" column, the values will be empty", path, column);
+    }
+
+    report.files.push(FileReport {
+        path: path.to_string(),
+        rows_imported: 0,
+        missing_columns,
+    });
+
+    let headers = Rc::new(headers);
+    let mut rows = Vec::new();
+
+    for record in reader.records() {
+        match record {
+            Ok(record) => rows.push(CsvRow {
+                path: Rc::clone(&path),
+                line: record.position().map_or(0, |p| p.line()),
+                user_provided: source.user_provided,
+                snapshot: source.snapshot,
+                headers: Rc::clone(&headers),
+                record,
+                parse_failures: RefCell::new(Vec::new()),
+            }),
+            Err(err) => {
+                let line = err.position().map_or(0, |p| p.line());
+                report.skip(&path, line, format!("unreadable row: {}", err));
+            }
+        }
+    }
+
+    rows
+}
+
+struct ImportedRow {
+    path: String,
+    line: u64,
+    snapshot: Snapshot,
+    values: BTreeMap<String, String>,
+}
+
+/// Tracks the rows imported so far by primary key, to report rows that replace earlier ones.
+#[derive(Default)]
+pub(crate) struct ImportedRows {
+    rows: HashMap<String, ImportedRow>,
+}
+
+/// The fields with a value in both `old` and `new` that differ, matching headers by their
+/// normalized form.
+fn changes(key: &str, old: &ImportedRow, new: &ImportedRow) -> Vec<SpecChange> {
+    let old_values = old
+        .values
+        .iter()
+        .map(|(header, value)| (normalize_header(header), value))
+        .collect::<HashMap<_, _>>();
+
+    new.values
+        .iter()
+        .filter_map(|(header, new_value)| {
+            let old_value = old_values.get(&normalize_header(header))?;
+            (*old_value != new_value).then(|| SpecChange {
+                product: key.to_string(),
+                field: header.clone(),
+                old_value: old_value.to_string(),
+                new_value: new_value.clone(),
+                old_snapshot: old.snapshot.to_string(),
+                new_snapshot: new.snapshot.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl ImportedRows {
+    /// `row` with the values of the row imported before under the same key, the value of
+    /// `key_header`, filled in for the columns it leaves empty. Only applies to user-provided rows,
+    /// so overlays change the columns they set and keep the other upstream values.
+    pub(crate) fn merge_overlay(&self, key_header: &str, row: CsvRow) -> CsvRow {
+        let previous = match row.get(key_header).and_then(|key| self.rows.get(key)) {
+            Some(previous) if row.user_provided => previous,
+            _ => return row,
+        };
+
+        let values = row.values();
+        let overlaid = values.keys().map(|header| normalize_header(header)).collect::<Vec<_>>();
+        let merged = previous
+            .values
+            .iter()
+            .filter(|(header, _)| !overlaid.contains(&normalize_header(header)))
+            .chain(&values);
+
+        let (headers, record): (Vec<_>, StringRecord) = merged
+            .map(|(header, value)| {
+                (Header { name: header.clone(), normalized: normalize_header(header) }, value.as_str())
+            })
+            .unzip();
+
+        CsvRow {
+            headers: Rc::new(headers),
+            record,
+            ..row
+        }
+    }
+
+    /// Records that `row` was imported under `key` and updates `report`: the row count of its file,
+    /// its parse failures, and if `key` was imported before, the [`SpecChange`]s from an older
+    /// snapshot or else a [`Collision`].
+    pub(crate) fn add(&mut self, key: &str, row: &CsvRow, report: &mut ImportReport) {
+        if let Some(file) = report.files.iter_mut().rev().find(|f| *f.path == *row.path) {
+            file.rows_imported += 1;
+        }
+        report.parse_failures.extend(row.take_parse_failures());
+
+        let imported = ImportedRow {
+            path: row.path.to_string(),
+            line: row.line,
+            snapshot: row.snapshot,
+            values: row.values(),
+        };
+        let previous = self.rows.insert(key.to_string(), imported);
+        let (Some(previous), Some(imported)) = (previous, self.rows.get(key)) else {
+            return;
+        };
+
+        if !row.user_provided && previous.snapshot < imported.snapshot {
+            report.history.extend(changes(key, &previous, imported));
+            return;
+        }
+
+        let overwritten = previous
+            .values
+            .into_iter()
+            .filter(|(header, value)| imported.values.get(header) != Some(value))
+            .collect::<BTreeMap<_, _>>();
+
+        log::warn!(
+            "{}:{}: {} replaces the row from {}:{}",
+            row.path, row.line, key, previous.path, previous.line
+        );
+
+        report.collisions.push(Collision {
+            key: key.to_string(),
+            path: row.path.to_string(),
+            line: row.line,
+            previous_path: previous.path,
+            previous_line: previous.line,
+            overwritten,
+        });
+    }
+}
+
+/// Serializes the unknown columns of a row for the `extra` column.
+pub(crate) fn extra_to_json(extra: &BTreeMap<String, String>) -> String {
+    serde_json::to_string(extra).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Reads the `extra` column back, treating `NULL` or invalid JSON as no extra columns.
+pub(crate) fn extra_from_json(json: Option<String>) -> BTreeMap<String, String> {
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::intel::IntelData;
+    use crate::cpu::private::Database;
+    use crate::tests::source;
+    use rusqlite::Connection;
+
+    const EXPECTED: &[&str] = &["Product", "Cores", "Lithography (nm)", "TDP (W)"];
+
+    #[test]
+    fn reordered_and_extra_headers() {
+        let mut report = ImportReport::default();
+        let csv = "\
+TDP (W),Socket Type, lithography(nm) ,Product,Notes
+125,FCLGA1700,Intel 7,Core i9-13900K,
+  65 ,FCLGA1151,14,Core i7-8700,Refresh of the i7-7700
+";
+        let rows = read_rows(&source("test.csv", csv, false), EXPECTED, &mut report);
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[1].line, 3);
+        assert_eq!(rows[0].get("Product"), Some("Core i9-13900K"));
+        assert_eq!(rows[0].get("Lithography (nm)"), Some("Intel 7"));
+        assert_eq!(rows[1].get("TDP (W)"), Some("65"));
+        assert_eq!(rows[1].get("tdp w"), Some("65"));
+        assert_eq!(rows[1].text("Cores"), "");
+        assert_eq!(rows[1].get("Cores"), None);
+
+        // Unknown columns are kept by their original header, empty values are left out
+        let extra = rows[0].extra(EXPECTED);
+        assert_eq!(extra, BTreeMap::from([("Socket Type".to_string(), "FCLGA1700".to_string())]));
+        let extra = rows[1].extra(EXPECTED);
+        assert_eq!(extra.get("Notes").map(String::as_str), Some("Refresh of the i7-7700"));
+        assert_eq!(extra.len(), 2);
+        assert_eq!(extra_from_json(Some(extra_to_json(&extra))), extra);
+        assert_eq!(extra_from_json(Some("not json".to_string())), BTreeMap::new());
+
+        assert_eq!(
+            report.files,
+            vec![FileReport { path: "test.csv".to_string(), rows_imported: 0, missing_columns: vec!["Cores".to_string()] }]
+        );
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn short_rows_and_missing_header() {
+        let mut report = ImportReport::default();
+        let rows = read_rows(&source("test.csv", "Product,Cores\nCore i7-8700\n", false), EXPECTED, &mut report);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get("Cores"), None);
+
+        let mut report = ImportReport::default();
+        assert!(read_rows(&source("empty.csv", "", false), EXPECTED, &mut report).is_empty());
+        assert_eq!(report.files[0].missing_columns.len(), EXPECTED.len());
+    }
+
+    #[test]
+    fn parse_failures() {
+        let mut report = ImportReport::default();
+        let csv = "Product,Cores,TDP (W)\nCore i7-8700,six,N/A\n";
+        let rows = read_rows(&source("test.csv", csv, false), EXPECTED, &mut report);
+        let number = |v: &str| v.parse::<u32>().ok();
+
+        assert_eq!(rows[0].parse("Cores", number), None);
+        // "N/A" is a known absent value rather than a failure
+        assert_eq!(rows[0].parse("TDP (W)", number), None);
+        assert_eq!(rows[0].parse("Lithography (nm)", number), None);
+        assert_eq!(
+            rows[0].take_parse_failures(),
+            vec![ParseFailure {
+                path: "test.csv".to_string(),
+                line: 2,
+                column: "Cores".to_string(),
+                value: "six".to_string(),
+            }]
+        );
+        assert!(rows[0].take_parse_failures().is_empty());
+    }
+
+    #[test]
+    fn report() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let csv = "\
+Product,Cores,TDP (W)
+Core i7-8700,6,65
+,8,95
+Core i9-13900K,lots,125
+Core i7-8700,6,80
+";
+        let report = IntelData::save_to_database(&mut conn, &[source("test.csv", csv, false)]).unwrap();
+
+        assert_eq!(report.rows_imported(), 3);
+        assert_eq!(
+            report.skipped,
+            vec![SkippedRow { path: "test.csv".to_string(), line: 3, reason: "no \"Product\" value".to_string() }]
+        );
+        assert_eq!(report.parse_failures.len(), 1);
+        assert_eq!((report.parse_failures[0].line, report.parse_failures[0].column.as_str()), (4, "Cores"));
+        assert_eq!(report.parse_failures[0].value, "lots");
+        assert_eq!(
+            report.collisions,
+            vec![Collision {
+                key: "Core i7-8700".to_string(),
+                path: "test.csv".to_string(),
+                line: 5,
+                previous_path: "test.csv".to_string(),
+                previous_line: 2,
+                overwritten: BTreeMap::from([("TDP (W)".to_string(), "65".to_string())]),
+            }]
+        );
+        assert!(!report.is_clean());
+
+        // The later row wins
+        let tdp: f64 = conn
+            .query_row("SELECT thermal_design_power FROM intel_cpus WHERE product = 'Core i7-8700'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tdp, 80.0);
+
+        let text = report.to_string();
+        assert!(text.contains("test.csv: 3 row(s) imported\n"));
+        assert!(text.contains("test.csv:3: skipped, no \"Product\" value\n"));
+        assert!(text.contains("test.csv:4: \"lots\" is not a valid Cores value\n"));
+        assert!(text.contains("test.csv:5: Core i7-8700 replaces the row from test.csv:2\n  TDP (W): \"65\"\n"));
+
+        let mut retained = report.clone();
+        retained.retain_file("other.csv");
+        assert!(retained.is_clean() && retained.files.is_empty());
+    }
+}
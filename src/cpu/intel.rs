@@ -1,10 +1,16 @@
-
-use csv;
-use std::fs;
-use std::fs::File;
-use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use rusqlite::{params, Connection, Row};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::cache::{self, CacheSpec};
 use crate::cpu::{eCPUDetails};
-use crate::cpu::private::Database;
+use crate::cpu::embedded::{EmbeddedSource, INTEL_SOURCES};
+use crate::cpu::launch::LaunchDate;
+use crate::cpu::memory::{self, MemorySupport};
+use crate::cpu::power::PowerSpec;
+use crate::cpu::spec::Vendor;
+use crate::cpu::import::{extra_from_json, extra_to_json, read_rows, CsvRow, CsvSource, ImportReport, ImportedRows};
+use crate::cpu::private::Database as _;
+use crate::utils::{first_number, leading_number, parse_size_bytes};
 use super::EnumCPUData;
 
 /// This is a struct for the data of Intel CPUS according to the 
@@ -21,18 +27,42 @@ pub struct IntelData {
     pub code_name: String,
     pub cores: Option<usize>,
     pub threads: Option<usize>,
+    /// Lithography in nm
     pub lithography: Option<usize>,
+    /// Max turbo frequency in MHz
     pub max_turbo_freq: Option<usize>,
+    /// Base frequency in MHz
     pub base_freq: Option<usize>,
+    /// TDP in watts
     pub thermal_design_power: Option<usize>,
+    /// Cache size in bytes
     pub cache: Option<usize>,
     pub cache_info: String,
+    /// Max memory size in GB
     pub max_memory_size: Option<usize>,
     pub memory_types: Vec<String>,
+    /// Max memory speed in MHz
     pub max_memory_speed: Option<usize>,
     pub graphics: Option<String>,
+    /// Values of CSV columns hwisak does not know about, by header name
+    pub extra: BTreeMap<String, String>,
+    /// The last level cache, parsed from `cache` and `cache_info`
+    pub caches: Vec<CacheSpec>,
+    /// Parsed from `release_date`
+    pub launched: Option<LaunchDate>,
+    /// Parsed from `memory_types`, `max_memory_speed` and `max_memory_size`
+    pub memory: Vec<MemorySupport>,
+    /// Parsed from `thermal_design_power` and the configurable TDP columns of `extra`
+    pub power: PowerSpec,
+    /// Socket from a `Sockets Supported` column of `extra`, or inferred from the code name for
+    /// desktop processors, see [`infer_socket`]. `None` for soldered or unknown processors.
+    pub socket: Option<String>,
+    /// Whether the record comes from a user overlay, see
+    /// [`CpuDatabase::add_overlay`](crate::cpu::database::CpuDatabase::add_overlay)
+    pub user_provided: bool,
 }
 
+#[allow(non_camel_case_types)]
 pub enum eIntelData {
     Name,
     Status,
@@ -50,10 +80,18 @@ pub enum eIntelData {
     MemoryTypes,
     MaxMemorySpeed,
     Graphics,
+    /// The sortable launch date, see [`LaunchDate::key`]
+    LaunchKey,
+    /// Lowest configurable TDP in watts
+    ConfigurableTDPMinWatts,
+    /// Highest configurable TDP in watts
+    ConfigurableTDPMaxWatts,
+    Socket,
+    UserProvided,
 }
 
 impl eIntelData {
-    fn to_string(&self) -> &str {
+    pub(crate) fn to_string(&self) -> &'static str {
         match self {
             eIntelData::Name => "product",
             eIntelData::Status => "status",
@@ -71,28 +109,218 @@ impl eIntelData {
             eIntelData::MemoryTypes => "memory_types",
             eIntelData::MaxMemorySpeed => "max_memory_speed",
             eIntelData::Graphics => "graphics",
+            eIntelData::LaunchKey => "launch_key",
+            eIntelData::ConfigurableTDPMinWatts => "ctdp_min_w",
+            eIntelData::ConfigurableTDPMaxWatts => "ctdp_max_w",
+            eIntelData::Socket => "socket",
+            eIntelData::UserProvided => "user_provided",
         }
     }
 }
 
+/// Column headers of the intel-processors CSV files.
+mod header {
+    pub(super) const PRODUCT: &str = "Product";
+    pub(super) const STATUS: &str = "Status";
+    pub(super) const RELEASE_DATE: &str = "Release Date";
+    pub(super) const CODE_NAME: &str = "Code Name";
+    pub(super) const CORES: &str = "Cores";
+    pub(super) const THREADS: &str = "Threads";
+    pub(super) const LITHOGRAPHY: &str = "Lithography (nm)";
+    pub(super) const MAX_TURBO_FREQ: &str = "Max. Turbo Freq. (GHz)";
+    pub(super) const BASE_FREQ: &str = "Base Freq. (GHz)";
+    pub(super) const TDP: &str = "TDP (W)";
+    pub(super) const CACHE: &str = "Cache (MB)";
+    pub(super) const CACHE_INFO: &str = "Cache Info";
+    pub(super) const MAX_MEMORY_SIZE: &str = "Max. Memory Size (GB)";
+    pub(super) const MEMORY_TYPES: &str = "Memory Types";
+    pub(super) const MAX_MEMORY_SPEED: &str = "Max. Memory Speed (MHz)";
+    pub(super) const GRAPHICS: &str = "Integ. Graphics";
+
+    pub(super) const ALL: &[&str] = &[
+        PRODUCT, STATUS, RELEASE_DATE, CODE_NAME, CORES, THREADS, LITHOGRAPHY, MAX_TURBO_FREQ,
+        BASE_FREQ, TDP, CACHE, CACHE_INFO, MAX_MEMORY_SIZE, MEMORY_TYPES, MAX_MEMORY_SPEED, GRAPHICS,
+    ];
+}
+
+/// Sockets of the desktop processors of a code name. Mobile processors of the same code names are
+/// soldered, and the HEDT variants (e.g. "Skylake-X") use other sockets.
+const DESKTOP_SOCKETS: &[(&str, &str)] = &[
+    ("Arrow Lake", "LGA1851"),
+    ("Raptor Lake", "LGA1700"),
+    ("Alder Lake", "LGA1700"),
+    ("Rocket Lake", "LGA1200"),
+    ("Comet Lake", "LGA1200"),
+    ("Coffee Lake", "LGA1151"),
+    ("Kaby Lake", "LGA1151"),
+    ("Skylake", "LGA1151"),
+    ("Broadwell", "LGA1150"),
+    ("Haswell", "LGA1150"),
+    ("Ivy Bridge", "LGA1155"),
+    ("Sandy Bridge", "LGA1155"),
+];
+
+/// Model number suffixes of socketed desktop processors, e.g. "K" for "Core i9-13900K".
+const DESKTOP_SUFFIXES: &[&str] = &["", "K", "KF", "KS", "F", "T", "S", "C"];
+
+/// Infers the socket of a desktop processor from its code name, e.g. `LGA1700` for
+/// "Core i9-13900K" and "Raptor Lake" or `LGA1200` for "Pentium Gold G6400" and "Comet Lake".
+/// `None` for mobile processors like "Core i7-1065G7" and code names not in the list.
+pub fn infer_socket(name: &str, code_name: &str) -> Option<&'static str> {
+    let model = name.rsplit([' ', '-']).next()?;
+    // Desktop Pentium and Celeron model numbers start with a "G"
+    let model = model.strip_prefix('G').filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit())).unwrap_or(model);
+    let suffix = model.trim_start_matches(|c: char| c.is_ascii_digit());
+    if suffix.len() == model.len() || !DESKTOP_SUFFIXES.contains(&suffix.to_uppercase().as_str()) {
+        return None;
+    }
+
+    if code_name.contains('-') {
+        return None;
+    }
+
+    DESKTOP_SOCKETS
+        .iter()
+        .find(|(code, _)| code_name.contains(code))
+        .map(|(_, socket)| *socket)
+}
+
 #[derive(Debug, PartialEq)]
-enum ProductStatus {
+pub enum ProductStatus {
     Launched,
     Discontinued,
     Announced,
 }
 
-impl crate::cpu::Database for IntelData {
-    fn fetch(keyword: &str, column: EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error> {
-        if !Self::check_if_db_exists() {
-            Self::gen_db()
+impl IntelData {
+    /// Builds the struct from a row of an intel-processors CSV file, `None` if it has no product name.
+    pub(crate) fn from_csv_row(row: &CsvRow) -> Option<Self> {
+        let name = row.get(header::PRODUCT)?.to_string();
+        let clock_speed = |v: &str| Some(Self::parse_clock_speed(v)).filter(|&mhz| mhz > 0);
+        let number = |column: &str| row.parse(column, leading_number);
+        // Values of columns that are missing or "N/A"
+        let available = |column: &str| row.get(column).filter(|value| *value != "N/A");
+
+        // The intel-processors dataset lists cache sizes in MB
+        let cache = row.parse(header::CACHE, |v| parse_size_bytes(v, 1024 * 1024));
+        let cache_info = row.text(header::CACHE_INFO);
+
+        let memory_types: Vec<String> = available(header::MEMORY_TYPES)
+            .map(|types| types.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+        let max_memory_size = number(header::MAX_MEMORY_SIZE).map(|v| v as usize);
+        let max_memory_speed = number(header::MAX_MEMORY_SPEED).map(|v| v as usize);
+        let thermal_design_power = number(header::TDP).map(|v| v.round() as usize);
+        let extra = row.extra(header::ALL);
+        let code_name = row.text(header::CODE_NAME);
+        let socket = extra
+            .iter()
+            .find(|(header, _)| header.to_lowercase().contains("socket"))
+            .map(|(_, socket)| socket.clone())
+            .or_else(|| infer_socket(&name, &code_name).map(str::to_string));
+
+        Some(IntelData {
+            name,
+            status: match row.get(header::STATUS) {
+                Some("Launched") => ProductStatus::Launched,
+                Some("Discontinued") => ProductStatus::Discontinued,
+                _ => ProductStatus::Announced,
+            },
+            release_date: row.text(header::RELEASE_DATE),
+            launched: row.parse(header::RELEASE_DATE, LaunchDate::parse),
+            code_name,
+            socket,
+            user_provided: row.user_provided,
+            cores: number(header::CORES).map(|v| v as usize),
+            threads: number(header::THREADS).map(|v| v as usize),
+            lithography: row.parse(header::LITHOGRAPHY, first_number).map(|v| v as usize),
+            max_turbo_freq: row.parse(header::MAX_TURBO_FREQ, clock_speed),
+            base_freq: row.parse(header::BASE_FREQ, clock_speed),
+            power: PowerSpec::from_intel(thermal_design_power, &extra),
+            thermal_design_power,
+            cache: cache.map(|v| v as usize),
+            caches: CacheSpec::from_intel(cache, &cache_info),
+            cache_info,
+            memory: memory::from_intel(
+                &memory_types,
+                max_memory_speed,
+                max_memory_size.map(|gb| gb as u64 * 1024 * 1024 * 1024),
+                &extra,
+            ),
+            max_memory_size,
+            memory_types,
+            max_memory_speed,
+            graphics: available(header::GRAPHICS).map(str::to_string),
+            extra,
+        })
+    }
+
+    /// Builds the struct from a `SELECT * FROM intel_cpus` row.
+    pub(crate) fn from_row(row: &Row) -> Self {
+        IntelData {
+            name: row.get(0).unwrap_or_else(|e| {
+                log::warn!("Error getting product: {}", e);
+                "Unknown".to_string()
+            }),
+            status: match row.get::<_, String>(1).unwrap_or_else(|e| {
+                log::warn!("Error getting status: {}", e);
+                "Announced".to_string()
+            }).as_str() {
+                "Launched" => ProductStatus::Launched,
+                "Discontinued" => ProductStatus::Discontinued,
+                _ => ProductStatus::Announced,
+            },
+            release_date: row.get(2).unwrap_or_else(|e| {
+                log::warn!("Error getting release_date: {}", e);
+                "Unknown".to_string()
+            }),
+            code_name: row.get(3).unwrap_or_else(|e| {
+                log::warn!("Error getting code_name: {}", e);
+                "Unknown".to_string()
+            }),
+            cores: row.get::<_, Option<usize>>(4).ok().flatten(),
+            threads: row.get::<_, Option<usize>>(5).ok().flatten(),
+            lithography: row.get::<_, Option<usize>>(6).ok().flatten(),
+            max_turbo_freq: row.get::<_, Option<usize>>(7).ok().flatten(),
+            base_freq: row.get::<_, Option<usize>>(8).ok().flatten(),
+            thermal_design_power: row.get::<_, Option<usize>>(9).ok().flatten(),
+            cache: row.get::<_, Option<usize>>(10).ok().flatten(),
+            cache_info: row.get(11).unwrap_or_else(|e| {
+                log::warn!("Error getting cache_info: {}", e);
+                "Unknown".to_string()
+            }),
+            max_memory_size: row.get::<_, Option<usize>>(12).ok().flatten(),
+            memory_types: row.get::<_, String>(13).unwrap_or_else(|e| {
+                log::warn!("Error getting memory_types: {}", e);
+                "Unknown".to_string()
+            }).split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+            max_memory_speed: row.get::<_, Option<usize>>(14).ok().flatten(),
+            graphics: row.get::<_, String>(15).ok().filter(|g| !g.is_empty()),
+            extra: extra_from_json(row.get(16).ok().flatten()),
+            caches: cache::from_json(row.get(17).ok().flatten()),
+            launched: row.get::<_, Option<u32>>(18).ok().flatten().and_then(LaunchDate::from_key),
+            memory: memory::from_json(row.get(19).ok().flatten()),
+            power: PowerSpec {
+                default_w: row.get::<_, Option<f32>>(9).ok().flatten(),
+                ctdp_min_w: row.get::<_, Option<f32>>(20).ok().flatten(),
+                ctdp_max_w: row.get::<_, Option<f32>>(21).ok().flatten(),
+            },
+            socket: row.get::<_, Option<String>>(22).ok().flatten(),
+            user_provided: row.get::<_, Option<bool>>(23).ok().flatten().unwrap_or(false),
         }
+    }
+}
 
+impl crate::cpu::Database for IntelData {
+    fn fetch_in(db: &CpuDatabase, keyword: &str, column: EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error> {
         let column = match column {
             EnumCPUData::Intel(intel_col) => intel_col,
-            EnumCPUData::AMD(_) => panic!("Cannot use AMD enum for Intel query")
+            EnumCPUData::AMD(_) => {
+                log::debug!("Cannot use an AMD column for an Intel query");
+                return Ok(None);
+            }
         };
-        let conn = Connection::open(Self::DATABASE)?;
+        let conn = db.connection();
 
         let query = format!(
             "SELECT * FROM intel_cpus WHERE {} LIKE ?1",
@@ -103,202 +331,73 @@ impl crate::cpu::Database for IntelData {
         let mut rows = stmt.query(params![format!("%{}%", keyword)])?;
 
         if let Some(row) = rows.next()? {
-            let intel_data = IntelData {
-                name: row.get(0).unwrap_or_else(|e| {
-                    println!("Error getting product: {}", e);
-                    "Unknown".to_string()
-                }),
-                status: match row.get::<_, String>(1).unwrap_or_else(|e| {
-                    println!("Error getting status: {}", e);
-                    "Announced".to_string()
-                }).as_str() {
-                    "Launched" => ProductStatus::Launched,
-                    "Discontinued" => ProductStatus::Discontinued,
-                    _ => ProductStatus::Announced,
-                },
-                release_date: row.get(2).unwrap_or_else(|e| {
-                    println!("Error getting release_date: {}", e);
-                    "Unknown".to_string()
-                }),
-                code_name: row.get(3).unwrap_or_else(|e| {
-                    println!("Error getting code_name: {}", e);
-                    "Unknown".to_string()
-                }),
-                cores: row.get::<_, String>(4).ok().and_then(|s| s.parse().ok()),
-                threads: row.get::<_, String>(5).ok().and_then(|s| s.parse().ok()),
-                lithography: row.get::<_, String>(6).ok().and_then(|s| s.parse().ok()),
-                max_turbo_freq: row.get::<_, String>(7).ok().and_then(|s| s.parse().ok()),
-                base_freq: row.get::<_, String>(8).ok().and_then(|s| s.parse().ok()),
-                thermal_design_power: row.get::<_, String>(9).ok().and_then(|s| s.parse().ok()),
-                cache: row.get::<_, String>(10).ok().and_then(|s| s.parse().ok()),
-                cache_info: row.get(11).unwrap_or_else(|e| {
-                    println!("Error getting cache_info: {}", e);
-                    "Unknown".to_string()
-                }),
-                max_memory_size: row.get::<_, String>(12).ok().and_then(|s| s.parse().ok()),
-                memory_types: row.get::<_, String>(13).unwrap_or_else(|e| {
-                    println!("Error getting memory_types: {}", e);
-                    "Unknown".to_string()
-                }).split(',').map(|s| s.trim().to_string()).collect(),
-                max_memory_speed: row.get::<_, String>(14).ok().and_then(|s| s.parse().ok()),
-                graphics: row.get::<_, String>(15).ok(),
-            };
+            let intel_data = IntelData::from_row(row);
 
             Ok(Some(eCPUDetails::Intel(intel_data)))
         } else {
-            println!("No rows found of keyword [{}] in row [{}]", keyword, column.to_string());
+            log::debug!("No rows found of keyword [{}] in column [{}]", keyword, column.to_string());
             Ok(None)
         }
     }
-
-    fn gen_db() {
-        let dir = Self::CPU_INFO_FOLDER;
-        fs::create_dir_all("./res/db").expect("Unable to create the directory");
-        let folder1 = dir.to_string();
-
-        let folder2 = format!("{}/v1_1", folder1);
-
-        let folder3 = format!("{}/v1_2", folder1);
-
-        let file_list1 = Self::get_file_names(folder1)
-            .expect("Error occurred while reading directory")
-            .into_iter()
-            .filter(|file| file.ends_with(".csv"))
-            .collect::<Vec<_>>();
-        let file_list2 = Self::get_file_names(folder2)
-            .expect("Error occurred while reading directory")
-            .into_iter()
-            .filter(|file| file.ends_with(".csv"))
-            .collect::<Vec<_>>();
-        let file_list3 = Self::get_file_names(folder3)
-            .expect("Error occurred while reading directory")
-            .into_iter()
-            .filter(|file| file.ends_with(".csv"))
-            .collect::<Vec<_>>();
-
-        let mut temp: Vec<String> = Vec::new();
-        for file in file_list1 {
-            let thing = format!("./res/cpu/intel/intel-processors/{}", file);
-            temp.push(thing);
-        }
-
-        for file in file_list2 {
-            let thing = format!("./res/cpu/intel/intel-processors/v1_1/{}", file);
-            temp.push(thing);
-        }
-
-        for file in file_list3 {
-            let thing = format!("./res/cpu/intel/intel-processors/v1_2/{}", file);
-            temp.push(thing);
-        }
-
-        Self::save_to_database(temp).expect("Failed to save to database");
-    }
 }
 
 impl crate::cpu::private::Database for IntelData {
-    const DATABASE: &'static str = "res/db/cpu.db";
+    const SOURCES: &'static [EmbeddedSource] = INTEL_SOURCES;
 
-    const CPU_INFO_FOLDER: &'static str = "res/cpu/intel/intel-processors";
+    const TABLE: &'static str = "intel_cpus";
 
-    fn get_file_names(directory: String) -> Result<Vec<String>, std::io::Error> {
-        // Read the directory contents
-        let paths = fs::read_dir(directory)?;
+    const VENDOR: Vendor = Vendor::Intel;
 
-        // Collect and convert file names to a vector of Strings
-        let file_names: Vec<String> = paths
-            .filter_map(|entry| {
-                entry.ok().and_then(|dir_entry| {
-                    dir_entry.file_name().into_string().ok()
-                })
-            })
-            .collect();
-
-        Ok(file_names)
-    }
-
-    fn save_to_database(files: Vec<String>) -> Result<(), rusqlite::Error> {
-        // Initialize database connection
-        let mut conn = Connection::open(Self::DATABASE)?;
-
-        // Create table
+    fn create_table(conn: &Connection) -> Result<(), rusqlite::Error> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS intel_cpus (
                 product TEXT PRIMARY KEY,
                 status TEXT,
                 release_date TEXT,
                 code_name TEXT,
-                cores TEXT,
-                threads TEXT,
-                lithography TEXT,
-                max_turbo_freq TEXT,
-                base_freq TEXT,
-                thermal_design_power TEXT,
-                cache TEXT,
+                cores INTEGER,
+                threads INTEGER,
+                lithography INTEGER,
+                max_turbo_freq INTEGER,
+                base_freq INTEGER,
+                thermal_design_power INTEGER,
+                cache INTEGER,
                 cache_info TEXT,
-                max_memory_size TEXT,
+                max_memory_size INTEGER,
                 memory_types TEXT,
-                max_memory_speed TEXT,
-                graphics TEXT
+                max_memory_speed INTEGER,
+                graphics TEXT,
+                extra TEXT,
+                caches TEXT,
+                launch_key INTEGER,
+                memory TEXT,
+                ctdp_min_w REAL,
+                ctdp_max_w REAL,
+                socket TEXT,
+                user_provided INTEGER
             )",
             [],
         )?;
+        Ok(())
+    }
 
-        let mut intel_items = Vec::new();
-
-        // Existing file parsing logic
-        for file in files {
-            let file_content = match fs::read_to_string(&file) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!("Error reading file {}: {}", file, e);
-                    continue;
-                }
-            };
+    fn save_to_database(conn: &mut Connection, sources: &[CsvSource]) -> Result<ImportReport, rusqlite::Error> {
+        Self::prepare_schema(conn)?;
 
-            for line in file_content.lines().skip(1) {
-                let fields = Self::split_csv_line(line);
+        let mut report = ImportReport::default();
+        let mut imported = ImportedRows::default();
+        let mut intel_items = Vec::new();
 
-                // Pad fields array if incomplete
-                let mut fields_padded = vec!["N/A".to_string(); 16];
-                for (i, field) in fields.iter().enumerate() {
-                    if i < 16 {
-                        fields_padded[i] = field.to_string();
+        for source in sources {
+            for row in read_rows(source, header::ALL, &mut report) {
+                let row = imported.merge_overlay(header::PRODUCT, row);
+                match IntelData::from_csv_row(&row) {
+                    Some(item) => {
+                        imported.add(&item.name, &row, &mut report);
+                        intel_items.push(item);
                     }
+                    None => report.skip(&source.path, row.line, format!("no \"{}\" value", header::PRODUCT)),
                 }
-
-                let intel_item = IntelData {
-                    name: fields_padded[0].trim().to_string(),
-                    status: match fields_padded[1].trim() {
-                        "Launched" => ProductStatus::Launched,
-                        "Discontinued" => ProductStatus::Discontinued,
-                        _ => ProductStatus::Announced,
-                    },
-                    release_date: fields_padded[2].trim().to_string(),
-                    code_name: fields_padded[3].trim().to_string(),
-                    cores: fields_padded[4].trim().parse::<usize>().ok(),
-                    threads: fields_padded[5].trim().parse::<usize>().ok(),
-                    lithography: fields_padded[6].trim().parse::<usize>().ok(),
-                    max_turbo_freq: fields_padded[7].trim().parse::<usize>().ok(),
-                    base_freq: fields_padded[8].trim().parse::<usize>().ok(),
-                    thermal_design_power: fields_padded[9].trim().parse::<usize>().ok(),
-                    cache: fields_padded[10].trim().parse::<usize>().ok(),
-                    cache_info: fields_padded[11].trim().to_string(),
-                    max_memory_size: fields_padded[12].trim().parse::<usize>().ok(),
-                    memory_types: if fields_padded[13].trim() == "N/A" {
-                        Vec::new()
-                    } else {
-                        fields_padded[13].split(',').map(|s| s.trim().to_string()).collect()
-                    },
-                    max_memory_speed: fields_padded[14].trim().parse::<usize>().ok(),
-                    graphics: if fields_padded[15].trim() == "N/A" {
-                        None
-                    } else {
-                        Some(fields_padded[15].trim().to_string())
-                    },
-                };
-
-                intel_items.push(intel_item);
             }
         }
 
@@ -311,8 +410,11 @@ impl crate::cpu::private::Database for IntelData {
                         product, status, release_date, code_name, cores, threads,
                         lithography, max_turbo_freq, base_freq, thermal_design_power,
                         cache, cache_info, max_memory_size, memory_types,
-                        max_memory_speed, graphics
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)"
+                        max_memory_speed, graphics, extra, caches, launch_key, memory, ctdp_min_w, ctdp_max_w, socket, user_provided
+                    ) VALUES (
+                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12,
+                        ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24
+                    )"
                 )?;
 
                 for item in &intel_items {  // Use reference to avoid moving item
@@ -321,23 +423,120 @@ impl crate::cpu::private::Database for IntelData {
                         &format!("{:?}", item.status),
                         &item.release_date,
                         &item.code_name,
-                        &item.cores.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.threads.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.lithography.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.max_turbo_freq.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.base_freq.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.thermal_design_power.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.cache.map(|v| v.to_string()).unwrap_or_default(),
+                        &item.cores,
+                        &item.threads,
+                        &item.lithography,
+                        &item.max_turbo_freq,
+                        &item.base_freq,
+                        &item.thermal_design_power,
+                        &item.cache,
                         &item.cache_info,
-                        &item.max_memory_size.map(|v| v.to_string()).unwrap_or_default(),
+                        &item.max_memory_size,
                         &item.memory_types.join(","),
-                        &item.max_memory_speed.map(|v| v.to_string()).unwrap_or_default(),
-                        &item.graphics.as_deref().unwrap_or_default()
+                        &item.max_memory_speed,
+                        &item.graphics.as_deref().unwrap_or_default(),
+                        &extra_to_json(&item.extra),
+                        &cache::to_json(&item.caches),
+                        &item.launched.map(|date| date.key()),
+                        &memory::to_json(&item.memory),
+                        &item.power.ctdp_min_w,
+                        &item.power.ctdp_max_w,
+                        &item.socket,
+                        &item.user_provided
                     ])?;
                 }
             }
             tx.commit()?;
         }
-        Ok(())
+        Ok(report)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::amd::eAMDData;
+    use crate::cpu::spec::Vendor;
+    use crate::cpu::Database;
+    use crate::tests::{source, TempDir};
+
+    const UPSTREAM: &str = "\
+Product,Status,Code Name,Cores,Threads,TDP (W),Cache (MB)
+Core i9-13900K,Launched,Raptor Lake,24,32,125,36
+";
+
+    fn fetch(conn: &Connection, product: &str) -> IntelData {
+        conn.query_row("SELECT * FROM intel_cpus WHERE product = ?1", [product], |row| Ok(IntelData::from_row(row)))
+            .unwrap()
+    }
+
+    #[test]
+    fn fetch_with_an_amd_column() {
+        let db = CpuDatabase::in_memory().unwrap();
+        assert!(IntelData::fetch_in(&db, "Core", EnumCPUData::AMD(eAMDData::Name)).unwrap().is_none());
+    }
+
+    #[test]
+    fn overlay_keeps_upstream_columns() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let sources = [
+            source("upstream.csv", UPSTREAM, false),
+            source("overlay.csv", "Product,TDP (W)\nCore i9-13900K,253\n", true),
+        ];
+
+        let report = IntelData::save_to_database(&mut conn, &sources).unwrap();
+        let cpu = fetch(&conn, "Core i9-13900K");
+
+        assert_eq!(cpu.thermal_design_power, Some(253));
+        assert_eq!((cpu.cores, cpu.threads, cpu.cache), (Some(24), Some(32), Some(36 * 1024 * 1024)));
+        assert_eq!(cpu.code_name, "Raptor Lake");
+        assert_eq!(cpu.socket.as_deref(), Some("LGA1700"));
+        assert!(cpu.user_provided);
+
+        assert_eq!(report.collisions.len(), 1);
+        let collision = &report.collisions[0];
+        assert_eq!((collision.path.as_str(), collision.previous_path.as_str()), ("overlay.csv", "upstream.csv"));
+        assert_eq!(collision.overwritten, [("TDP (W)".to_string(), "125".to_string())].into_iter().collect());
+    }
+
+    #[test]
+    fn overlays_merge_over_each_other() {
+        let dir = TempDir::new();
+        let first = dir.write("first.csv", "Product,Code Name,Cores\nCore i5-99999X,Test Lake,8\n");
+        let second = dir.write("second.json", r#"[{"Product": "Core i5-99999X", "Threads": 16}]"#);
+        let mut db = CpuDatabase::in_memory().unwrap();
+
+        let report = db.add_overlay(Vendor::Intel, &first).unwrap();
+        assert_eq!(report.rows_imported(), 1);
+        assert!(report.collisions.is_empty());
+        assert_eq!(report.files.iter().map(|file| file.path.clone()).collect::<Vec<_>>(), vec![first.display().to_string()]);
+
+        let report = db.add_overlay(Vendor::Intel, &second).unwrap();
+        assert_eq!(report.rows_imported(), 1);
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!(report.collisions[0].previous_path, first.display().to_string());
+
+        let cpu = fetch(db.connection(), "Core i5-99999X");
+        assert_eq!((cpu.cores, cpu.threads), (Some(8), Some(16)));
+        assert_eq!(cpu.code_name, "Test Lake");
+
+        assert!(db.remove_overlay(second.display().to_string()).unwrap());
+        assert_eq!(fetch(db.connection(), "Core i5-99999X").threads, None);
+    }
+
+    #[test]
+    fn desktop_sockets() {
+        assert_eq!(infer_socket("Core i9-13900K", "Raptor Lake"), Some("LGA1700"));
+        assert_eq!(infer_socket("Core i5-12400F", "Products formerly Alder Lake"), Some("LGA1700"));
+        assert_eq!(infer_socket("Core i7-8700", "Coffee Lake"), Some("LGA1151"));
+        assert_eq!(infer_socket("Pentium Gold G6400", "Comet Lake"), Some("LGA1200"));
+        assert_eq!(infer_socket("Celeron G5905", "Comet Lake"), Some("LGA1200"));
+
+        // Mobile models, HEDT and server code names and unknown code names
+        assert_eq!(infer_socket("Core i7-1065G7", "Ice Lake"), None);
+        assert_eq!(infer_socket("Core i7-13700H", "Raptor Lake"), None);
+        assert_eq!(infer_socket("Core i9-10900X", "Cascade Lake-X"), None);
+        assert_eq!(infer_socket("Core i7-6950X", "Broadwell-E"), None);
+        assert_eq!(infer_socket("Core i9-13900K", "Unknown Lake"), None);
+        assert_eq!(infer_socket("Pentium Gold", "Comet Lake"), None);
+    }
+}
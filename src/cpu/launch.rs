@@ -0,0 +1,278 @@
+//! Launch dates parsed from the `release_date` / `launch_date` strings of the datasets.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// When a CPU was launched, as precise as the dataset is. Sorts chronologically, with less
+/// precise dates first within their year (or quarter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LaunchDate {
+    pub year: u16,
+    /// 1 to 4, also set when only the month is known
+    pub quarter: Option<u8>,
+    /// 1 to 12
+    pub month: Option<u8>,
+}
+
+/// Expands a two digit year, e.g. `19` to `2019` and `98` to `1998`.
+fn full_year(year: u16) -> u16 {
+    match year {
+        0..=70 => 2000 + year,
+        71..=99 => 1900 + year,
+        _ => year,
+    }
+}
+
+fn month_from_name(name: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    MONTHS
+        .iter()
+        .position(|month| name.starts_with(month))
+        .map(|i| i as u8 + 1)
+}
+
+impl LaunchDate {
+    /// The only way to build a date with a quarter or month, `None` if the quarter is not 1 to 4,
+    /// the month is not 1 to 12 or the month is not in the quarter.
+    fn new(year: u16, quarter: Option<u8>, month: Option<u8>) -> Option<Self> {
+        let quarter = match (quarter, month) {
+            (_, Some(month)) if !(1..=12).contains(&month) => return None,
+            (Some(quarter), Some(month)) if quarter != (month - 1) / 3 + 1 => return None,
+            (None, Some(month)) => Some((month - 1) / 3 + 1),
+            (Some(quarter), _) if !(1..=4).contains(&quarter) => return None,
+            (quarter, _) => quarter,
+        };
+
+        Some(LaunchDate { year, quarter, month })
+    }
+
+    pub fn year(year: u16) -> Self {
+        LaunchDate { year, quarter: None, month: None }
+    }
+
+    /// `None` unless `quarter` is 1 to 4.
+    pub fn quarter(year: u16, quarter: u8) -> Option<Self> {
+        Self::new(year, Some(quarter), None)
+    }
+
+    /// `None` unless `month` is 1 to 12.
+    pub fn month(year: u16, month: u8) -> Option<Self> {
+        Self::new(year, None, Some(month))
+    }
+
+    /// Parses the date formats found in the datasets: `"Q3'19"`, `"Q3 2019"`, `"4/6/2023"`
+    /// (month/day/year), `"2023-04-06"`, `"04/2023"`, `"Apr 2023"`, `"April 6, 2023"` and `"2023"`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return None;
+        }
+
+        // "Q3'19", "Q3 '19", "Q3 2019"
+        if let Some(rest) = s.strip_prefix(['Q', 'q']) {
+            let quarter = rest.chars().next()?.to_digit(10)? as u8;
+            let year = rest[1..].trim_start_matches([' ', '\'']).trim().parse::<u16>().ok()?;
+            return Self::quarter(full_year(year), quarter);
+        }
+
+        // "April 6, 2023", "Apr 2023"
+        if let Some(month) = s.split_whitespace().next().and_then(month_from_name) {
+            let year = s.rsplit(|c: char| !c.is_ascii_digit())
+                .find(|part| part.len() == 4)?
+                .parse::<u16>()
+                .ok()?;
+            return Self::month(year, month);
+        }
+
+        let numbers = s
+            .split(['/', '-', '.', ' '])
+            .map(|part| part.trim().parse::<u16>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let (year, month) = match numbers.as_slice() {
+            [year] => (*year, None),
+            // "2023-04-06"
+            [year, month, _] if *year > 31 => (*year, Some(*month)),
+            // "4/6/2023"
+            [month, _, year] => (*year, Some(*month)),
+            // "2023-04"
+            [year, month] if *year > 12 && *month <= 12 => (*year, Some(*month)),
+            // "04/2023"
+            [month, year] => (*year, Some(*month)),
+            _ => return None,
+        };
+
+        let year = full_year(year);
+        match month {
+            Some(month) => Self::month(year, u8::try_from(month).ok()?),
+            None => Some(Self::year(year)),
+        }
+    }
+
+    /// The date as a single sortable number, `yyyyqmm` with 0 for unknown parts, e.g. `2019300`
+    /// for Q3 2019. This is what the `launch_key` column holds.
+    pub fn key(&self) -> u32 {
+        self.year as u32 * 1000 + self.quarter.unwrap_or(0) as u32 * 100 + self.month.unwrap_or(0) as u32
+    }
+
+    pub fn from_key(key: u32) -> Option<Self> {
+        let year = (key / 1000) as u16;
+        let quarter = (key / 100 % 10) as u8;
+        let month = (key % 100) as u8;
+
+        if year == 0 {
+            return None;
+        }
+        Self::new(year, (quarter > 0).then_some(quarter), (month > 0).then_some(month))
+    }
+
+    /// The smallest key of any date this one could stand for, e.g. January for a year.
+    pub(crate) fn first_key(&self) -> u32 {
+        self.key()
+    }
+
+    /// The largest key of any date this one could stand for, e.g. Q4 December for a year.
+    pub(crate) fn last_key(&self) -> u32 {
+        let year = self.year as u32 * 1000;
+        match (self.quarter, self.month) {
+            (_, Some(_)) => self.key(),
+            (Some(quarter), None) => year + quarter as u32 * 100 + quarter as u32 * 3,
+            (None, None) => year + 412,
+        }
+    }
+
+    /// Months since year 0, using the middle of the quarter or year when the month is unknown.
+    fn months(&self) -> f32 {
+        let month = match (self.quarter, self.month) {
+            (_, Some(month)) => month as f32,
+            (Some(quarter), None) => quarter as f32 * 3.0 - 1.0,
+            (None, None) => 6.5,
+        };
+        self.year as f32 * 12.0 + month
+    }
+
+    /// Years between the launch and `today`.
+    pub fn age_years_on(&self, today: LaunchDate) -> f32 {
+        (today.months() - self.months()) / 12.0
+    }
+
+    /// Years since the launch, based on the system clock.
+    pub fn age_years(&self) -> f32 {
+        self.age_years_on(today())
+    }
+}
+
+/// The current month according to the system clock.
+fn today() -> LaunchDate {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    LaunchDate::month(year as u16, month as u8).unwrap_or(LaunchDate::year(year as u16))
+}
+
+impl fmt::Display for LaunchDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.quarter, self.month) {
+            (_, Some(month)) => write!(f, "{}-{:02}", self.year, month),
+            (Some(quarter), None) => write!(f, "Q{} {}", quarter, self.year),
+            (None, None) => write!(f, "{}", self.year),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_formats() {
+        let q3 = LaunchDate::quarter(2022, 3);
+        let september = LaunchDate::month(2022, 9);
+        let april = LaunchDate::month(2023, 4);
+
+        let cases = [
+            ("Q3'22", q3),
+            ("Q3 '22", q3),
+            ("Q3 2022", q3),
+            ("q3'22", q3),
+            ("2022-09", september),
+            ("09/2022", september),
+            ("September 2022", september),
+            ("Sep 2022", september),
+            ("April 6, 2023", april),
+            ("4/6/2023", april),
+            ("2023-04-06", april),
+            ("2022", Some(LaunchDate::year(2022))),
+            (" 2022 ", Some(LaunchDate::year(2022))),
+            ("98", Some(LaunchDate::year(1998))),
+        ];
+
+        for (s, date) in cases {
+            assert!(date.is_some());
+            assert_eq!(LaunchDate::parse(s), date, "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let cases = ["", "   ", "N/A", "Launched", "Q5'22", "Q0 2022", "Q3", "2022-13", "0/2022", "13/2022", "1/2/3/4", "September"];
+
+        for s in cases {
+            assert_eq!(LaunchDate::parse(s), None, "{}", s);
+        }
+    }
+
+    #[test]
+    fn out_of_range_parts() {
+        assert_eq!(LaunchDate::month(2022, 0), None);
+        assert_eq!(LaunchDate::month(2022, 13), None);
+        assert_eq!(LaunchDate::quarter(2022, 0), None);
+        assert_eq!(LaunchDate::quarter(2022, 5), None);
+        assert_eq!(LaunchDate::month(2022, 12).and_then(|date| date.quarter), Some(4));
+    }
+
+    #[test]
+    fn keys() {
+        let q3 = LaunchDate::quarter(2022, 3).unwrap();
+        let september = LaunchDate::month(2022, 9).unwrap();
+        let year = LaunchDate::year(2022);
+
+        assert_eq!(september.key(), 2022309);
+        assert_eq!((q3.first_key(), q3.last_key()), (2022300, 2022309));
+        assert_eq!((year.first_key(), year.last_key()), (2022000, 2022412));
+        assert!(year < q3 && q3 < september);
+
+        for date in [q3, september, year] {
+            assert_eq!(LaunchDate::from_key(date.key()), Some(date));
+        }
+        // No year, month 13, quarter 5 and a month outside its quarter
+        for key in [309, 2022013, 2022500, 2022105] {
+            assert_eq!(LaunchDate::from_key(key), None, "{}", key);
+        }
+    }
+
+    #[test]
+    fn age() {
+        let today = LaunchDate::month(2024, 9).unwrap();
+
+        assert_eq!(LaunchDate::month(2022, 9).unwrap().age_years_on(today), 2.0);
+        // The middle of the quarter or year
+        assert_eq!(LaunchDate::quarter(2023, 4).unwrap().age_years_on(LaunchDate::month(2024, 11).unwrap()), 1.0);
+        assert!((LaunchDate::year(2020).age_years_on(today) - 50.5 / 12.0).abs() < 1e-4);
+    }
+}
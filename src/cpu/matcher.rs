@@ -0,0 +1,249 @@
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::query::{CpuQuery, Op, Queryable};
+
+/// How many runner-up candidates a [`MatchResult`] keeps.
+const MAX_RUNNER_UPS: usize = 5;
+
+/// A database record together with how well it matches the brand string.
+#[derive(Debug)]
+pub struct ModelMatch<T> {
+    pub record: T,
+    /// Between 0.0 (unrelated) and 1.0 (identical after normalization)
+    pub confidence: f32,
+}
+
+/// The best database match for a brand string plus the next best candidates.
+#[derive(Debug)]
+pub struct MatchResult<T> {
+    pub best: ModelMatch<T>,
+    pub runner_ups: Vec<ModelMatch<T>>,
+}
+
+/// Strips trademark symbols, vendor names and marketing suffixes from a brand string, e.g.
+/// `"AMD Ryzen 7 7800X3D 8-Core Processor"` and `"AMD Ryzen™ 7 7800X3D"` both become
+/// `"Ryzen 7 7800X3D"`, and `"Intel(R) Core(TM) i7-1065G7 CPU @ 1.30GHz"` becomes `"Core i7-1065G7"`.
+pub fn normalize_brand(brand: &str) -> String {
+    let mut brand = brand.to_string();
+    for symbol in ["(TM)", "(tm)", "(R)", "(r)", "™", "®"] {
+        brand = brand.replace(symbol, " ");
+    }
+
+    // Clock speed and integrated graphics suffixes
+    for cut in ["@", " with ", " w/ "] {
+        if let Some(pos) = brand.find(cut) {
+            brand.truncate(pos);
+        }
+    }
+
+    let words = brand
+        .split_whitespace()
+        .filter(|word| !is_noise_word(word))
+        .collect::<Vec<_>>();
+
+    words.join(" ")
+}
+
+fn is_noise_word(word: &str) -> bool {
+    let lower = word.to_lowercase();
+
+    if matches!(lower.as_str(), "intel" | "amd" | "processor" | "cpu" | "apu" | "gen") {
+        return true;
+    }
+
+    // "8-Core", "Six-Core", "Quad-Core"
+    if lower.ends_with("-core") {
+        return true;
+    }
+
+    // "12th" in "12th Gen Intel Core i7-12700K"
+    lower.len() > 2
+        && lower.ends_with("th")
+        && lower[..lower.len() - 2].chars().all(|c| c.is_ascii_digit())
+}
+
+fn tokens(normalized: &str) -> Vec<String> {
+    normalized.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// The model number of a normalized brand split into its digits and suffix, e.g.
+/// `("1065", "g7")` for `"Core i7-1065G7"` and `("7800", "x3d")` for `"Ryzen 7 7800X3D"`.
+fn model_number(tokens: &[String]) -> Option<(String, String)> {
+    tokens.iter().rev().find_map(|token| {
+        // "i7-1065g7" and "e5-2690" carry the model number after the dash
+        let token = token.rsplit('-').next().unwrap_or(token);
+        let start = token.find(|c: char| c.is_ascii_digit())?;
+        let digits = token[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+
+        if digits.len() < 3 {
+            return None;
+        }
+
+        let suffix = token[start + digits.len()..].to_string();
+        Some((digits, suffix))
+    })
+}
+
+/// Scores how well a database product name matches a brand string, between 0.0 and 1.0.
+///
+/// Candidates with a different model number never match, and a different suffix (K/KF/F/X3D/HX, ...)
+/// costs most of the score, so that `"Core i9-13900K"` does not match `"Core i9-13900KF"`.
+pub fn score(brand: &str, candidate: &str) -> f32 {
+    let brand = normalize_brand(brand);
+    let candidate = normalize_brand(candidate);
+
+    if brand.eq_ignore_ascii_case(&candidate) {
+        return 1.0;
+    }
+
+    let brand_tokens = tokens(&brand);
+    let candidate_tokens = tokens(&candidate);
+
+    let shared = brand_tokens.iter().filter(|t| candidate_tokens.contains(t)).count();
+    let total = brand_tokens.len() + candidate_tokens.len() - shared;
+    let similarity = if total == 0 { 0.0 } else { shared as f32 / total as f32 };
+
+    match (model_number(&brand_tokens), model_number(&candidate_tokens)) {
+        (Some((digits, suffix)), Some((candidate_digits, candidate_suffix))) => {
+            if digits != candidate_digits {
+                0.0
+            } else if suffix == candidate_suffix {
+                0.6 + 0.35 * similarity
+            } else {
+                0.15 * similarity
+            }
+        }
+        _ => 0.9 * similarity,
+    }
+}
+
+/// Finds the records of the default [`CpuDatabase`] that best match a CPU brand string.
+///
+/// Returns `None` if no record shares the model number (or, without one, any word) with the brand.
+pub fn best_match<T: Queryable>(brand: &str) -> Result<Option<MatchResult<T>>, rusqlite::Error> {
+    best_match_in(&CpuDatabase::open_default()?, brand)
+}
+
+/// Like [`best_match`], but looks the brand up in `db`.
+pub fn best_match_in<T: Queryable>(db: &CpuDatabase, brand: &str) -> Result<Option<MatchResult<T>>, rusqlite::Error> {
+    let normalized = normalize_brand(brand);
+    let brand_tokens = tokens(&normalized);
+
+    let keyword = match model_number(&brand_tokens) {
+        Some((digits, _)) => digits,
+        None => match brand_tokens.last() {
+            Some(token) => token.clone(),
+            None => return Ok(None),
+        },
+    };
+
+    let candidates = CpuQuery::<T>::new()
+        .filter(T::name_column(), Op::Like, format!("%{}%", keyword))
+        .fetch_in(db)?;
+
+    let mut matches = candidates
+        .into_iter()
+        .map(|record| {
+            let confidence = score(&normalized, record.name());
+            ModelMatch { record, confidence }
+        })
+        .filter(|m| m.confidence > 0.0)
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    matches.truncate(MAX_RUNNER_UPS + 1);
+
+    log::debug!(
+        "Matched [{}] against {} candidate(s)",
+        normalized,
+        matches.len()
+    );
+
+    if matches.is_empty() {
+        return Ok(None);
+    }
+
+    let best = matches.remove(0);
+    Ok(Some(MatchResult { best, runner_ups: matches }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::intel::IntelData;
+
+    /// The candidate with the highest score for `brand`.
+    fn best<'a>(brand: &str, candidates: &[&'a str]) -> &'a str {
+        candidates
+            .iter()
+            .copied()
+            .max_by(|a, b| score(brand, a).total_cmp(&score(brand, b)))
+            .unwrap()
+    }
+
+    #[test]
+    fn normalize_brand_strips_noise() {
+        let cases = [
+            ("Intel(R) Core(TM) i7-1065G7 CPU @ 1.30GHz", "Core i7-1065G7"),
+            ("13th Gen Intel(R) Core(TM) i9-13900K", "Core i9-13900K"),
+            ("Intel® Core™ i9-13900KS Processor", "Core i9-13900KS"),
+            ("AMD Ryzen 7 7800X3D 8-Core Processor", "Ryzen 7 7800X3D"),
+            ("AMD Ryzen™ 7 7840HS w/ Radeon™ 780M Graphics", "Ryzen 7 7840HS"),
+            ("AMD Ryzen 5 5600G with Radeon Graphics", "Ryzen 5 5600G"),
+        ];
+
+        for (brand, normalized) in cases {
+            assert_eq!(normalize_brand(brand), normalized, "{}", brand);
+        }
+    }
+
+    #[test]
+    fn score_picks_the_exact_suffix() {
+        let intel = ["Intel® Core™ i9-13900K Processor", "Intel® Core™ i9-13900KF Processor", "Intel® Core™ i9-13900KS Processor"];
+        let amd = ["AMD Ryzen™ 7 7800X", "AMD Ryzen™ 7 7800X3D"];
+
+        let cases = [
+            ("13th Gen Intel(R) Core(TM) i9-13900K", &intel[..], intel[0]),
+            ("13th Gen Intel(R) Core(TM) i9-13900KF", &intel[..], intel[1]),
+            ("13th Gen Intel(R) Core(TM) i9-13900KS", &intel[..], intel[2]),
+            ("AMD Ryzen 7 7800X 8-Core Processor", &amd[..], amd[0]),
+            ("AMD Ryzen 7 7800X3D 8-Core Processor", &amd[..], amd[1]),
+        ];
+
+        for (brand, candidates, expected) in cases {
+            assert_eq!(best(brand, candidates), expected, "{}", brand);
+            assert_eq!(score(brand, expected), 1.0, "{}", brand);
+        }
+    }
+
+    #[test]
+    fn score_table() {
+        let cases = [
+            // Identical after normalization
+            ("Intel(R) Core(TM) i7-1065G7 CPU @ 1.30GHz", "Intel® Core™ i7-1065G7 Processor", 1.0, 1.0),
+            // Same model number, other suffix
+            ("13th Gen Intel(R) Core(TM) i9-13900K", "Intel® Core™ i9-13900KF Processor", 0.0, 0.15),
+            ("AMD Ryzen 7 7800X3D 8-Core Processor", "AMD Ryzen™ 7 7800X", 0.0, 0.15),
+            // Same suffix, extra words
+            ("Intel(R) Core(TM) i7-1065G7 CPU @ 1.30GHz", "Intel® Core™ i7-1065G7 Processor (8M Cache)", 0.6, 0.95),
+            // Other model number
+            ("Intel(R) Core(TM) i7-1065G7 CPU @ 1.30GHz", "Intel® Core™ i7-1165G7 Processor", 0.0, 0.0),
+            ("AMD Ryzen 9 7950X 16-Core Processor", "AMD Ryzen™ 7 7800X3D", 0.0, 0.0),
+        ];
+
+        for (brand, candidate, min, max) in cases {
+            let score = score(brand, candidate);
+            assert!((min..=max).contains(&score), "{} vs {}: {}", brand, candidate, score);
+        }
+    }
+
+    #[test]
+    fn best_match_without_candidates() {
+        let db = CpuDatabase::in_memory().unwrap();
+
+        assert!(best_match_in::<IntelData>(&db, "Intel(R) Core(TM) i7-98765X CPU @ 3.00GHz").unwrap().is_none());
+        assert!(best_match_in::<IntelData>(&db, "(R)(TM)").unwrap().is_none());
+    }
+}
@@ -0,0 +1,377 @@
+//! Supported memory parsed from the memory descriptions of the datasets, and a check whether a
+//! DIMM kit works with a CPU.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::utils::leading_number;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MemoryKind {
+    DDR3,
+    /// Low voltage DDR3
+    DDR3L,
+    DDR4,
+    DDR5,
+    LPDDR3,
+    LPDDR4,
+    LPDDR4X,
+    LPDDR5,
+    LPDDR5X,
+}
+
+impl MemoryKind {
+    /// Parses a memory kind such as `"DDR5"`, `"LPDDR4x"` or `"DDR3L"`, ignoring case.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "DDR3" => Some(MemoryKind::DDR3),
+            "DDR3L" => Some(MemoryKind::DDR3L),
+            "DDR4" => Some(MemoryKind::DDR4),
+            "DDR5" => Some(MemoryKind::DDR5),
+            "LPDDR3" => Some(MemoryKind::LPDDR3),
+            "LPDDR4" => Some(MemoryKind::LPDDR4),
+            "LPDDR4X" => Some(MemoryKind::LPDDR4X),
+            "LPDDR5" => Some(MemoryKind::LPDDR5),
+            "LPDDR5X" => Some(MemoryKind::LPDDR5X),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryKind::DDR3 => "DDR3",
+            MemoryKind::DDR3L => "DDR3L",
+            MemoryKind::DDR4 => "DDR4",
+            MemoryKind::DDR5 => "DDR5",
+            MemoryKind::LPDDR3 => "LPDDR3",
+            MemoryKind::LPDDR4 => "LPDDR4",
+            MemoryKind::LPDDR4X => "LPDDR4X",
+            MemoryKind::LPDDR5 => "LPDDR5",
+            MemoryKind::LPDDR5X => "LPDDR5X",
+        }
+    }
+}
+
+impl fmt::Display for MemoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A kind of memory a CPU supports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemorySupport {
+    pub kind: MemoryKind,
+    /// Fastest supported speed of this kind in MT/s
+    pub max_mts: Option<u32>,
+    pub channels: Option<u8>,
+    /// Largest total memory size the CPU supports
+    pub max_capacity_bytes: Option<u64>,
+    /// Whether ECC memory is supported, `None` if the dataset does not say
+    pub ecc: Option<bool>,
+}
+
+/// One entry of a memory description, e.g. `"DDR4-3200"` or `"Up to DDR5 5600 MT/s"`.
+struct Entry {
+    kind: Option<MemoryKind>,
+    mts: Option<u32>,
+}
+
+/// Splits a description like `"DDR4-3200, LPDDR4-3733"` into its entries.
+fn parse_entries(description: &str) -> Vec<Entry> {
+    description
+        .split(',')
+        .map(|entry| {
+            let words = entry.split(|c: char| c.is_whitespace() || c == '-' || c == '/');
+            let mut kind = None;
+            let mut mts = None;
+
+            for word in words {
+                if let Some(parsed) = MemoryKind::parse(word) {
+                    kind = kind.or(Some(parsed));
+                } else if let Some(speed) = leading_number(word).filter(|&speed| speed >= 100.0) {
+                    mts = mts.max(Some(speed as u32));
+                }
+            }
+
+            Entry { kind, mts }
+        })
+        .filter(|entry| entry.kind.is_some() || entry.mts.is_some())
+        .collect()
+}
+
+/// The value of the first unknown CSV column whose header contains `needle`, ignoring case.
+fn extra_value<'a>(extra: &'a BTreeMap<String, String>, needle: &str) -> Option<&'a str> {
+    extra
+        .iter()
+        .find(|(header, _)| header.to_lowercase().contains(needle))
+        .map(|(_, value)| value.as_str())
+}
+
+fn parse_yes_no(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "yes" | "true" | "supported" => Some(true),
+        "no" | "false" | "not supported" => Some(false),
+        _ => None,
+    }
+}
+
+/// Memory support of an intel-processors row from its `Memory Types` entries, e.g.
+/// `["DDR4-3200", "LPDDR4-3733"]`. Kinds listed without a speed get `max_mts`, the fastest
+/// speed of the CPU. The dataset has no ECC or channel columns, they are taken from `extra` if
+/// a newer CSV file has them.
+pub(crate) fn from_intel(
+    memory_types: &[String],
+    max_mts: Option<usize>,
+    max_capacity_bytes: Option<u64>,
+    extra: &BTreeMap<String, String>,
+) -> Vec<MemorySupport> {
+    let channels = extra_value(extra, "memory channels")
+        .and_then(leading_number)
+        .map(|channels| channels as u8);
+    let ecc = extra_value(extra, "ecc").and_then(parse_yes_no);
+
+    let mut memory: Vec<MemorySupport> = Vec::new();
+    for entry in parse_entries(&memory_types.join(", ")) {
+        let Some(kind) = entry.kind else {
+            continue;
+        };
+
+        let mts = entry.mts.or(max_mts.map(|mts| mts as u32));
+        match memory.iter_mut().find(|support| support.kind == kind) {
+            Some(support) => support.max_mts = support.max_mts.max(mts),
+            None => memory.push(MemorySupport { kind, max_mts: mts, channels, max_capacity_bytes, ecc }),
+        }
+    }
+
+    memory
+}
+
+/// Memory support of an AMDCpuData row from its `System Memory Type`, e.g. `"DDR5, LPDDR5x"`, and
+/// `System Memory Specification`, e.g. `"Up to 5600MT/s, Up to 7500MT/s"`. Speeds are matched to
+/// kinds by name if the specification names them, otherwise by position.
+pub(crate) fn from_amd(
+    memory_type: &str,
+    specification: &str,
+    channels: usize,
+    supported_technologies: &[String],
+    extra: &BTreeMap<String, String>,
+) -> Vec<MemorySupport> {
+    let kinds = memory_type.split(',').filter_map(MemoryKind::parse).collect::<Vec<_>>();
+    let speeds = parse_entries(specification);
+    let fastest = speeds.iter().filter_map(|entry| entry.mts).max();
+
+    let ecc = extra_value(extra, "ecc").and_then(parse_yes_no).or_else(|| {
+        supported_technologies
+            .iter()
+            .any(|technology| technology.to_uppercase().contains("ECC"))
+            .then_some(true)
+    });
+
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(i, &kind)| {
+            let named = speeds.iter().find(|entry| entry.kind == Some(kind)).and_then(|entry| entry.mts);
+            let positional = (speeds.len() == kinds.len()).then(|| speeds[i].mts).flatten();
+
+            MemorySupport {
+                kind,
+                max_mts: named.or(positional).or(fastest),
+                channels: (channels > 0).then_some(channels as u8),
+                max_capacity_bytes: None,
+                ecc,
+            }
+        })
+        .collect()
+}
+
+/// Serializes memory support for the `memory` column.
+pub(crate) fn to_json(memory: &[MemorySupport]) -> String {
+    serde_json::to_string(memory).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Reads the `memory` column back, treating `NULL` or invalid JSON as unknown memory support.
+pub(crate) fn from_json(json: Option<String>) -> Vec<MemorySupport> {
+    json.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+/// A set of identical memory modules, e.g. 2 × 32 GB DDR5-6000.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimmKit {
+    pub kind: MemoryKind,
+    /// Rated speed in MT/s
+    pub mts: u32,
+    pub modules: u32,
+    pub module_bytes: u64,
+    pub ecc: bool,
+}
+
+impl DimmKit {
+    pub fn total_bytes(&self) -> u64 {
+        self.modules as u64 * self.module_bytes
+    }
+}
+
+/// Why a [`DimmKit`] may not work as expected with a CPU.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KitIssue {
+    /// The CPU does not support the memory kind at all
+    UnsupportedKind(MemoryKind),
+    /// The kit is larger than the CPU can address
+    TooLarge { kit_bytes: u64, max_bytes: u64 },
+    /// The kit is faster than the CPU officially supports and runs at `max_mts` unless overclocked
+    Downclocked { kit_mts: u32, max_mts: u32 },
+    /// The kit has ECC but the CPU does not support it, the modules work without error correction
+    EccNotSupported,
+    /// The kit has ECC and the dataset does not say whether the CPU supports it
+    EccUnknown,
+}
+
+impl KitIssue {
+    /// Whether the kit does not work at all because of this issue.
+    pub fn is_blocking(&self) -> bool {
+        matches!(self, KitIssue::UnsupportedKind(_) | KitIssue::TooLarge { .. })
+    }
+}
+
+/// The result of [`check_kit`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KitCheck {
+    /// `true` unless one of the issues is blocking
+    pub supported: bool,
+    /// The speed the kit runs at without overclocking, if known
+    pub effective_mts: Option<u32>,
+    pub issues: Vec<KitIssue>,
+}
+
+/// Checks whether `kit` works with a CPU supporting `memory`.
+pub fn check_kit(memory: &[MemorySupport], kit: &DimmKit) -> KitCheck {
+    let Some(support) = memory.iter().find(|support| support.kind == kit.kind) else {
+        return KitCheck {
+            supported: false,
+            effective_mts: None,
+            issues: vec![KitIssue::UnsupportedKind(kit.kind)],
+        };
+    };
+
+    let mut issues = Vec::new();
+
+    if let Some(max_bytes) = support.max_capacity_bytes.filter(|&max_bytes| kit.total_bytes() > max_bytes) {
+        issues.push(KitIssue::TooLarge { kit_bytes: kit.total_bytes(), max_bytes });
+    }
+
+    let effective_mts = match support.max_mts {
+        Some(max_mts) if kit.mts > max_mts => {
+            issues.push(KitIssue::Downclocked { kit_mts: kit.mts, max_mts });
+            Some(max_mts)
+        }
+        Some(_) => Some(kit.mts),
+        None => None,
+    };
+
+    if kit.ecc {
+        match support.ecc {
+            Some(false) => issues.push(KitIssue::EccNotSupported),
+            None => issues.push(KitIssue::EccUnknown),
+            Some(true) => {}
+        }
+    }
+
+    KitCheck {
+        supported: !issues.iter().any(KitIssue::is_blocking),
+        effective_mts,
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn support(kind: MemoryKind, max_mts: Option<u32>, ecc: Option<bool>) -> MemorySupport {
+        MemorySupport { kind, max_mts, channels: Some(2), max_capacity_bytes: Some(128 * GIB), ecc }
+    }
+
+    fn kit(kind: MemoryKind, mts: u32, modules: u32, ecc: bool) -> DimmKit {
+        DimmKit { kind, mts, modules, module_bytes: 32 * GIB, ecc }
+    }
+
+    #[test]
+    fn memory_kinds() {
+        assert_eq!(MemoryKind::parse(" lpddr4x "), Some(MemoryKind::LPDDR4X));
+        assert_eq!(MemoryKind::parse("DDR3L"), Some(MemoryKind::DDR3L));
+        assert_eq!(MemoryKind::parse("DDR6"), None);
+        assert_eq!(MemoryKind::LPDDR5X.to_string(), "LPDDR5X");
+    }
+
+    #[test]
+    fn intel_memory_types() {
+        let mut extra = BTreeMap::new();
+        extra.insert("Max # of Memory Channels".to_string(), "2".to_string());
+        extra.insert("ECC Memory Supported ‡".to_string(), "No".to_string());
+
+        let types = ["DDR4-2933".to_string(), "DDR4-3200".to_string(), "LPDDR4".to_string()];
+        let memory = from_intel(&types, Some(3733), Some(128 * GIB), &extra);
+
+        assert_eq!(memory, vec![
+            MemorySupport { kind: MemoryKind::DDR4, max_mts: Some(3200), channels: Some(2), max_capacity_bytes: Some(128 * GIB), ecc: Some(false) },
+            // No speed of its own, the fastest speed of the CPU
+            MemorySupport { kind: MemoryKind::LPDDR4, max_mts: Some(3733), channels: Some(2), max_capacity_bytes: Some(128 * GIB), ecc: Some(false) },
+        ]);
+        assert!(from_intel(&[], Some(3200), None, &BTreeMap::new()).is_empty());
+    }
+
+    #[test]
+    fn amd_memory_speeds() {
+        let none = BTreeMap::new();
+
+        // By position
+        let memory = from_amd("DDR5, LPDDR5x", "Up to 5600MT/s, Up to 7500MT/s", 2, &[], &none);
+        let speeds = memory.iter().map(|support| (support.kind, support.max_mts)).collect::<Vec<_>>();
+        assert_eq!(speeds, vec![(MemoryKind::DDR5, Some(5600)), (MemoryKind::LPDDR5X, Some(7500))]);
+
+        // By name, in any order
+        let memory = from_amd("DDR5, LPDDR5x", "LPDDR5x-7500, DDR5-5600", 2, &[], &none);
+        let speeds = memory.iter().map(|support| (support.kind, support.max_mts)).collect::<Vec<_>>();
+        assert_eq!(speeds, vec![(MemoryKind::DDR5, Some(5600)), (MemoryKind::LPDDR5X, Some(7500))]);
+
+        // One speed for every kind
+        let memory = from_amd("DDR4", "Up to 3200MHz", 0, &["AMD Ryzen Technologies".to_string(), "ECC Support".to_string()], &none);
+        assert_eq!(memory, vec![MemorySupport { kind: MemoryKind::DDR4, max_mts: Some(3200), channels: None, max_capacity_bytes: None, ecc: Some(true) }]);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let memory = vec![support(MemoryKind::DDR5, Some(5600), None)];
+
+        assert_eq!(from_json(Some(to_json(&memory))), memory);
+        assert!(from_json(None).is_empty());
+    }
+
+    #[test]
+    fn kit_checks() {
+        let memory = [support(MemoryKind::DDR5, Some(5600), None)];
+
+        let check = check_kit(&memory, &kit(MemoryKind::DDR5, 5200, 2, false));
+        assert_eq!(check, KitCheck { supported: true, effective_mts: Some(5200), issues: vec![] });
+
+        let check = check_kit(&memory, &kit(MemoryKind::DDR5, 6000, 2, true));
+        assert!(check.supported);
+        assert_eq!(check.effective_mts, Some(5600));
+        assert_eq!(check.issues, vec![KitIssue::Downclocked { kit_mts: 6000, max_mts: 5600 }, KitIssue::EccUnknown]);
+
+        let check = check_kit(&memory, &kit(MemoryKind::DDR5, 5600, 6, false));
+        assert!(!check.supported);
+        assert_eq!(check.issues, vec![KitIssue::TooLarge { kit_bytes: 192 * GIB, max_bytes: 128 * GIB }]);
+
+        let check = check_kit(&memory, &kit(MemoryKind::DDR4, 3200, 2, false));
+        assert!(!check.supported);
+        assert_eq!(check.issues, vec![KitIssue::UnsupportedKind(MemoryKind::DDR4)]);
+
+        let check = check_kit(&[support(MemoryKind::DDR5, Some(5600), Some(false))], &kit(MemoryKind::DDR5, 5600, 2, true));
+        assert_eq!(check.issues, vec![KitIssue::EccNotSupported]);
+    }
+}
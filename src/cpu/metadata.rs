@@ -0,0 +1,267 @@
+//! Provenance of a [`CpuDatabase`](crate::cpu::database::CpuDatabase): which schema it uses, when
+//! it was generated, by which crate version and from which CSV files.
+
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use crate::cpu::amd::AMDData;
+use crate::cpu::embedded::EmbeddedSource;
+use crate::cpu::intel::IntelData;
+use crate::cpu::private::Database;
+
+/// Version of the `intel_cpus`, `amd_cpus`, `cpu_search` and `cpu_history` schema. Bump it whenever
+/// a table changes, databases with a different version are rebuilt when opened.
+pub const SCHEMA_VERSION: u32 = 10;
+
+/// A CSV file the database was generated from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    /// `"intel"` or `"amd"`
+    pub vendor: String,
+    /// Path relative to the crate root, e.g. `res/cpu/amd/AMDCpuData/amdProcessorInformation_4-12-24.csv`
+    pub path: String,
+    /// Lowercase hex SHA-256 of the file contents
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseMetadata {
+    pub schema_version: u32,
+    /// Version of hwisak-rs that generated the database
+    pub crate_version: String,
+    /// UTC timestamp of the last import, e.g. `"2024-12-01T18:30:00Z"`
+    pub generated_at: String,
+    pub sources: Vec<SourceFile>,
+}
+
+impl DatabaseMetadata {
+    /// The source files embedded into this build of the library.
+    pub fn embedded_sources() -> Vec<SourceFile> {
+        let describe = |vendor: &str, source: &EmbeddedSource| SourceFile {
+            vendor: vendor.to_string(),
+            path: source.path.to_string(),
+            sha256: sha256_hex(source.contents),
+        };
+
+        IntelData::SOURCES.iter().map(|s| describe("intel", s))
+            .chain(AMDData::SOURCES.iter().map(|s| describe("amd", s)))
+            .collect()
+    }
+
+    /// Why a database with this metadata has to be regenerated by this build of the library, or
+    /// `None` if it is up to date.
+    pub fn stale_reason(&self) -> Option<String> {
+        if self.schema_version != SCHEMA_VERSION {
+            return Some(format!("schema version {} != {}", self.schema_version, SCHEMA_VERSION));
+        }
+
+        if self.crate_version != env!("CARGO_PKG_VERSION") {
+            return Some(format!(
+                "generated by hwisak-rs {}, running {}",
+                self.crate_version,
+                env!("CARGO_PKG_VERSION")
+            ));
+        }
+
+        if self.sources != Self::embedded_sources() {
+            return Some("the embedded CSV files changed".to_string());
+        }
+
+        None
+    }
+
+    fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS metadata_sources (
+                path TEXT PRIMARY KEY,
+                vendor TEXT NOT NULL,
+                sha256 TEXT NOT NULL
+            );"
+        )
+    }
+
+    /// Reads the metadata stored in `conn`, or `None` if the database predates the metadata table.
+    pub(crate) fn read(conn: &Connection) -> Result<Option<Self>, rusqlite::Error> {
+        let has_table: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'metadata')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if !has_table {
+            return Ok(None);
+        }
+
+        let value = |key: &str| -> Result<Option<String>, rusqlite::Error> {
+            conn.query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| row.get(0))
+                .optional()
+        };
+
+        let (Some(schema_version), Some(crate_version), Some(generated_at)) =
+            (value("schema_version")?, value("crate_version")?, value("generated_at")?)
+        else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare("SELECT vendor, path, sha256 FROM metadata_sources ORDER BY rowid")?;
+        let sources = stmt
+            .query_map([], |row| {
+                Ok(SourceFile {
+                    vendor: row.get(0)?,
+                    path: row.get(1)?,
+                    sha256: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(Self {
+            schema_version: schema_version.parse().unwrap_or(0),
+            crate_version,
+            generated_at,
+            sources,
+        }))
+    }
+
+    /// Records that `conn` was just generated from the embedded sources by this build of the library.
+    pub(crate) fn write_current(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+        Self::create_tables(conn)?;
+
+        let tx = conn.transaction()?;
+        tx.execute_batch("DELETE FROM metadata; DELETE FROM metadata_sources;")?;
+        tx.execute(
+            "INSERT INTO metadata (key, value) VALUES
+                ('schema_version', ?1),
+                ('crate_version', ?2),
+                ('generated_at', strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))",
+            (SCHEMA_VERSION.to_string(), env!("CARGO_PKG_VERSION")),
+        )?;
+
+        for source in Self::embedded_sources() {
+            tx.execute(
+                "INSERT OR REPLACE INTO metadata_sources (path, vendor, sha256) VALUES (?1, ?2, ?3)",
+                (&source.path, &source.vendor, &source.sha256),
+            )?;
+        }
+
+        tx.commit()
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::database::CpuDatabase;
+    use crate::tests::TempDir;
+
+    fn current() -> DatabaseMetadata {
+        DatabaseMetadata {
+            schema_version: SCHEMA_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: "2024-12-01T18:30:00Z".to_string(),
+            sources: DatabaseMetadata::embedded_sources(),
+        }
+    }
+
+    fn count(conn: &Connection, product: &str) -> usize {
+        conn.query_row("SELECT COUNT(*) FROM intel_cpus WHERE product = ?1", [product], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn stale_reasons() {
+        assert_eq!(current().stale_reason(), None);
+
+        let metadata = DatabaseMetadata { schema_version: 0, ..current() };
+        assert_eq!(metadata.stale_reason(), Some(format!("schema version 0 != {}", SCHEMA_VERSION)));
+
+        let metadata = DatabaseMetadata { crate_version: "0.0.1".to_string(), ..current() };
+        assert_eq!(
+            metadata.stale_reason(),
+            Some(format!("generated by hwisak-rs 0.0.1, running {}", env!("CARGO_PKG_VERSION")))
+        );
+
+        let mut metadata = current();
+        metadata.sources[0].sha256 = "0".repeat(64);
+        assert_eq!(metadata.stale_reason(), Some("the embedded CSV files changed".to_string()));
+
+        let mut metadata = current();
+        metadata.sources.pop();
+        assert!(metadata.stale_reason().is_some());
+    }
+
+    #[test]
+    fn write_and_read() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(DatabaseMetadata::read(&conn).unwrap(), None);
+
+        // The table without every key is treated like no metadata
+        DatabaseMetadata::create_tables(&conn).unwrap();
+        conn.execute("INSERT INTO metadata (key, value) VALUES ('schema_version', '10')", []).unwrap();
+        assert_eq!(DatabaseMetadata::read(&conn).unwrap(), None);
+
+        DatabaseMetadata::write_current(&mut conn).unwrap();
+        let metadata = DatabaseMetadata::read(&conn).unwrap().unwrap();
+        assert_eq!(metadata, DatabaseMetadata { generated_at: metadata.generated_at.clone(), ..current() });
+        assert_eq!(metadata.generated_at.len(), "2024-12-01T18:30:00Z".len());
+        assert!(metadata.sources.iter().all(|source| source.sha256.len() == 64));
+        assert_eq!(metadata.sources.iter().filter(|source| source.vendor == "intel").count(), IntelData::SOURCES.len());
+    }
+
+    #[test]
+    fn mismatched_metadata_is_regenerated() {
+        let mismatches = [
+            "UPDATE metadata SET value = '0' WHERE key = 'schema_version'",
+            "UPDATE metadata SET value = '0.0.1' WHERE key = 'crate_version'",
+            "UPDATE metadata_sources SET sha256 = 'abc' WHERE rowid = 1",
+            "DELETE FROM metadata_sources WHERE vendor = 'amd'",
+            "DROP TABLE metadata",
+        ];
+
+        for mismatch in mismatches {
+            let dir = TempDir::new();
+            let path = dir.path().join("cpu.db");
+            {
+                let db = CpuDatabase::open(&path).unwrap();
+                db.connection().execute_batch(mismatch).unwrap();
+                db.connection()
+                    .execute("INSERT INTO intel_cpus (product) VALUES ('Stale CPU')", [])
+                    .unwrap();
+            }
+
+            let db = CpuDatabase::open(&path).unwrap();
+            let metadata = db.metadata().unwrap();
+            assert_eq!(metadata.stale_reason(), None, "{}", mismatch);
+            assert_eq!(metadata.sources, DatabaseMetadata::embedded_sources(), "{}", mismatch);
+            assert_eq!(count(db.connection(), "Stale CPU"), 0, "{}", mismatch);
+            assert_eq!(count(db.connection(), "Core i9-13900K"), 1, "{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn current_metadata_is_kept() {
+        let dir = TempDir::new();
+        let path = dir.path().join("cpu.db");
+        let generated_at = {
+            let db = CpuDatabase::open(&path).unwrap();
+            db.connection()
+                .execute("UPDATE metadata SET value = '2024-12-01T18:30:00Z' WHERE key = 'generated_at'", [])
+                .unwrap();
+            db.connection().execute("INSERT INTO intel_cpus (product) VALUES ('Kept CPU')", []).unwrap();
+            db.metadata().unwrap().generated_at
+        };
+        assert_eq!(generated_at, "2024-12-01T18:30:00Z");
+
+        let db = CpuDatabase::open(&path).unwrap();
+        assert_eq!(db.metadata().unwrap().generated_at, generated_at);
+        assert_eq!(count(db.connection(), "Kept CPU"), 1);
+    }
+}
@@ -1,14 +1,43 @@
-use std::{eprintln, format, fs, println, vec};
-use std::alloc;
 use amd::eAMDData;
-use rusqlite::{params, Connection, Error};
 use sysinfo::{CpuRefreshKind, RefreshKind};
 use crate::cpu::amd::AMDData;
-use crate::cpu::eCPUDetails::Intel;
+use crate::cpu::cache::{CacheHierarchy, CacheMismatch};
+use crate::cpu::cpuid::CpuIdInfo;
 use crate::cpu::intel::{eIntelData, IntelData};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::frequency::CpuFrequencies;
+use crate::cpu::import::ImportReport;
+use crate::cpu::metadata::DatabaseMetadata;
+use crate::cpu::security::SecurityReport;
+use crate::cpu::spec::CpuSpec;
+use crate::cpu::topology::CpuTopology;
 
 pub mod intel;
 pub mod amd;
+pub(crate) mod embedded;
+pub mod import;
+pub mod database;
+pub mod metadata;
+pub mod query;
+pub mod matcher;
+pub mod search;
+pub mod compare;
+pub mod spec;
+pub mod cache;
+pub mod launch;
+pub mod memory;
+pub mod power;
+pub mod upgrade;
+pub mod overlay;
+pub mod history;
+pub mod export;
+pub mod cpuid;
+pub mod topology;
+pub mod frequency;
+pub mod usage;
+pub mod security;
+
+pub use compare::compare;
 
 pub enum EnumCPUData {
     Intel(eIntelData),
@@ -16,57 +45,91 @@ pub enum EnumCPUData {
 }
 
 pub trait Database: private::Database {
-    fn fetch(keyword: &str, column: crate::cpu::EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error>;
-    fn gen_db();
+    /// Looks up the first CPU whose `column` contains `keyword` in the default [`CpuDatabase`].
+    fn fetch(keyword: &str, column: crate::cpu::EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error> {
+        Self::fetch_in(&CpuDatabase::open_default()?, keyword, column)
+    }
+
+    /// Like [`Database::fetch`], but looks the CPU up in `db`. `None` if `column` belongs to the
+    /// other vendor.
+    fn fetch_in(db: &CpuDatabase, keyword: &str, column: crate::cpu::EnumCPUData) -> Result<Option<eCPUDetails>, rusqlite::Error>;
+
+    /// Re-imports the embedded CPU data into the default [`CpuDatabase`].
+    fn gen_db() -> Result<ImportReport, rusqlite::Error> {
+        Self::gen_db_in(&mut CpuDatabase::open_default()?)
+    }
+
+    /// Replaces the rows of this vendor in `db` with the embedded CPU data and reports what was
+    /// imported.
+    fn gen_db_in(db: &mut CpuDatabase) -> Result<ImportReport, rusqlite::Error> {
+        let report = Self::reimport(db.connection_mut())?;
+        search::rebuild_index(db.connection())?;
+        DatabaseMetadata::write_current(db.connection_mut())?;
+        Ok(report)
+    }
 }
 
 pub(crate) mod private {
-    use std::path::Path;
-    use crate::cpu::eCPUDetails;
+    use rusqlite::{Connection, OptionalExtension};
+    use crate::cpu::embedded::EmbeddedSource;
+    use crate::cpu::import::{CsvSource, ImportReport};
+    use crate::cpu::{history, overlay};
+    use crate::cpu::spec::Vendor;
 
     pub trait Database {
-        const DATABASE: &'static str = "res/db/cpu.db";
-        const CPU_INFO_FOLDER: &'static str;
-        fn check_if_db_exists() -> bool {
-            if Path::new(Self::DATABASE).exists() {
-                log::debug!("Database exists");
-                Path::new(Self::DATABASE).exists()
-            } else {false}
-        }
-        fn get_file_names(directory: String) -> Result<Vec<String>, std::io::Error> {
-            Ok(Vec::new())
+        /// CSV files embedded at compile time
+        const SOURCES: &'static [EmbeddedSource];
+        const TABLE: &'static str;
+        const VENDOR: Vendor;
+        /// Imports the rows of `sources`, replacing rows with the same product name. Overlay rows
+        /// only replace the values they have, see [`ImportedRows::merge_overlay`](crate::cpu::import::ImportedRows::merge_overlay).
+        fn save_to_database(conn: &mut Connection, sources: &[CsvSource]) -> Result<ImportReport, rusqlite::Error>;
+
+        /// Creates the typed table if it does not exist yet.
+        fn create_table(conn: &Connection) -> Result<(), rusqlite::Error>;
+
+        /// Databases generated before the typed schema declared every column as `TEXT`,
+        /// including `cores`, which makes numeric comparisons compare strings.
+        fn is_legacy_table(conn: &Connection) -> Result<bool, rusqlite::Error> {
+            let column_type: Option<String> = conn.query_row(
+                "SELECT type FROM pragma_table_info(?1) WHERE name = 'cores'",
+                [Self::TABLE],
+                |row| row.get(0),
+            ).optional()?;
+
+            Ok(column_type.is_some_and(|t| t.eq_ignore_ascii_case("TEXT")))
         }
-        fn save_to_database(files: Vec<String>) -> Result<(), rusqlite::Error>;
-        fn split_csv_line(line: &str) -> Vec<String> {
-            let mut fields = Vec::new();
-            let mut current_field = String::new();
-            let mut in_quotes = false;
-
-            for c in line.chars() {
-                if c == '"' {
-                    if in_quotes {
-                        // End of quoted field
-                        in_quotes = false;
-                    } else {
-                        // Start of quoted field
-                        in_quotes = true;
-                    }
-                } else if c == ',' && !in_quotes {
-                    // End of unquoted field
-                    fields.push(current_field.clone());
-                    current_field.clear();
-                } else {
-                    // Append character to current field
-                    current_field.push(c);
-                }
-            }
 
-            // Handle the last field
-            if !current_field.is_empty() {
-                fields.push(current_field.clone());
+        /// Makes sure the table exists with the typed schema. A legacy table is dropped, it is
+        /// rebuilt from the embedded CPU data like any database without metadata.
+        fn prepare_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+            if Self::is_legacy_table(conn)? {
+                log::info!("Dropping legacy {} table, it is rebuilt with the typed schema", Self::TABLE);
+                Self::drop_table(conn)?;
             }
 
-            fields
+            Self::create_table(conn)
+        }
+
+        /// Drops the table, e.g. because it was created with another schema version.
+        fn drop_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+            conn.execute_batch(&format!("DROP TABLE IF EXISTS {}", Self::TABLE))
+        }
+
+        /// Replaces every row of the table with the embedded CPU data, oldest snapshot first so
+        /// the newest one wins, followed by the user overlays of the vendor. Records the changes
+        /// between snapshots in the history table.
+        fn reimport(conn: &mut Connection) -> Result<ImportReport, rusqlite::Error> {
+            log::debug!("Filling {} from the embedded CPU data", Self::TABLE);
+            conn.execute_batch(&format!("DELETE FROM {}", Self::TABLE))?;
+
+            let mut sources = Self::SOURCES.iter().map(EmbeddedSource::to_csv_source).collect::<Vec<_>>();
+            sources.sort_by_key(|source| source.snapshot);
+            sources.extend(overlay::sources(conn, Self::VENDOR)?);
+
+            let report = Self::save_to_database(conn, &sources)?;
+            history::write(conn, Self::VENDOR, &report.history)?;
+            Ok(report)
         }
 
         fn parse_clock_speed(speed_str: &str) -> usize {
@@ -77,9 +140,9 @@ pub(crate) mod private {
                 .replace(" MHz", "");
 
             // Check if it's in GHz or MHz
-            if cleaned.contains('.') {
+            if cleaned.contains('.') || speed_str.contains("GHz") {
                 // Convert GHz to MHz
-                (cleaned.parse::<f64>().unwrap_or(0.0) * 1000.0) as usize
+                (cleaned.parse::<f64>().unwrap_or(0.0) * 1000.0).round() as usize
             } else {
                 cleaned.parse::<usize>().unwrap_or(0)
             }
@@ -89,22 +152,52 @@ pub(crate) mod private {
 
 #[derive(Debug)]
 pub struct CPUDetails {
+    /// Physical cores, or logical CPUs if the topology is not known
     pub cores: usize,
+    /// Logical CPUs, i.e. hardware threads
+    pub threads: usize,
     pub vendor: String,
     pub brand: String,
     pub model: String,
+    /// Speed of one logical CPU in MHz as reported by sysinfo, see `frequencies` for every CPU
     pub frequency: usize,
     pub details: eCPUDetails,
+    /// How confidently `details` was matched against the brand string, between 0.0 and 1.0.
+    /// `None` if no database record matched.
+    pub match_confidence: Option<f32>,
+    /// Vendor-neutral view of `details`, `None` if no database record matched.
+    pub spec: Option<CpuSpec>,
+    /// What the CPUID instruction reports, `None` on non-x86 CPUs.
+    pub cpuid: Option<CpuIdInfo>,
+    /// Packages, cores and threads from sysfs, `None` outside of Linux.
+    pub topology: Option<CpuTopology>,
+    /// The caches of the machine from sysfs, `None` outside of Linux.
+    pub caches: Option<CacheHierarchy>,
+    /// Per-core speeds and scaling settings from cpufreq, `None` if it is not available.
+    pub frequencies: Option<CpuFrequencies>,
+    /// Vulnerabilities, mitigations and microcode, `None` outside of Linux.
+    pub security: Option<SecurityReport>,
 }
 
 #[derive(Debug)]
-#[allow(clippy::large_enum_variant)]
+#[allow(clippy::large_enum_variant, non_camel_case_types)]
 pub enum eCPUDetails {
     Intel(IntelData),
     AMD(AMDData),
     Else
 }
 
+impl eCPUDetails {
+    /// The record as a [`CpuSpec`], `None` for [`eCPUDetails::Else`].
+    pub fn spec(&self) -> Option<CpuSpec> {
+        match self {
+            eCPUDetails::Intel(data) => Some(CpuSpec::from(data)),
+            eCPUDetails::AMD(data) => Some(CpuSpec::from(data)),
+            eCPUDetails::Else => None,
+        }
+    }
+}
+
 impl CPUDetails {
     pub fn fetch() -> Self {
         let s = sysinfo::System::new_with_specifics(
@@ -123,115 +216,66 @@ impl CPUDetails {
             num_cores.push(cpu.name())
         }
 
-        let cores = num_cores.len();
-        let model = Self::get_intel_model(brand.clone().as_str()).unwrap_or_default();
+        let topology = CpuTopology::read();
+        let threads = num_cores.len();
+        let cores = topology.as_ref().map_or(threads, CpuTopology::physical_cores);
+        let model = matcher::normalize_brand(&brand);
+        let mut match_confidence = None;
 
-        let details = if vendor == String::from("GenuineIntel") {
-            let _temp = IntelData::fetch(
-                Self::get_intel_model(&brand).unwrap_or_default().as_str(),
-                EnumCPUData::Intel(eIntelData::Name)
-            );
-
-            match _temp {
-                Ok(thing) => {
-                    thing.unwrap_or(eCPUDetails::Else)
+        let details = if vendor == "GenuineIntel" {
+            match matcher::best_match::<IntelData>(&brand) {
+                Ok(Some(found)) => {
+                    match_confidence = Some(found.best.confidence);
+                    eCPUDetails::Intel(found.best.record)
                 },
+                Ok(None) => eCPUDetails::Else,
                 Err(err) => {
-                    eprintln!("An error occurred while fetching CPU details: {}", err);
+                    log::warn!("An error occurred while fetching CPU details: {}", err);
                     eCPUDetails::Else
                 }
             }
-        } else if vendor == String::from("AuthenticAMD") {
-
-            let _temp = AMDData::fetch(
-                Self::get_intel_model(&brand).unwrap_or_default().as_str(),
-                EnumCPUData::AMD(eAMDData::Name)
-            );
-            
-            match _temp {
-                Ok(thing) => {
-                    thing.unwrap_or(eCPUDetails::Else)
+        } else if vendor == "AuthenticAMD" {
+            match matcher::best_match::<AMDData>(&brand) {
+                Ok(Some(found)) => {
+                    match_confidence = Some(found.best.confidence);
+                    eCPUDetails::AMD(found.best.record)
                 },
+                Ok(None) => eCPUDetails::Else,
                 Err(err) => {
-                    eprintln!("An error occurred while fetching CPU details: {}", err);
+                    log::warn!("An error occurred while fetching CPU details: {}", err);
                     eCPUDetails::Else
                 }
             }
         } else {
             eCPUDetails::Else
         };
+        let spec = details.spec();
 
         Self {
             cores,
+            threads,
             vendor,
             brand,
             model,
             frequency,
-            details
+            details,
+            match_confidence,
+            spec,
+            cpuid: CpuIdInfo::read(),
+            topology,
+            caches: CacheHierarchy::read(),
+            frequencies: CpuFrequencies::read(),
+            security: SecurityReport::read(),
         }
     }
-    
-    fn get_intel_model(brand: &str) -> Option<String> {
-        // Remove "(TM)" from the brand string
-        let brand = brand.replace("(TM)", "").replace("(tm)", "");
-        
-        // Common Intel product lines
-        let product_lines = ["Atom", "Core", "Xeon", "Pentium", "Celeron"];
-        
-        // Try to find product line in brand string
-        for line in product_lines {
-            if let Some(pos) = brand.find(line) {
-                // Get everything after the product line
-                let after_line = &brand[pos..];
-                
-                // Split by spaces and get components
-                let parts: Vec<&str> = after_line.split_whitespace().collect();
-                
-                if parts.len() >= 2 {
-                    // Handle different format cases
-                    if parts[1].starts_with("i") {
-                        // Case: Core i3/i5/i7/i9-XXXXX
-                        return Some(format!("{} {}", parts[0], parts[1]));
-                    } else if parts[1].contains("-") {
-                        // Case: Atom/Pentium/Celeron XXXXX
-                        return Some(format!("{} {}", parts[0], parts[1]));
-                    } else {
-                        // Case: Core 2 XXXXX or other formats
-                        let model = parts[..3].join(" ");
-                        return Some(model);
-                    }
-                }
-            }
-        }
-        None
-    }
 
-    fn get_amd_model(brand: &str) -> Option<String> {
-        // Split brand into words
-        let words: Vec<&str> = brand.split_whitespace().collect();
-        
-        // Look for word containing 3 consecutive digits
-        for word in words {
-            let mut digit_count = 0;
-            let mut chars = word.chars().peekable();
-            
-            while let Some(c) = chars.next() {
-                if c.is_ascii_digit() {
-                    digit_count += 1;
-                    // Check next two characters for digits
-                    if digit_count == 1 {
-                        if let (Some(d1), Some(d2)) = (chars.next(), chars.next()) {
-                            if d1.is_ascii_digit() && d2.is_ascii_digit() {
-                                return Some(word.to_string());
-                            }
-                        }
-                    }
-                } else {
-                    digit_count = 0;
-                }
-            }
+    /// The cache levels whose detected size differs from the database record, empty if either
+    /// is not known.
+    pub fn cache_mismatches(&self) -> Vec<CacheMismatch> {
+        match (&self.caches, &self.spec) {
+            (Some(caches), Some(spec)) => caches.check_against(spec),
+            _ => Vec::new(),
         }
-        None
     }
 
 }
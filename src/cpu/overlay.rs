@@ -0,0 +1,155 @@
+//! CPU records registered by the user, e.g. for engineering samples, OEM-only SKUs or parts missing
+//! from the upstream datasets. The files are kept in the database and imported after the embedded
+//! data every time a vendor table is regenerated, so their values replace those of upstream rows
+//! with the same name.
+
+use std::borrow::Cow;
+use std::path::Path;
+use csv::WriterBuilder;
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use crate::cpu::history::Snapshot;
+use crate::cpu::import::{CsvSource, ImportReport};
+use crate::cpu::spec::Vendor;
+
+/// A user file registered with [`CpuDatabase::add_overlay`](crate::cpu::database::CpuDatabase::add_overlay).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub vendor: Vendor,
+    pub path: String,
+}
+
+pub(crate) fn create_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS user_overlays (
+            path TEXT PRIMARY KEY,
+            vendor TEXT NOT NULL,
+            contents TEXT NOT NULL
+        )"
+    )
+}
+
+/// The overlays of `vendor` as CSV sources, in the order they were registered.
+pub(crate) fn sources(conn: &Connection, vendor: Vendor) -> Result<Vec<CsvSource<'static>>, rusqlite::Error> {
+    create_table(conn)?;
+
+    let mut stmt = conn.prepare("SELECT path, contents FROM user_overlays WHERE vendor = ?1 ORDER BY rowid")?;
+    let sources = stmt.query_map([vendor.key()], |row| {
+        Ok(CsvSource {
+            path: Cow::Owned(row.get(0)?),
+            text: Cow::Owned(row.get(1)?),
+            user_provided: true,
+            snapshot: Snapshot::Unknown,
+        })
+    })?;
+
+    sources.collect()
+}
+
+pub(crate) fn list(conn: &Connection) -> Result<Vec<Overlay>, rusqlite::Error> {
+    create_table(conn)?;
+
+    let mut stmt = conn.prepare("SELECT path, vendor FROM user_overlays ORDER BY rowid")?;
+    let overlays = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    Ok(overlays
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|(path, vendor)| Some(Overlay { vendor: Vendor::from_key(&vendor)?, path }))
+        .collect())
+}
+
+/// Stores the CSV text of an overlay, replacing an earlier version of the same file. The replaced
+/// file moves to the end, so its rows take precedence over the other overlays.
+pub(crate) fn store(conn: &Connection, vendor: Vendor, source: &CsvSource) -> Result<(), rusqlite::Error> {
+    create_table(conn)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO user_overlays (path, vendor, contents) VALUES (?1, ?2, ?3)",
+        params![source.path.as_ref(), vendor.key(), source.text.as_ref()],
+    )?;
+    Ok(())
+}
+
+/// Forgets the overlay at `path`, returns its vendor if it was registered.
+pub(crate) fn remove(conn: &Connection, path: &str) -> Result<Option<Vendor>, rusqlite::Error> {
+    let vendor = list(conn)?.into_iter().find(|overlay| overlay.path == path).map(|overlay| overlay.vendor);
+    conn.execute("DELETE FROM user_overlays WHERE path = ?1", [path])?;
+    Ok(vendor)
+}
+
+/// A JSON value as a CSV field: lists are joined with `", "` and booleans become `"Yes"`/`"No"`,
+/// like in the upstream datasets.
+fn json_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(true) => "Yes".to_string(),
+        Value::Bool(false) => "No".to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values.iter().map(json_field).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
+
+/// Converts a JSON array of objects keyed by CSV header, e.g. `[{"Product": "Core i9-14900KS",
+/// "Cores": 24}]`, into CSV text. A single object is one record.
+fn json_to_csv(json: &str) -> Result<String, String> {
+    let records = match serde_json::from_str::<Value>(json).map_err(|e| e.to_string())? {
+        Value::Array(records) => records,
+        record @ Value::Object(_) => vec![record],
+        _ => return Err("expected an array of objects".to_string()),
+    };
+
+    let mut headers: Vec<String> = Vec::new();
+    for record in &records {
+        let Value::Object(record) = record else {
+            return Err("expected an array of objects".to_string());
+        };
+        for key in record.keys() {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+    }
+
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+    writer.write_record(&headers).map_err(|e| e.to_string())?;
+    for record in &records {
+        let fields = headers
+            .iter()
+            .map(|header| record.get(header).map(json_field).unwrap_or_default());
+        writer.write_record(fields).map_err(|e| e.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Reads a user CSV file, or a JSON file if its extension is `.json`, as a CSV source. Files that
+/// are not valid JSON are added to `report` as skipped and return `None`.
+pub(crate) fn read_file(path: &Path, report: &mut ImportReport) -> Result<Option<CsvSource<'static>>, rusqlite::Error> {
+    let display = path.display().to_string();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        log::warn!("Unable to read {}: {}", display, e);
+        rusqlite::Error::InvalidPath(path.to_path_buf())
+    })?;
+
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let text = if is_json {
+        match json_to_csv(&contents) {
+            Ok(text) => text,
+            Err(err) => {
+                report.skip(&display, 1, format!("invalid JSON: {}", err));
+                return Ok(None);
+            }
+        }
+    } else {
+        contents
+    };
+
+    Ok(Some(CsvSource {
+        path: Cow::Owned(display),
+        text: Cow::Owned(text),
+        user_provided: true,
+        snapshot: Snapshot::Unknown,
+    }))
+}
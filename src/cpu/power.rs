@@ -0,0 +1,118 @@
+//! Power envelopes parsed from the TDP values of the datasets.
+
+use std::collections::BTreeMap;
+use crate::utils::leading_number;
+
+/// Default and configurable TDP of a CPU, in watts. Fields are `None` if the dataset does not
+/// have them.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PowerSpec {
+    pub default_w: Option<f32>,
+    /// Lowest configurable TDP (cTDP down)
+    pub ctdp_min_w: Option<f32>,
+    /// Highest configurable TDP (cTDP up)
+    pub ctdp_max_w: Option<f32>,
+}
+
+/// The lowest and highest wattage in a value such as `"45-65W"`, `"35W - 54W"` or `"65W"`.
+fn parse_range(s: &str) -> Option<(f32, f32)> {
+    let watts = s
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter_map(leading_number)
+        .map(|w| w as f32)
+        .collect::<Vec<_>>();
+
+    let min = watts.iter().copied().reduce(f32::min)?;
+    let max = watts.iter().copied().reduce(f32::max)?;
+    Some((min, max))
+}
+
+impl PowerSpec {
+    /// Whether the CPU can run at `watts`, either by default or configured within its cTDP range.
+    pub fn allows(&self, watts: f32) -> bool {
+        let in_range = match (self.ctdp_min_w, self.ctdp_max_w) {
+            (Some(min), Some(max)) => (min..=max).contains(&watts),
+            _ => false,
+        };
+        in_range || self.default_w == Some(watts)
+    }
+
+    /// Power of an AMDCpuData row from its `Default TDP`, e.g. `"65W"`, and its
+    /// `AMD Configurable TDP (cTDP)`, e.g. `"45-65W"`.
+    pub(crate) fn from_amd(default_tdp: &str, configurable_tdp: &str) -> Self {
+        let ctdp = parse_range(configurable_tdp);
+
+        PowerSpec {
+            default_w: leading_number(default_tdp).map(|w| w as f32),
+            ctdp_min_w: ctdp.map(|(min, _)| min),
+            ctdp_max_w: ctdp.map(|(_, max)| max),
+        }
+    }
+
+    /// Power of an intel-processors row. The dataset only has the TDP, the configurable TDP is
+    /// taken from the `Configurable TDP-down` / `-up` columns of `extra` if a CSV file has them.
+    pub(crate) fn from_intel(tdp: Option<usize>, extra: &BTreeMap<String, String>) -> Self {
+        let extra_watts = |needle: &str| {
+            extra
+                .iter()
+                .find(|(header, _)| header.to_lowercase().contains(needle))
+                .and_then(|(_, value)| leading_number(value))
+                .map(|w| w as f32)
+        };
+
+        PowerSpec {
+            default_w: tdp.map(|w| w as f32),
+            ctdp_min_w: extra_watts("tdp-down"),
+            ctdp_max_w: extra_watts("tdp-up"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let cases = [
+            ("45-65W", Some((45.0, 65.0))),
+            ("35W - 54W", Some((35.0, 54.0))),
+            ("65W", Some((65.0, 65.0))),
+            ("65 / 45 / 35W", Some((35.0, 65.0))),
+            ("12.5-25W", Some((12.5, 25.0))),
+            ("", None),
+            ("N/A", None),
+        ];
+
+        for (s, range) in cases {
+            assert_eq!(parse_range(s), range, "{}", s);
+        }
+    }
+
+    #[test]
+    fn amd_power() {
+        let power = PowerSpec::from_amd("65W", "45-65W");
+
+        assert_eq!(power, PowerSpec { default_w: Some(65.0), ctdp_min_w: Some(45.0), ctdp_max_w: Some(65.0) });
+        assert!(power.allows(45.0) && power.allows(65.0));
+        assert!(!power.allows(105.0));
+
+        let power = PowerSpec::from_amd("120W", "");
+        assert_eq!(power, PowerSpec { default_w: Some(120.0), ctdp_min_w: None, ctdp_max_w: None });
+        assert!(power.allows(120.0));
+        assert!(!power.allows(65.0));
+    }
+
+    #[test]
+    fn intel_power() {
+        let mut extra = BTreeMap::new();
+        extra.insert("Configurable TDP-down".to_string(), "12 W".to_string());
+        extra.insert("Configurable TDP-up".to_string(), "25 W".to_string());
+
+        assert_eq!(
+            PowerSpec::from_intel(Some(15), &extra),
+            PowerSpec { default_w: Some(15.0), ctdp_min_w: Some(12.0), ctdp_max_w: Some(25.0) },
+        );
+        assert_eq!(PowerSpec::from_intel(None, &BTreeMap::new()), PowerSpec::default());
+    }
+}
@@ -0,0 +1,433 @@
+use rusqlite::{Row, ToSql};
+use crate::cpu::amd::{eAMDData, AMDData};
+use crate::cpu::intel::{eIntelData, IntelData};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::launch::LaunchDate;
+use crate::cpu::memory::MemoryKind;
+use crate::cpu::Database;
+
+/// SQL expression evaluating to the launch year of a row, or `NULL` if unknown.
+pub(crate) const LAUNCH_YEAR_SQL: &str = "NULLIF(launch_key / 1000, 0)";
+
+/// Comparison used by a [`CpuQuery`] filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// SQL `LIKE`, the value is used as the pattern as-is (e.g. `"%Ryzen 7%"`)
+    Like,
+}
+
+impl Op {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Like => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// A CPU record type that can be queried with [`CpuQuery`].
+pub trait Queryable: Database + Sized {
+    /// The column enum of the vendor, e.g. [`eIntelData`]
+    type Column;
+
+    fn column_name(column: &Self::Column) -> &'static str;
+
+    /// The column holding the product name
+    fn name_column() -> Self::Column;
+
+    /// The column holding the default TDP in watts
+    fn tdp_column() -> Self::Column;
+
+    fn name(&self) -> &str;
+
+    fn from_row(row: &Row) -> Self;
+}
+
+impl Queryable for IntelData {
+    type Column = eIntelData;
+
+    fn column_name(column: &eIntelData) -> &'static str {
+        column.to_string()
+    }
+
+    fn name_column() -> eIntelData {
+        eIntelData::Name
+    }
+
+    fn tdp_column() -> eIntelData {
+        eIntelData::ThermalDesignPower
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn from_row(row: &Row) -> Self {
+        IntelData::from_row(row)
+    }
+}
+
+impl Queryable for AMDData {
+    type Column = eAMDData;
+
+    fn column_name(column: &eAMDData) -> &'static str {
+        column.to_string()
+    }
+
+    fn name_column() -> eAMDData {
+        eAMDData::Name
+    }
+
+    fn tdp_column() -> eAMDData {
+        eAMDData::TDPWatts
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn from_row(row: &Row) -> Self {
+        AMDData::from_row(row)
+    }
+}
+
+/// Builder for queries over the CPU database that return every matching CPU.
+///
+/// ```no_run
+/// use hwisak_rs::cpu::amd::{eAMDData, AMDData};
+/// use hwisak_rs::cpu::query::{CpuQuery, Op, Order};
+///
+/// let cpus: Vec<AMDData> = CpuQuery::new()
+///     .filter(eAMDData::Cores, Op::Ge, 8)
+///     .filter(eAMDData::Socket, Op::Eq, "AM5")
+///     .filter(eAMDData::LithographyNm, Op::Le, 7)
+///     .cache(3, Op::Ge, 32 * 1024 * 1024)
+///     .launched_after(2022)
+///     .order_by(eAMDData::MaxBoostClock, Order::Descending)
+///     .limit(10)
+///     .fetch()
+///     .unwrap();
+/// ```
+pub struct CpuQuery<T: Queryable> {
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql>>,
+    order_by: Vec<(&'static str, Order)>,
+    limit: Option<usize>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Queryable> Default for CpuQuery<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Queryable> CpuQuery<T> {
+    pub fn new() -> Self {
+        Self {
+            conditions: Vec::new(),
+            params: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Adds a `<column> <op> <value>` predicate. All predicates must match.
+    pub fn filter(mut self, column: T::Column, op: Op, value: impl ToSql + 'static) -> Self {
+        self.params.push(Box::new(value));
+        self.conditions.push(format!(
+            "{} {} ?{}",
+            T::column_name(&column),
+            op.as_sql(),
+            self.params.len()
+        ));
+        self
+    }
+
+    /// Only keeps CPUs launched in `year` or later.
+    pub fn launched_after(mut self, year: i32) -> Self {
+        self.params.push(Box::new(year));
+        self.conditions.push(format!("{} >= ?{}", LAUNCH_YEAR_SQL, self.params.len()));
+        self
+    }
+
+    /// Only keeps CPUs launched in `year` or earlier.
+    pub fn launched_before(mut self, year: i32) -> Self {
+        self.params.push(Box::new(year));
+        self.conditions.push(format!("{} <= ?{}", LAUNCH_YEAR_SQL, self.params.len()));
+        self
+    }
+
+    /// Only keeps CPUs launched between `from` and `to`, both included. Less precise bounds cover
+    /// their whole quarter or year, e.g. 2020 to Q2 2022 keeps CPUs from January 2020 to June 2022.
+    /// CPUs with an unknown launch date never match.
+    pub fn launched_between(mut self, from: LaunchDate, to: LaunchDate) -> Self {
+        self.params.push(Box::new(from.first_key()));
+        self.params.push(Box::new(to.last_key()));
+        self.conditions.push(format!(
+            "launch_key BETWEEN ?{} AND ?{}",
+            self.params.len() - 1,
+            self.params.len()
+        ));
+        self
+    }
+
+    /// Only keeps CPUs whose level `level` caches add up to a size that compares to `bytes` with
+    /// `op`, e.g. `.cache(3, Op::Ge, 32 * 1024 * 1024)` for at least 32 MiB of L3.
+    pub fn cache(mut self, level: u8, op: Op, bytes: u64) -> Self {
+        self.params.push(Box::new(level));
+        self.params.push(Box::new(bytes as i64));
+        self.conditions.push(format!(
+            "(SELECT SUM(json_extract(value, '$.size_bytes')) FROM json_each(caches)
+                WHERE json_extract(value, '$.level') = ?{}) {} ?{}",
+            self.params.len() - 1,
+            op.as_sql(),
+            self.params.len()
+        ));
+        self
+    }
+
+    /// Only keeps CPUs with a default TDP between `min_w` and `max_w` watts, both included.
+    pub fn tdp_between(mut self, min_w: f32, max_w: f32) -> Self {
+        self.params.push(Box::new(min_w));
+        self.params.push(Box::new(max_w));
+        self.conditions.push(format!(
+            "{} BETWEEN ?{} AND ?{}",
+            T::column_name(&T::tdp_column()),
+            self.params.len() - 1,
+            self.params.len()
+        ));
+        self
+    }
+
+    /// Only keeps CPUs that can run at `watts`, by default or within their configurable TDP range,
+    /// see [`PowerSpec::allows`](crate::cpu::power::PowerSpec::allows).
+    pub fn allows_tdp(mut self, watts: f32) -> Self {
+        self.params.push(Box::new(watts));
+        self.conditions.push(format!(
+            "({0} = ?{1} OR ?{1} BETWEEN ctdp_min_w AND ctdp_max_w)",
+            T::column_name(&T::tdp_column()),
+            self.params.len()
+        ));
+        self
+    }
+
+    /// Only keeps CPUs supporting `kind` memory at `min_mts` MT/s or faster. CPUs with an unknown
+    /// speed for `kind` match any `min_mts`.
+    pub fn memory(mut self, kind: MemoryKind, min_mts: u32) -> Self {
+        self.params.push(Box::new(kind.as_str()));
+        self.params.push(Box::new(min_mts));
+        self.conditions.push(format!(
+            "EXISTS (SELECT 1 FROM json_each(memory) WHERE json_extract(value, '$.kind') = ?{}
+                AND coalesce(json_extract(value, '$.max_mts'), ?{1}) >= ?{1})",
+            self.params.len() - 1,
+            self.params.len()
+        ));
+        self
+    }
+
+    pub fn order_by(mut self, column: T::Column, order: Order) -> Self {
+        self.order_by.push((T::column_name(&column), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub(crate) fn to_sql(&self) -> String {
+        let mut sql = format!("SELECT * FROM {}", T::TABLE);
+
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+
+        if !self.order_by.is_empty() {
+            let order_by = self.order_by
+                .iter()
+                .map(|(column, order)| match order {
+                    Order::Ascending => format!("{} ASC", column),
+                    Order::Descending => format!("{} DESC", column),
+                })
+                .collect::<Vec<_>>();
+            sql.push_str(" ORDER BY ");
+            sql.push_str(&order_by.join(", "));
+        }
+
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        sql
+    }
+
+    /// Runs the query against the default [`CpuDatabase`] and returns every matching CPU.
+    pub fn fetch(self) -> Result<Vec<T>, rusqlite::Error> {
+        self.fetch_in(&CpuDatabase::open_default()?)
+    }
+
+    /// Runs the query against `db` and returns every matching CPU.
+    pub fn fetch_in(self, db: &CpuDatabase) -> Result<Vec<T>, rusqlite::Error> {
+        let conn = db.connection();
+        let sql = self.to_sql();
+        log::debug!("Running CPU query: {}", sql);
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params = self.params.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+        let rows = stmt.query_map(params.as_slice(), |row| Ok(T::from_row(row)))?;
+
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::spec::Vendor;
+    use crate::tests::TempDir;
+
+    /// The embedded data with a few more AM5 processors.
+    fn database(dir: &TempDir) -> CpuDatabase {
+        let overlay = dir.write(
+            "amd.csv",
+            "Name,# of CPU Cores,# of Threads,Max. Boost Clock,Base Clock,CPU Socket\n\
+             AMD Ryzen™ 9 7900X,12,24,Up to 5.6 GHz,4.7 GHz,AM5\n\
+             AMD Ryzen™ 7 7700X,8,16,Up to 5.4 GHz,4.5 GHz,AM5\n\
+             AMD Ryzen™ 5 7600X,6,12,Up to 5.3 GHz,4.7 GHz,AM5\n",
+        );
+        let mut db = CpuDatabase::in_memory().unwrap();
+        db.add_overlay(Vendor::AMD, &overlay).unwrap();
+        db
+    }
+
+    fn names<T: Queryable>(cpus: &[T]) -> Vec<&str> {
+        cpus.iter().map(|cpu| cpu.name()).collect()
+    }
+
+    #[test]
+    fn filter_order_and_limit() {
+        let dir = TempDir::new();
+        let db = database(&dir);
+
+        let query = || CpuQuery::<AMDData>::new()
+            .filter(eAMDData::Cores, Op::Ge, 8)
+            .filter(eAMDData::Socket, Op::Eq, "AM5")
+            .order_by(eAMDData::MaxBoostClock, Order::Descending);
+
+        let cpus = query().fetch_in(&db).unwrap();
+        assert_eq!(
+            names(&cpus),
+            vec!["AMD Ryzen™ 9 7950X", "AMD Ryzen™ 9 7900X", "AMD Ryzen™ 7 7700X", "AMD Ryzen™ 7 7800X3D"]
+        );
+
+        let cpus = query().limit(2).fetch_in(&db).unwrap();
+        assert_eq!(names(&cpus), vec!["AMD Ryzen™ 9 7950X", "AMD Ryzen™ 9 7900X"]);
+    }
+
+    #[test]
+    fn operators() {
+        let dir = TempDir::new();
+        let db = database(&dir);
+        let fetch = |op, cores| {
+            let cpus = CpuQuery::<AMDData>::new()
+                .filter(eAMDData::Cores, op, cores)
+                .filter(eAMDData::Socket, Op::Eq, "AM5")
+                .order_by(eAMDData::Cores, Order::Ascending)
+                .order_by(eAMDData::Name, Order::Ascending)
+                .fetch_in(&db)
+                .unwrap();
+            cpus.into_iter().map(|cpu| cpu.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(fetch(Op::Eq, 8), vec!["AMD Ryzen™ 7 7700X", "AMD Ryzen™ 7 7800X3D"]);
+        assert_eq!(fetch(Op::Ne, 8), vec!["AMD Ryzen™ 5 7600X", "AMD Ryzen™ 9 7900X", "AMD Ryzen™ 9 7950X"]);
+        assert_eq!(fetch(Op::Lt, 8), vec!["AMD Ryzen™ 5 7600X"]);
+        assert_eq!(fetch(Op::Le, 8), vec!["AMD Ryzen™ 5 7600X", "AMD Ryzen™ 7 7700X", "AMD Ryzen™ 7 7800X3D"]);
+        assert_eq!(fetch(Op::Gt, 12), vec!["AMD Ryzen™ 9 7950X"]);
+        assert_eq!(fetch(Op::Ge, 12), vec!["AMD Ryzen™ 9 7900X", "AMD Ryzen™ 9 7950X"]);
+
+        let cpus = CpuQuery::<AMDData>::new()
+            .filter(eAMDData::Name, Op::Like, "%Ryzen™ 7 7%X%")
+            .order_by(eAMDData::Name, Order::Descending)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["AMD Ryzen™ 7 7800X3D", "AMD Ryzen™ 7 7700X"]);
+    }
+
+    #[test]
+    fn intel_columns() {
+        let db = CpuDatabase::in_memory().unwrap();
+
+        // The name and TDP columns are "product" and "thermal_design_power" in the Intel table
+        let cpus = CpuQuery::<IntelData>::new()
+            .filter(eIntelData::Cores, Op::Gt, 4)
+            .filter(eIntelData::ThermalDesignPower, Op::Le, 125)
+            .order_by(eIntelData::Cores, Order::Ascending)
+            .order_by(eIntelData::Name, Order::Descending)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["Core i7-8700", "Core i9-13900KF", "Core i9-13900K"]);
+
+        let cpus = CpuQuery::<IntelData>::new()
+            .filter(eIntelData::CodeName, Op::Eq, "Raptor Lake")
+            .order_by(eIntelData::Name, Order::Ascending)
+            .limit(1)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["Core i9-13900K"]);
+    }
+
+    #[test]
+    fn launch_dates() {
+        let db = CpuDatabase::in_memory().unwrap();
+
+        let cpus = CpuQuery::<AMDData>::new()
+            .launched_between(LaunchDate::year(2022), LaunchDate::quarter(2023, 1).unwrap())
+            .order_by(eAMDData::Name, Order::Ascending)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["AMD Ryzen™ 7 7840HS", "AMD Ryzen™ 9 7950X"]);
+
+        // The Intel data only has the quarter, Q4 2022 is not within October 2022
+        let cpus = CpuQuery::<IntelData>::new()
+            .launched_between(LaunchDate::month(2022, 10).unwrap(), LaunchDate::month(2022, 10).unwrap())
+            .fetch_in(&db)
+            .unwrap();
+        assert!(cpus.is_empty());
+
+        let cpus = CpuQuery::<IntelData>::new()
+            .launched_between(LaunchDate::quarter(2017, 4).unwrap(), LaunchDate::year(2019))
+            .order_by(eIntelData::Name, Order::Ascending)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["Core i7-1065G7", "Core i7-8700"]);
+
+        let cpus = CpuQuery::<IntelData>::new()
+            .launched_after(2020)
+            .launched_before(2022)
+            .order_by(eIntelData::Name, Order::Ascending)
+            .fetch_in(&db)
+            .unwrap();
+        assert_eq!(names(&cpus), vec!["Core i9-13900K", "Core i9-13900KF"]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Full-text search over both vendor tables, backed by an SQLite FTS5 index.
+
+use std::collections::HashMap;
+use rusqlite::{params_from_iter, Connection};
+use crate::cpu::amd::AMDData;
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::eCPUDetails;
+use crate::cpu::intel::IntelData;
+use crate::cpu::query::{Queryable, LAUNCH_YEAR_SQL};
+use crate::cpu::spec::Vendor;
+
+/// How many results [`search`] returns at most.
+const MAX_RESULTS: usize = 50;
+
+/// A CPU matching a search, best matches first.
+#[derive(Debug)]
+pub struct SearchHit {
+    pub vendor: Vendor,
+    pub name: String,
+    /// Relevance of the hit, higher is better. Only comparable between hits of the same search.
+    pub score: f64,
+    /// Excerpt of the best matching column with the matched words in `[` `]`
+    pub snippet: String,
+    pub details: eCPUDetails,
+}
+
+/// Creates the `cpu_search` index if it does not exist yet.
+pub(crate) fn create_index(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // The porter stemmer lets "laptop" match "Laptops"
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS cpu_search USING fts5(
+            vendor UNINDEXED,
+            name,
+            family,
+            series,
+            code_name,
+            socket,
+            form_factor,
+            graphics,
+            launch_year,
+            tokenize = 'porter unicode61'
+        )"
+    )
+}
+
+/// Fills the `cpu_search` index from the `intel_cpus` and `amd_cpus` tables.
+pub(crate) fn rebuild_index(conn: &Connection) -> Result<(), rusqlite::Error> {
+    create_index(conn)?;
+
+    conn.execute_batch(&format!(
+        "DELETE FROM cpu_search;
+        INSERT INTO cpu_search SELECT
+            'intel', product, '', '', code_name, socket, '', graphics, {0}
+        FROM intel_cpus;
+        INSERT INTO cpu_search SELECT
+            'amd', name, family, series, '', cpu_socket, form_factor, graphics_model, {0}
+        FROM amd_cpus;",
+        LAUNCH_YEAR_SQL,
+    ))
+}
+
+/// Turns free text into an FTS5 query matching rows that contain every word, e.g.
+/// `"ryzen"* AND "laptop"*` for `"ryzen laptop"`.
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// The records of `T` named `names`, by name, with a single query.
+fn fetch_named<T: Queryable>(conn: &Connection, names: &[&str]) -> Result<HashMap<String, T>, rusqlite::Error> {
+    if names.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let placeholders = (1..=names.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM {} WHERE {} IN ({})",
+        T::TABLE,
+        T::column_name(&T::name_column()),
+        placeholders.join(", ")
+    ))?;
+
+    let records = stmt.query_map(params_from_iter(names), |row| {
+        let record = T::from_row(row);
+        Ok((record.name().to_string(), record))
+    })?;
+
+    records.collect()
+}
+
+/// Searches the default [`CpuDatabase`] for CPUs matching every word of `text`, e.g.
+/// `"ryzen 7 laptop 2023"`. Words match the name, family, series, code name, socket, form factor,
+/// graphics model and launch year.
+pub fn search(text: &str) -> Result<Vec<SearchHit>, rusqlite::Error> {
+    search_in(&CpuDatabase::open_default()?, text)
+}
+
+/// Like [`search`], but searches `db`.
+pub fn search_in(db: &CpuDatabase, text: &str) -> Result<Vec<SearchHit>, rusqlite::Error> {
+    let query = fts_query(text);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = db.connection();
+    log::debug!("Searching CPUs for {}", query);
+
+    // Matches in the name count the most, bm25() is lower for better matches
+    let mut stmt = conn.prepare(
+        "SELECT vendor, name,
+            bm25(cpu_search, 0.0, 10.0, 3.0, 3.0, 3.0, 2.0, 2.0, 1.0, 1.0),
+            snippet(cpu_search, -1, '[', ']', '…', 8)
+        FROM cpu_search
+        WHERE cpu_search MATCH ?1
+        ORDER BY 3
+        LIMIT ?2"
+    )?;
+
+    let rows = stmt
+        .query_map((&query, MAX_RESULTS), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let names = |vendor: &str| {
+        rows.iter().filter(|row| row.0 == vendor).map(|row| row.1.as_str()).collect::<Vec<_>>()
+    };
+    let mut intel = fetch_named::<IntelData>(conn, &names("intel"))?;
+    let mut amd = fetch_named::<AMDData>(conn, &names("amd"))?;
+
+    let mut hits = Vec::new();
+    for (vendor, name, rank, snippet) in rows {
+        let (vendor, details) = if vendor == "intel" {
+            (Vendor::Intel, intel.remove(&name).map(eCPUDetails::Intel))
+        } else {
+            (Vendor::AMD, amd.remove(&name).map(eCPUDetails::AMD))
+        };
+
+        hits.push(SearchHit {
+            vendor,
+            name,
+            score: -rank,
+            snippet,
+            details: details.unwrap_or(eCPUDetails::Else),
+        });
+    }
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::private::Database;
+    use crate::tests::{source, TempDir};
+
+    #[test]
+    fn sockets_of_both_vendors() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let intel = source("intel.csv", "Product,Code Name\nCore i9-13900K,Raptor Lake\n", false);
+        let amd = source("amd.csv", "Name,CPU Socket\nAMD Ryzen™ 7 7800X3D,AM5\n", false);
+        IntelData::save_to_database(&mut conn, &[intel]).unwrap();
+        AMDData::save_to_database(&mut conn, &[amd]).unwrap();
+        rebuild_index(&conn).unwrap();
+
+        let names = |query: &str| {
+            let mut stmt = conn.prepare("SELECT name FROM cpu_search WHERE cpu_search MATCH ?1").unwrap();
+            let rows = stmt.query_map([query], |row| row.get::<_, String>(0)).unwrap();
+            rows.collect::<Result<Vec<_>, _>>().unwrap()
+        };
+
+        assert_eq!(names("socket:LGA1700"), vec!["Core i9-13900K"]);
+        assert_eq!(names("socket:AM5"), vec!["AMD Ryzen™ 7 7800X3D"]);
+        assert_eq!(names(&fts_query("raptor lga1700")), vec!["Core i9-13900K"]);
+    }
+
+    #[test]
+    fn fts_queries() {
+        assert_eq!(fts_query("ryzen laptop"), "\"ryzen\"* AND \"laptop\"*");
+        assert_eq!(fts_query("say \"hi\""), "\"say\"* AND \"\"\"hi\"\"\"*");
+        assert_eq!(fts_query("  "), "");
+    }
+
+    #[test]
+    fn ranked_hits_of_both_vendors() {
+        let dir = TempDir::new();
+        let intel = dir.write(
+            "intel.csv",
+            "Product,Code Name,Cores\nCore i5-9990,Vortex Lake,6\nCore Vortex 1,Raptor Lake,8\n",
+        );
+        let amd = dir.write("amd.csv", "Name,Series,# of CPU Cores\nAMD Vortex 5,Ryzen 7000 Series,12\n");
+        let mut db = CpuDatabase::in_memory().unwrap();
+        db.add_overlay(Vendor::Intel, &intel).unwrap();
+        db.add_overlay(Vendor::AMD, &amd).unwrap();
+
+        // Matches in the name come before matches in the code name
+        let hits = search_in(&db, "vortex").unwrap();
+        let mut names = hits.iter().map(|hit| hit.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names.pop(), Some("Core i5-9990"));
+        names.sort();
+        assert_eq!(names, vec!["AMD Vortex 5", "Core Vortex 1"]);
+        assert!(hits.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(hits[1].score > hits[2].score);
+        assert_eq!(hits[2].snippet, "[Vortex] Lake");
+
+        for hit in &hits {
+            match &hit.details {
+                eCPUDetails::Intel(record) => assert_eq!((hit.vendor, &record.name), (Vendor::Intel, &hit.name)),
+                eCPUDetails::AMD(record) => assert_eq!((hit.vendor, &record.name), (Vendor::AMD, &hit.name)),
+                eCPUDetails::Else => panic!("{} is not hydrated", hit.name),
+            }
+        }
+        let amd = hits.iter().find(|hit| hit.vendor == Vendor::AMD).unwrap();
+        assert!(matches!(&amd.details, eCPUDetails::AMD(record) if record.cores == 12));
+        assert_eq!(amd.snippet, "AMD [Vortex] 5");
+
+        // Every word has to match, in any column
+        let hits = search_in(&db, "vortex raptor").unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.name.as_str()).collect::<Vec<_>>(), vec!["Core Vortex 1"]);
+        assert!(search_in(&db, "vortex ice").unwrap().is_empty());
+        assert!(search_in(&db, " ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn launch_years_of_both_vendors() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let hits = search_in(&db, "2022").unwrap();
+
+        let mut names = hits.iter().map(|hit| (hit.vendor, hit.name.as_str())).collect::<Vec<_>>();
+        names.sort_by_key(|(_, name)| *name);
+        assert_eq!(
+            names,
+            vec![
+                (Vendor::AMD, "AMD Ryzen™ 9 7950X"),
+                (Vendor::Intel, "Core i9-13900K"),
+                (Vendor::Intel, "Core i9-13900KF"),
+            ]
+        );
+        assert!(hits.iter().all(|hit| hit.snippet == "[2022]"));
+    }
+}
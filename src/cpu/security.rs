@@ -0,0 +1,300 @@
+//! CPU vulnerabilities and their mitigations as reported by the Linux kernel, the running
+//! microcode revision and kernel command line flags that turn mitigations off.
+
+use std::fmt;
+use std::path::Path;
+use crate::utils::{read_sysfs, SYSFS_CPU_DIR, SYSFS_ROOT};
+
+/// Where procfs is mounted on Linux.
+const PROCFS_ROOT: &str = "/proc";
+
+/// Kernel parameters that turn a mitigation off when set to `off`.
+const MITIGATION_PARAMETERS: &[&str] = &[
+    "mitigations",
+    "spectre_v2",
+    "spectre_v2_user",
+    "spectre_bhi",
+    "spec_store_bypass_disable",
+    "spec_rstack_overflow",
+    "pti",
+    "l1tf",
+    "mds",
+    "tsx_async_abort",
+    "mmio_stale_data",
+    "retbleed",
+    "srbds",
+    "gather_data_sampling",
+    "reg_file_data_sampling",
+    "indirect_target_selection",
+    "kvm.nx_huge_pages",
+];
+
+/// Kernel flags that turn a mitigation or hardening feature off on their own.
+const WEAKENING_FLAGS: &[&str] = &[
+    "nopti",
+    "nospectre_v1",
+    "nospectre_v2",
+    "nospec_store_bypass_disable",
+    "nosmap",
+    "nosmep",
+    "nokaslr",
+    "tsx=on",
+];
+
+/// How the kernel handles a vulnerability, from its file in
+/// `/sys/devices/system/cpu/vulnerabilities`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VulnerabilityState {
+    NotAffected,
+    /// The mitigation in use, e.g. `"Enhanced / Automatic IBRS; IBPB: conditional"`
+    Mitigated(String),
+    /// Details of the vulnerable state, e.g. `"Clear CPU buffers attempted, no microcode"`
+    Vulnerable(Option<String>),
+    /// Any other state, e.g. `"Unknown: Dependent on hypervisor status"`
+    Unknown(String),
+}
+
+impl VulnerabilityState {
+    /// Parses the contents of a vulnerability file. The `KVM: ` prefix of `itlb_multihit` is
+    /// ignored.
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        let state = s.strip_prefix("KVM: ").unwrap_or(s);
+
+        if state.eq_ignore_ascii_case("Not affected") {
+            VulnerabilityState::NotAffected
+        } else if let Some(how) = state.strip_prefix("Mitigation: ") {
+            VulnerabilityState::Mitigated(how.trim().to_string())
+        } else if let Some(details) = state.strip_prefix("Vulnerable") {
+            let details = details.trim_start_matches([':', ';', ',']).trim();
+            VulnerabilityState::Vulnerable((!details.is_empty()).then(|| details.to_string()))
+        } else {
+            VulnerabilityState::Unknown(s.to_string())
+        }
+    }
+}
+
+impl fmt::Display for VulnerabilityState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VulnerabilityState::NotAffected => write!(f, "Not affected"),
+            VulnerabilityState::Mitigated(how) => write!(f, "Mitigation: {}", how),
+            VulnerabilityState::Vulnerable(None) => write!(f, "Vulnerable"),
+            VulnerabilityState::Vulnerable(Some(details)) => write!(f, "Vulnerable: {}", details),
+            VulnerabilityState::Unknown(state) => write!(f, "{}", state),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vulnerability {
+    /// The name of the file, e.g. `"spectre_v2"` or `"gather_data_sampling"`
+    pub name: String,
+    pub state: VulnerabilityState,
+}
+
+impl Vulnerability {
+    /// Whether the CPU is vulnerable, including mitigations that leave a part vulnerable, e.g.
+    /// `"Mitigation: Enhanced IBRS; BHI: Vulnerable"` or `"Mitigation: ...; SMT vulnerable"`.
+    pub fn is_vulnerable(&self) -> bool {
+        match &self.state {
+            VulnerabilityState::Vulnerable(_) => true,
+            VulnerabilityState::Mitigated(how) => how.to_lowercase().contains("vulnerable"),
+            VulnerabilityState::NotAffected | VulnerabilityState::Unknown(_) => false,
+        }
+    }
+}
+
+/// The security state of the CPUs of the machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityReport {
+    /// Sorted by name
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// The microcode revisions of the CPUs, normally one, sorted
+    pub microcode_revisions: Vec<u32>,
+    /// Kernel command line parameters that turn mitigations off, e.g. `"mitigations=off"`
+    pub weakening_flags: Vec<String>,
+}
+
+/// The distinct `microcode` revisions of `/proc/cpuinfo` text.
+fn microcode_revisions(cpuinfo: &str) -> Vec<u32> {
+    let mut revisions = cpuinfo
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim() != "microcode" {
+                return None;
+            }
+            let value = value.trim();
+            u32::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16).ok()
+        })
+        .collect::<Vec<_>>();
+
+    revisions.sort_unstable();
+    revisions.dedup();
+    revisions
+}
+
+/// The flags of a kernel command line that weaken mitigations. Arguments after `--` belong to
+/// init and are ignored.
+pub fn weakening_flags(cmdline: &str) -> Vec<String> {
+    cmdline
+        .split_whitespace()
+        .take_while(|&arg| arg != "--")
+        .filter(|&arg| {
+            let weakens_mitigation = arg
+                .split_once('=')
+                .is_some_and(|(key, value)| MITIGATION_PARAMETERS.contains(&key) && value == "off");
+            weakens_mitigation || WEAKENING_FLAGS.contains(&arg)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+impl SecurityReport {
+    /// Reads the report from `/sys` and `/proc`, `None` if the kernel does not report
+    /// vulnerabilities, e.g. on other operating systems.
+    pub fn read() -> Option<Self> {
+        Self::read_from(SYSFS_ROOT, PROCFS_ROOT)
+    }
+
+    /// Reads the report from the sysfs tree at `sysfs` and the procfs tree at `procfs`, e.g.
+    /// copies recorded on another machine.
+    pub fn read_from(sysfs: impl AsRef<Path>, procfs: impl AsRef<Path>) -> Option<Self> {
+        let entries = std::fs::read_dir(sysfs.as_ref().join(SYSFS_CPU_DIR).join("vulnerabilities")).ok()?;
+        let procfs = procfs.as_ref();
+
+        let mut vulnerabilities = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                Some(Vulnerability {
+                    name: entry.file_name().to_str()?.to_string(),
+                    state: VulnerabilityState::parse(&read_sysfs(entry.path())?),
+                })
+            })
+            .collect::<Vec<_>>();
+        vulnerabilities.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Some(SecurityReport {
+            vulnerabilities,
+            microcode_revisions: std::fs::read_to_string(procfs.join("cpuinfo"))
+                .map(|cpuinfo| microcode_revisions(&cpuinfo))
+                .unwrap_or_default(),
+            weakening_flags: read_sysfs(procfs.join("cmdline"))
+                .map(|cmdline| weakening_flags(&cmdline))
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The vulnerabilities the CPU is fully or partially exposed to, see
+    /// [`Vulnerability::is_vulnerable`].
+    pub fn vulnerable(&self) -> impl Iterator<Item = &Vulnerability> {
+        self.vulnerabilities.iter().filter(|vulnerability| vulnerability.is_vulnerable())
+    }
+
+    /// The microcode revision, `None` if it is not known or differs between CPUs.
+    pub fn microcode(&self) -> Option<u32> {
+        match self.microcode_revisions.as_slice() {
+            [revision] => Some(*revision),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    fn vulnerability(state: &str) -> Vulnerability {
+        Vulnerability { name: "test".to_string(), state: VulnerabilityState::parse(state) }
+    }
+
+    #[test]
+    fn parse_states() {
+        let cases = [
+            ("Not affected\n", VulnerabilityState::NotAffected),
+            ("Mitigation: Enhanced / Automatic IBRS; IBPB: conditional", VulnerabilityState::Mitigated("Enhanced / Automatic IBRS; IBPB: conditional".to_string())),
+            ("KVM: Mitigation: VMX disabled", VulnerabilityState::Mitigated("VMX disabled".to_string())),
+            ("Vulnerable", VulnerabilityState::Vulnerable(None)),
+            ("Vulnerable: Clear CPU buffers attempted, no microcode; SMT vulnerable", VulnerabilityState::Vulnerable(Some("Clear CPU buffers attempted, no microcode; SMT vulnerable".to_string()))),
+            ("Vulnerable; SMT disabled", VulnerabilityState::Vulnerable(Some("SMT disabled".to_string()))),
+            ("Vulnerable, IBPB: disabled, STIBP: disabled", VulnerabilityState::Vulnerable(Some("IBPB: disabled, STIBP: disabled".to_string()))),
+            ("Unknown: Dependent on hypervisor status", VulnerabilityState::Unknown("Unknown: Dependent on hypervisor status".to_string())),
+            ("KVM: Vulnerable", VulnerabilityState::Vulnerable(None)),
+        ];
+
+        for (s, state) in cases {
+            assert_eq!(VulnerabilityState::parse(s), state, "{}", s);
+        }
+
+        for s in ["Not affected", "Mitigation: PTI", "Vulnerable", "Vulnerable: No microcode", "Unknown: No mitigations"] {
+            assert_eq!(VulnerabilityState::parse(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn vulnerable_states() {
+        assert!(vulnerability("Vulnerable").is_vulnerable());
+        assert!(vulnerability("Vulnerable: No microcode").is_vulnerable());
+        // Mitigations can leave a part of the vulnerability open
+        assert!(vulnerability("Mitigation: Enhanced IBRS; BHI: Vulnerable").is_vulnerable());
+        assert!(vulnerability("Mitigation: Clear CPU buffers; SMT vulnerable").is_vulnerable());
+
+        assert!(!vulnerability("Mitigation: Clear CPU buffers; SMT Host state unknown").is_vulnerable());
+        assert!(!vulnerability("Not affected").is_vulnerable());
+        assert!(!vulnerability("Unknown: Dependent on hypervisor status").is_vulnerable());
+    }
+
+    #[test]
+    fn weakening_kernel_flags() {
+        let cmdline = "BOOT_IMAGE=/vmlinuz root=UUID=1234 ro quiet mitigations=off pti=on spectre_v2=off nokaslr tsx=on -- nopti";
+
+        assert_eq!(weakening_flags(cmdline), vec!["mitigations=off", "spectre_v2=off", "nokaslr", "tsx=on"]);
+        assert!(weakening_flags("quiet splash mitigations=auto,nosmt tsx=off").is_empty());
+        assert!(weakening_flags("").is_empty());
+    }
+
+    #[test]
+    fn microcode() {
+        let cpuinfo = "\
+processor\t: 0
+microcode\t: 0x129
+processor\t: 1
+microcode\t: 0x129
+processor\t: 2
+microcode\t: 0x12b
+";
+        assert_eq!(microcode_revisions(cpuinfo), vec![0x129, 0x12b]);
+        assert_eq!(microcode_revisions("processor\t: 0\nmicrocode\t: 0xa601203\n"), vec![0xa601203]);
+        assert!(microcode_revisions("processor\t: 0\n").is_empty());
+    }
+
+    #[test]
+    fn read_report() {
+        let sysfs = TempDir::new();
+        let procfs = TempDir::new();
+        let vulnerabilities = format!("{}/vulnerabilities", SYSFS_CPU_DIR);
+        sysfs.write(format!("{}/spectre_v2", vulnerabilities), "Mitigation: Enhanced / Automatic IBRS; BHI: Vulnerable\n");
+        sysfs.write(format!("{}/meltdown", vulnerabilities), "Not affected\n");
+        sysfs.write(format!("{}/itlb_multihit", vulnerabilities), "KVM: Mitigation: VMX disabled\n");
+        sysfs.write(format!("{}/gather_data_sampling", vulnerabilities), "Vulnerable: No microcode\n");
+        procfs.write("cpuinfo", "processor\t: 0\nmicrocode\t: 0x129\nprocessor\t: 1\nmicrocode\t: 0x129\n");
+        procfs.write("cmdline", "root=/dev/sda1 ro mitigations=off\n");
+
+        let report = SecurityReport::read_from(sysfs.path(), procfs.path()).unwrap();
+        let names = report.vulnerabilities.iter().map(|vulnerability| vulnerability.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["gather_data_sampling", "itlb_multihit", "meltdown", "spectre_v2"]);
+
+        let vulnerable = report.vulnerable().map(|vulnerability| vulnerability.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(vulnerable, vec!["gather_data_sampling", "spectre_v2"]);
+        assert_eq!(report.microcode(), Some(0x129));
+        assert_eq!(report.weakening_flags, vec!["mitigations=off"]);
+
+        // Without procfs, only the vulnerabilities are known
+        let report = SecurityReport::read_from(sysfs.path(), TempDir::new().path()).unwrap();
+        assert_eq!((report.microcode(), report.weakening_flags.len()), (None, 0));
+
+        assert_eq!(SecurityReport::read_from(TempDir::new().path(), procfs.path()), None);
+    }
+}
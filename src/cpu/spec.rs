@@ -0,0 +1,238 @@
+//! Vendor-neutral view of a CPU database record.
+
+use crate::cpu::amd::AMDData;
+use crate::cpu::intel::IntelData;
+use crate::cpu::cache::{total_bytes, CacheSpec};
+use crate::cpu::launch::LaunchDate;
+use crate::cpu::memory::{check_kit, DimmKit, KitCheck, MemorySupport};
+use crate::cpu::power::PowerSpec;
+use crate::utils::first_number;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vendor {
+    Intel,
+    AMD,
+}
+
+impl Vendor {
+    /// The name of the vendor in the database tables, `"intel"` or `"amd"`.
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            Vendor::Intel => "intel",
+            Vendor::AMD => "amd",
+        }
+    }
+
+    pub(crate) fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "intel" => Some(Vendor::Intel),
+            "amd" => Some(Vendor::AMD),
+            _ => None,
+        }
+    }
+}
+
+/// The specification of a CPU with the same units for every vendor.
+///
+/// Fields are `None` (or empty) if the vendor dataset does not have them, e.g. mobile Intel records
+/// have no socket and AMD records have no maximum memory size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuSpec {
+    pub vendor: Vendor,
+    pub name: String,
+    pub cores: Option<usize>,
+    pub threads: Option<usize>,
+    pub base_clock_mhz: Option<usize>,
+    pub boost_clock_mhz: Option<usize>,
+    pub l1_cache_bytes: Option<u64>,
+    pub l2_cache_bytes: Option<u64>,
+    pub l3_cache_bytes: Option<u64>,
+    pub caches: Vec<CacheSpec>,
+    pub power: PowerSpec,
+    pub lithography_nm: Option<usize>,
+    pub socket: Option<String>,
+    pub launch_date: Option<LaunchDate>,
+    /// Supported memory, one entry per kind
+    pub memory: Vec<MemorySupport>,
+    /// Integrated graphics model, `None` if the CPU has none
+    pub integrated_graphics: Option<String>,
+    /// Whether the record comes from a user overlay rather than the upstream datasets
+    pub user_provided: bool,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() || s.eq_ignore_ascii_case("Unknown") || s.eq_ignore_ascii_case("N/A") {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+fn non_zero(n: usize) -> Option<usize> {
+    (n != 0).then_some(n)
+}
+
+impl CpuSpec {
+    /// Fastest supported memory of any kind in MT/s.
+    pub fn max_memory_speed_mts(&self) -> Option<u32> {
+        self.memory.iter().filter_map(|support| support.max_mts).max()
+    }
+
+    /// Checks whether `kit` works with this CPU, see [`check_kit`].
+    pub fn check_memory_kit(&self, kit: &DimmKit) -> KitCheck {
+        check_kit(&self.memory, kit)
+    }
+}
+
+impl From<&IntelData> for CpuSpec {
+    fn from(data: &IntelData) -> Self {
+        CpuSpec {
+            vendor: Vendor::Intel,
+            name: data.name.clone(),
+            cores: data.cores,
+            threads: data.threads,
+            base_clock_mhz: data.base_freq,
+            boost_clock_mhz: data.max_turbo_freq,
+            l1_cache_bytes: total_bytes(&data.caches, 1),
+            l2_cache_bytes: total_bytes(&data.caches, 2),
+            l3_cache_bytes: total_bytes(&data.caches, 3),
+            caches: data.caches.clone(),
+            power: data.power,
+            lithography_nm: data.lithography,
+            socket: data.socket.clone(),
+            launch_date: data.launched,
+            memory: data.memory.clone(),
+            integrated_graphics: data.graphics.as_deref().and_then(non_empty),
+            user_provided: data.user_provided,
+        }
+    }
+}
+
+impl From<&AMDData> for CpuSpec {
+    fn from(data: &AMDData) -> Self {
+        let mhz = |ghz: f32| non_zero((ghz * 1000.0).round() as usize);
+        let integrated_graphics = non_empty(&data.graphics.model)
+            .filter(|model| !model.contains("Discrete Graphics"));
+
+        CpuSpec {
+            vendor: Vendor::AMD,
+            name: data.name.clone(),
+            cores: non_zero(data.cores),
+            threads: non_zero(data.threads),
+            base_clock_mhz: mhz(data.base_clock),
+            boost_clock_mhz: mhz(data.boost_clock),
+            l1_cache_bytes: total_bytes(&data.caches, 1),
+            l2_cache_bytes: total_bytes(&data.caches, 2),
+            l3_cache_bytes: total_bytes(&data.caches, 3),
+            caches: data.caches.clone(),
+            power: data.power,
+            lithography_nm: first_number(&data.lithography).map(|nm| nm as usize),
+            socket: non_empty(&data.cpu_socket),
+            launch_date: data.launched,
+            memory: data.memory.clone(),
+            integrated_graphics,
+            user_provided: data.user_provided,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::amd::eAMDData;
+    use crate::cpu::cache::CacheKind;
+    use crate::cpu::database::CpuDatabase;
+    use crate::cpu::intel::eIntelData;
+    use crate::cpu::memory::MemoryKind;
+    use crate::cpu::query::{CpuQuery, Op};
+    use crate::tests::TempDir;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn intel(db: &CpuDatabase, name: &'static str) -> CpuSpec {
+        let records = CpuQuery::<IntelData>::new().filter(eIntelData::Name, Op::Eq, name).fetch_in(db).unwrap();
+        CpuSpec::from(&records[0])
+    }
+
+    fn amd(db: &CpuDatabase, name: &'static str) -> CpuSpec {
+        let records = CpuQuery::<AMDData>::new().filter(eAMDData::Name, Op::Eq, name).fetch_in(db).unwrap();
+        CpuSpec::from(&records[0])
+    }
+
+    #[test]
+    fn from_intel_data() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let spec = intel(&db, "Core i9-13900K");
+
+        assert_eq!(spec.vendor, Vendor::Intel);
+        assert_eq!((spec.cores, spec.threads), (Some(24), Some(32)));
+        // The dataset has the clocks in GHz
+        assert_eq!((spec.base_clock_mhz, spec.boost_clock_mhz), (Some(3000), Some(5800)));
+        // and only the size of the last level cache, in MB
+        assert_eq!((spec.l1_cache_bytes, spec.l2_cache_bytes, spec.l3_cache_bytes), (None, None, Some(36 * MIB)));
+        assert_eq!(spec.caches.len(), 1);
+        assert_eq!((spec.caches[0].kind, spec.caches[0].shared), (CacheKind::Unified, true));
+        assert_eq!(spec.power.default_w, Some(125.0));
+        assert_eq!(spec.socket.as_deref(), Some("LGA1700"));
+        assert_eq!(spec.launch_date, LaunchDate::quarter(2022, 4));
+        assert_eq!(spec.integrated_graphics.as_deref(), Some("Intel UHD Graphics 770"));
+        assert_eq!(spec.memory.iter().map(|m| (m.kind, m.max_mts)).collect::<Vec<_>>(), vec![
+            (MemoryKind::DDR5, Some(5600)),
+            (MemoryKind::DDR4, Some(3200)),
+        ]);
+        assert_eq!(spec.max_memory_speed_mts(), Some(5600));
+        assert!(!spec.user_provided);
+
+        assert_eq!(intel(&db, "Core i9-13900KF").integrated_graphics, None);
+    }
+
+    #[test]
+    fn from_amd_data() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let spec = amd(&db, "AMD Ryzen™ 7 7800X3D");
+
+        assert_eq!(spec.vendor, Vendor::AMD);
+        assert_eq!((spec.cores, spec.threads), (Some(8), Some(16)));
+        assert_eq!((spec.base_clock_mhz, spec.boost_clock_mhz), (Some(4200), Some(5000)));
+        assert_eq!(
+            (spec.l1_cache_bytes, spec.l2_cache_bytes, spec.l3_cache_bytes),
+            (Some(512 * 1024), Some(8 * MIB), Some(96 * MIB))
+        );
+        assert_eq!(spec.caches.iter().map(|c| (c.level, c.kind, c.shared)).collect::<Vec<_>>(), vec![
+            (1, CacheKind::Split, false),
+            (2, CacheKind::Unified, false),
+            (3, CacheKind::Unified, true),
+        ]);
+        assert_eq!(spec.power.default_w, Some(120.0));
+        assert_eq!(spec.lithography_nm, Some(5));
+        assert_eq!(spec.socket.as_deref(), Some("AM5"));
+        assert_eq!(spec.launch_date, LaunchDate::month(2023, 4));
+        assert_eq!(spec.integrated_graphics.as_deref(), Some("AMD Radeon™ Graphics"));
+        assert_eq!(spec.max_memory_speed_mts(), Some(5200));
+
+        let laptop = amd(&db, "AMD Ryzen™ 7 7840HS");
+        assert_eq!((laptop.power.ctdp_min_w, laptop.power.ctdp_max_w), (Some(35.0), Some(54.0)));
+        assert_eq!(laptop.boost_clock_mhz, Some(5100));
+    }
+
+    #[test]
+    fn missing_amd_values() {
+        let dir = TempDir::new();
+        let overlay = dir.write(
+            "amd.csv",
+            "Name,# of CPU Cores,Base Clock,CPU Socket,Graphics Model\n\
+             AMD Ryzen™ 5 9999X,,3.7 GHz,N/A,Discrete Graphics Card Required\n",
+        );
+        let mut db = CpuDatabase::in_memory().unwrap();
+        db.add_overlay(Vendor::AMD, &overlay).unwrap();
+
+        let spec = amd(&db, "AMD Ryzen™ 5 9999X");
+        assert_eq!((spec.cores, spec.threads), (None, None));
+        assert_eq!((spec.base_clock_mhz, spec.boost_clock_mhz), (Some(3700), None));
+        assert_eq!((spec.l3_cache_bytes, spec.lithography_nm), (None, None));
+        assert_eq!(spec.socket, None);
+        assert_eq!(spec.integrated_graphics, None);
+        assert!(spec.user_provided);
+    }
+}
@@ -0,0 +1,327 @@
+//! Packages, dies, physical cores and SMT threads of the current machine, read from Linux sysfs.
+//!
+//! Every reader takes the sysfs root as a parameter, so recorded sysfs trees can be read like the
+//! live one at `/sys`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::utils::{parse_cpu_list, read_sysfs, read_sysfs_number, sysfs_cpus, SYSFS_CPU_DIR, SYSFS_ROOT};
+
+/// The kind of a core on a hybrid CPU, e.g. Alder Lake and later Intel CPUs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreType {
+    /// A P-core, listed in `devices/cpu_core/cpus`
+    Performance,
+    /// An E-core, listed in `devices/cpu_atom/cpus`
+    Efficient,
+}
+
+/// A logical CPU, i.e. a hardware thread, as numbered by the kernel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalCpu {
+    pub id: usize,
+    pub package: u32,
+    pub die: u32,
+    pub core_id: u32,
+    /// The NUMA node of the CPU, `None` on kernels without NUMA support
+    pub node: Option<u32>,
+}
+
+/// A physical core and its SMT threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicalCore {
+    pub package: u32,
+    /// The die of the core within its package, a CCD on AMD CPUs if the kernel reports them
+    pub die: u32,
+    pub core_id: u32,
+    /// The logical CPUs running on the core, one per SMT thread
+    pub threads: Vec<usize>,
+    /// `None` unless the CPU is hybrid
+    pub core_type: Option<CoreType>,
+    pub node: Option<u32>,
+    /// Index of the core in [`CpuTopology::l3_groups`], `None` if the kernel does not report
+    /// the L3 cache
+    pub l3_group: Option<usize>,
+}
+
+/// A NUMA node and its logical CPUs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumaNode {
+    pub id: u32,
+    pub cpus: Vec<usize>,
+}
+
+/// The topology of the CPUs of the machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuTopology {
+    /// The online logical CPUs, sorted by id
+    pub cpus: Vec<LogicalCpu>,
+    /// The physical cores, sorted by their first thread
+    pub cores: Vec<PhysicalCore>,
+    pub numa_nodes: Vec<NumaNode>,
+    /// Logical CPUs sharing an L3 cache. On AMD Zen CPUs every group is a CCX.
+    pub l3_groups: Vec<Vec<usize>>,
+    /// Logical CPUs that exist but are offline, e.g. disabled SMT threads
+    pub offline: Vec<usize>,
+}
+
+/// The NUMA node of the CPU at `cpu_path`, from its `node<N>` link.
+fn numa_node(cpu_path: &Path) -> Option<u32> {
+    std::fs::read_dir(cpu_path)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+        .next()
+}
+
+/// The logical CPUs sharing the L3 cache of the CPU at `cpu_path`.
+fn l3_cpus(cpu_path: &Path) -> Option<Vec<usize>> {
+    let caches = std::fs::read_dir(cpu_path.join("cache")).ok()?;
+
+    caches.filter_map(Result::ok).find_map(|entry| {
+        let level = read_sysfs_number::<u8>(entry.path().join("level"))?;
+        (level == 3).then(|| read_sysfs(entry.path().join("shared_cpu_list")).map(|list| parse_cpu_list(&list)))?
+    })
+}
+
+impl CpuTopology {
+    /// Reads the topology from `/sys`, `None` if it is not available, e.g. on other operating
+    /// systems.
+    pub fn read() -> Option<Self> {
+        Self::read_from(SYSFS_ROOT)
+    }
+
+    /// Reads the topology from the sysfs tree at `sysfs`, e.g. a copy of `/sys` recorded on
+    /// another machine. `None` if it has no CPUs with topology information.
+    pub fn read_from(sysfs: impl AsRef<Path>) -> Option<Self> {
+        let sysfs = sysfs.as_ref();
+        let cpu_dir = sysfs.join(SYSFS_CPU_DIR);
+
+        let core_types = [("cpu_core", CoreType::Performance), ("cpu_atom", CoreType::Efficient)]
+            .into_iter()
+            .filter_map(|(device, core_type)| {
+                let cpus = read_sysfs(sysfs.join("devices").join(device).join("cpus"))?;
+                Some((core_type, parse_cpu_list(&cpus)))
+            })
+            .collect::<Vec<_>>();
+        let core_type = |cpu: usize| {
+            core_types
+                .iter()
+                .find(|(_, cpus)| cpus.contains(&cpu))
+                .map(|(core_type, _)| *core_type)
+        };
+
+        let mut cpus = Vec::new();
+        let mut cores = BTreeMap::<(u32, usize), PhysicalCore>::new();
+        let mut l3_groups: Vec<Vec<usize>> = Vec::new();
+
+        for (id, path) in sysfs_cpus(&cpu_dir) {
+            let topology = path.join("topology");
+            // Offline CPUs have no topology directory
+            let Some(package) = read_sysfs_number(topology.join("physical_package_id")) else {
+                continue;
+            };
+            let die = read_sysfs_number(topology.join("die_id")).unwrap_or(0);
+            let core_id = read_sysfs_number(topology.join("core_id")).unwrap_or(id as u32);
+            let node = numa_node(&path);
+
+            let threads = read_sysfs(topology.join("core_cpus_list"))
+                .or_else(|| read_sysfs(topology.join("thread_siblings_list")))
+                .map(|list| parse_cpu_list(&list))
+                .filter(|threads| threads.contains(&id))
+                .unwrap_or_else(|| vec![id]);
+
+            let l3_group = l3_cpus(&path).map(|group| {
+                l3_groups.iter().position(|known| *known == group).unwrap_or_else(|| {
+                    l3_groups.push(group);
+                    l3_groups.len() - 1
+                })
+            });
+
+            cores.entry((package, threads[0])).or_insert_with(|| PhysicalCore {
+                package,
+                die,
+                core_id,
+                threads: Vec::new(),
+                core_type: core_type(id),
+                node,
+                l3_group,
+            }).threads.push(id);
+
+            cpus.push(LogicalCpu { id, package, die, core_id, node });
+        }
+
+        if cpus.is_empty() {
+            return None;
+        }
+
+        let mut numa_nodes = BTreeMap::<u32, Vec<usize>>::new();
+        for cpu in &cpus {
+            if let Some(node) = cpu.node {
+                numa_nodes.entry(node).or_default().push(cpu.id);
+            }
+        }
+
+        let mut cores = cores.into_values().collect::<Vec<_>>();
+        cores.sort_by_key(|core| core.threads[0]);
+
+        Some(CpuTopology {
+            cpus,
+            cores,
+            numa_nodes: numa_nodes.into_iter().map(|(id, cpus)| NumaNode { id, cpus }).collect(),
+            l3_groups,
+            offline: read_sysfs(cpu_dir.join("offline")).map(|list| parse_cpu_list(&list)).unwrap_or_default(),
+        })
+    }
+
+    pub fn packages(&self) -> usize {
+        let mut packages = self.cpus.iter().map(|cpu| cpu.package).collect::<Vec<_>>();
+        packages.sort_unstable();
+        packages.dedup();
+        packages.len()
+    }
+
+    /// The number of dies across all packages.
+    pub fn dies(&self) -> usize {
+        let mut dies = self.cpus.iter().map(|cpu| (cpu.package, cpu.die)).collect::<Vec<_>>();
+        dies.sort_unstable();
+        dies.dedup();
+        dies.len()
+    }
+
+    pub fn physical_cores(&self) -> usize {
+        self.cores.len()
+    }
+
+    /// The number of online logical CPUs.
+    pub fn logical_cpus(&self) -> usize {
+        self.cpus.len()
+    }
+
+    /// Whether any core runs more than one thread.
+    pub fn smt_active(&self) -> bool {
+        self.cores.iter().any(|core| core.threads.len() > 1)
+    }
+
+    /// Whether the CPU has both P-cores and E-cores.
+    pub fn is_hybrid(&self) -> bool {
+        self.cores.iter().any(|core| core.core_type == Some(CoreType::Performance))
+            && self.cores.iter().any(|core| core.core_type == Some(CoreType::Efficient))
+    }
+
+    /// The cores of `core_type`, empty unless the CPU is hybrid.
+    pub fn cores_of_type(&self, core_type: CoreType) -> impl Iterator<Item = &PhysicalCore> {
+        self.cores.iter().filter(move |core| core.core_type == Some(core_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    /// Writes the topology of logical CPU `id` below `sysfs`. `l3` is the `shared_cpu_list` of its
+    /// L3 cache.
+    fn write_cpu(sysfs: &TempDir, id: usize, (package, die, core_id): (u32, u32, u32), siblings: &str, node: Option<u32>, l3: Option<&str>) {
+        let cpu = format!("{}/cpu{}", SYSFS_CPU_DIR, id);
+        sysfs.write(format!("{}/topology/physical_package_id", cpu), &format!("{}\n", package));
+        sysfs.write(format!("{}/topology/die_id", cpu), &format!("{}\n", die));
+        sysfs.write(format!("{}/topology/core_id", cpu), &format!("{}\n", core_id));
+        sysfs.write(format!("{}/topology/core_cpus_list", cpu), &format!("{}\n", siblings));
+        if let Some(node) = node {
+            sysfs.create_dir(format!("{}/node{}", cpu, node));
+        }
+        if let Some(l3) = l3 {
+            sysfs.write(format!("{}/cache/index0/level", cpu), "1\n");
+            sysfs.write(format!("{}/cache/index0/shared_cpu_list", cpu), &format!("{}\n", siblings));
+            sysfs.write(format!("{}/cache/index3/level", cpu), "3\n");
+            sysfs.write(format!("{}/cache/index3/shared_cpu_list", cpu), &format!("{}\n", l3));
+        }
+    }
+
+    #[test]
+    fn smt_siblings() {
+        let sysfs = TempDir::new();
+        // The kernel numbers the second thread of every core after the first threads
+        for (id, siblings) in [(0, "0,2"), (1, "1,3"), (2, "0,2"), (3, "1,3")] {
+            write_cpu(&sysfs, id, (0, 0, id as u32 % 2), siblings, Some(0), Some("0-3"));
+        }
+        // Offline CPUs have no topology directory
+        sysfs.create_dir(format!("{}/cpu4", SYSFS_CPU_DIR));
+        sysfs.write(format!("{}/offline", SYSFS_CPU_DIR), "4\n");
+
+        let topology = CpuTopology::read_from(sysfs.path()).unwrap();
+        assert_eq!((topology.logical_cpus(), topology.physical_cores()), (4, 2));
+        assert!(topology.smt_active());
+        assert!(!topology.is_hybrid());
+        assert_eq!(topology.offline, vec![4]);
+
+        let threads = topology.cores.iter().map(|core| core.threads.clone()).collect::<Vec<_>>();
+        assert_eq!(threads, vec![vec![0, 2], vec![1, 3]]);
+        assert_eq!(topology.cores[1].core_id, 1);
+        assert!(topology.cores.iter().all(|core| core.core_type.is_none()));
+    }
+
+    #[test]
+    fn hybrid_cores() {
+        let sysfs = TempDir::new();
+        // Two P-cores with two threads each, then four E-cores
+        for id in 0..4 {
+            let siblings = if id < 2 { "0-1" } else { "2-3" };
+            write_cpu(&sysfs, id, (0, 0, id as u32 / 2 * 4), siblings, Some(0), Some("0-7"));
+        }
+        for id in 4..8 {
+            write_cpu(&sysfs, id, (0, 0, 8 + id as u32), &id.to_string(), Some(0), Some("0-7"));
+        }
+        sysfs.write("devices/cpu_core/cpus", "0-3\n");
+        sysfs.write("devices/cpu_atom/cpus", "4-7\n");
+
+        let topology = CpuTopology::read_from(sysfs.path()).unwrap();
+        assert!(topology.is_hybrid());
+        assert!(topology.smt_active());
+        assert_eq!((topology.logical_cpus(), topology.physical_cores()), (8, 6));
+        assert_eq!(topology.cores_of_type(CoreType::Performance).count(), 2);
+        assert_eq!(topology.cores_of_type(CoreType::Efficient).count(), 4);
+
+        let p_core = &topology.cores[1];
+        assert_eq!((p_core.core_id, &p_core.threads, p_core.core_type), (4, &vec![2, 3], Some(CoreType::Performance)));
+        assert_eq!(topology.cores[5].core_type, Some(CoreType::Efficient));
+        assert!(topology.offline.is_empty());
+    }
+
+    #[test]
+    fn numa_nodes_and_l3_groups() {
+        let sysfs = TempDir::new();
+        // Two packages with one NUMA node and two dies each, every die with its own L3 cache
+        for id in 0..8 {
+            let package = id as u32 / 4;
+            let die = id as u32 / 2 % 2;
+            let l3 = format!("{}-{}", id / 2 * 2, id / 2 * 2 + 1);
+            write_cpu(&sysfs, id, (package, die, id as u32 % 4), &id.to_string(), Some(package), Some(&l3));
+        }
+
+        let topology = CpuTopology::read_from(sysfs.path()).unwrap();
+        assert_eq!((topology.packages(), topology.dies(), topology.physical_cores()), (2, 4, 8));
+        assert!(!topology.smt_active());
+        assert_eq!(topology.numa_nodes, vec![
+            NumaNode { id: 0, cpus: vec![0, 1, 2, 3] },
+            NumaNode { id: 1, cpus: vec![4, 5, 6, 7] },
+        ]);
+        assert_eq!(topology.l3_groups, vec![vec![0, 1], vec![2, 3], vec![4, 5], vec![6, 7]]);
+
+        let l3_groups = topology.cores.iter().map(|core| core.l3_group).collect::<Vec<_>>();
+        assert_eq!(l3_groups, [0, 0, 1, 1, 2, 2, 3, 3].map(Some));
+        assert_eq!(topology.cpus[5], LogicalCpu { id: 5, package: 1, die: 0, core_id: 1, node: Some(1) });
+    }
+
+    #[test]
+    fn without_numa_and_caches() {
+        let sysfs = TempDir::new();
+        write_cpu(&sysfs, 0, (0, 0, 0), "0", None, None);
+
+        let topology = CpuTopology::read_from(sysfs.path()).unwrap();
+        assert!(topology.numa_nodes.is_empty() && topology.l3_groups.is_empty());
+        assert_eq!((topology.cores[0].node, topology.cores[0].l3_group), (None, None));
+
+        assert_eq!(CpuTopology::read_from(TempDir::new().path()), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Drop-in upgrades for a CPU: the CPUs of the database that fit the same socket and are faster.
+
+use crate::cpu::amd::{eAMDData, AMDData};
+use crate::cpu::compare::{format_delta, Comparison, Winner};
+use crate::cpu::database::CpuDatabase;
+use crate::cpu::intel::{eIntelData, IntelData};
+use crate::cpu::query::{CpuQuery, Op};
+use crate::cpu::spec::CpuSpec;
+use crate::cpu::CPUDetails;
+
+/// Fields of a [`Comparison`] that make a CPU an upgrade. An upgrade is better in at least one of
+/// them and worse in none.
+const RANKED_FIELDS: &[&str] = &["Cores", "Threads", "Base clock", "Boost clock", "L3 cache"];
+
+/// A CPU that fits the socket of the current one, see [`upgrade_candidates`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeCandidate {
+    pub spec: CpuSpec,
+    /// The current CPU as `a` and the candidate as `b`
+    pub comparison: Comparison,
+    /// Sum of the relative improvements in cores, threads, clocks and L3 cache, in percent, none
+    /// of which is negative. Candidates are sorted by it, best first.
+    pub score: f64,
+}
+
+impl UpgradeCandidate {
+    /// The differences to the current CPU, e.g. `"Cores +8, Boost clock +500 MHz, L3 cache +32 MiB"`.
+    pub fn summary(&self) -> String {
+        self.comparison
+            .fields
+            .iter()
+            .filter(|field| RANKED_FIELDS.contains(&field.field))
+            .filter_map(|field| {
+                let delta = field.delta.filter(|&delta| delta != 0.0)?;
+                Some(format!("{} {}", field.field, format_delta(delta, field.unit)))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Ranks `comparison.b` as an upgrade of `comparison.a`, `None` if it is not one, e.g. because it
+/// has more cores but less L3 cache.
+fn score(comparison: &Comparison) -> Option<f64> {
+    let ranked = comparison
+        .fields
+        .iter()
+        .filter(|field| RANKED_FIELDS.contains(&field.field))
+        .collect::<Vec<_>>();
+
+    let worse = ranked.iter().any(|field| field.winner == Winner::A);
+    let better = ranked.iter().any(|field| field.winner == Winner::B);
+    if worse || !better {
+        return None;
+    }
+
+    Some(ranked.iter().filter_map(|field| field.delta_percent).sum())
+}
+
+/// Finds the CPUs of the default [`CpuDatabase`] that fit the socket of `current`, have more
+/// cores, threads, clock speed or L3 cache and less of none of them, best first. Empty if the
+/// socket of `current` is not known, e.g. for soldered mobile processors.
+pub fn upgrade_candidates(current: &CPUDetails) -> Result<Vec<UpgradeCandidate>, rusqlite::Error> {
+    upgrade_candidates_in(&CpuDatabase::open_default()?, current)
+}
+
+/// Like [`upgrade_candidates`], but looks the candidates up in `db`.
+pub fn upgrade_candidates_in(db: &CpuDatabase, current: &CPUDetails) -> Result<Vec<UpgradeCandidate>, rusqlite::Error> {
+    match &current.spec {
+        Some(spec) => candidates_for_in(db, spec),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Like [`upgrade_candidates_in`], but for any CPU of the database, e.g. one found with
+/// [`lookup_in`](crate::cpu::compare::lookup_in).
+pub fn candidates_for_in(db: &CpuDatabase, current: &CpuSpec) -> Result<Vec<UpgradeCandidate>, rusqlite::Error> {
+    let Some(socket) = &current.socket else {
+        return Ok(Vec::new());
+    };
+
+    let mut specs = CpuQuery::<IntelData>::new()
+        .filter(eIntelData::Socket, Op::Eq, socket.clone())
+        .fetch_in(db)?
+        .iter()
+        .map(CpuSpec::from)
+        .collect::<Vec<_>>();
+    specs.extend(
+        CpuQuery::<AMDData>::new()
+            .filter(eAMDData::Socket, Op::Eq, socket.clone())
+            .fetch_in(db)?
+            .iter()
+            .map(CpuSpec::from),
+    );
+
+    let mut candidates = specs
+        .into_iter()
+        .filter(|spec| spec.name != current.name)
+        .filter_map(|spec| {
+            let comparison = Comparison::new(current.clone(), spec.clone());
+            let score = score(&comparison)?;
+            Some(UpgradeCandidate { spec, comparison, score })
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::compare::lookup_in;
+    use crate::cpu::spec::Vendor;
+    use crate::tests::TempDir;
+
+    /// The embedded data with the 7950X3D, which is better than the 7800X3D in everything.
+    fn database(dir: &TempDir) -> CpuDatabase {
+        let overlay = dir.write(
+            "amd.csv",
+            "Name,# of CPU Cores,# of Threads,Max. Boost Clock,Base Clock,L3 Cache,CPU Socket\n\
+             AMD Ryzen™ 9 7950X3D,16,32,Up to 5.7 GHz,4.2 GHz,128MB,AM5\n",
+        );
+        let mut db = CpuDatabase::in_memory().unwrap();
+        db.add_overlay(Vendor::AMD, &overlay).unwrap();
+        db
+    }
+
+    fn names(candidates: &[UpgradeCandidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| candidate.spec.name.as_str()).collect()
+    }
+
+    #[test]
+    fn upgrades_are_worse_in_nothing() {
+        let dir = TempDir::new();
+        let db = database(&dir);
+        let current = lookup_in(&db, "AMD Ryzen 7 7800X3D 8-Core Processor").unwrap().unwrap();
+
+        // The 7950X has twice the cores but less L3 cache
+        let candidates = candidates_for_in(&db, &current).unwrap();
+        assert_eq!(names(&candidates), vec!["AMD Ryzen™ 9 7950X3D"]);
+
+        let candidate = &candidates[0];
+        assert_eq!(candidate.summary(), "Cores +8, Threads +16, Boost clock +700 MHz, L3 cache +32 MiB");
+        assert!((candidate.score - (100.0 + 100.0 + 14.0 + 100.0 / 3.0)).abs() < 1e-9);
+        assert!(candidate.comparison.fields.iter().all(|field| !RANKED_FIELDS.contains(&field.field) || field.winner != Winner::A));
+
+        // Neither is better than the 7950X3D in everything
+        let best = lookup_in(&db, "AMD Ryzen 9 7950X3D 16-Core Processor").unwrap().unwrap();
+        assert!(candidates_for_in(&db, &best).unwrap().is_empty());
+    }
+
+    #[test]
+    fn regressions_are_no_upgrade() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let x3d = lookup_in(&db, "AMD Ryzen 7 7800X3D 8-Core Processor").unwrap().unwrap();
+        let more_cores = lookup_in(&db, "AMD Ryzen 9 7950X 16-Core Processor").unwrap().unwrap();
+
+        assert_eq!(score(&Comparison::new(x3d.clone(), more_cores.clone())), None);
+        assert_eq!(score(&Comparison::new(more_cores, x3d.clone())), None);
+        // Equal in everything is no upgrade either
+        assert_eq!(score(&Comparison::new(x3d.clone(), x3d)), None);
+
+        let k = lookup_in(&db, "13th Gen Intel(R) Core(TM) i9-13900K").unwrap().unwrap();
+        assert_eq!(k.socket.as_deref(), Some("LGA1700"));
+        assert!(candidates_for_in(&db, &k).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pentium_upgrades() {
+        let dir = TempDir::new();
+        let overlay = dir.write(
+            "pentium.csv",
+            "Product,Code Name,Cores,Threads,Base Freq. (GHz),Cache (MB)\n\
+             Pentium Gold G6400,Comet Lake,2,4,4.00,4\n\
+             Pentium Gold G6600,Comet Lake,2,4,4.20,4\n\
+             Celeron G5905,Comet Lake,2,2,3.50,4\n",
+        );
+        let mut db = CpuDatabase::in_memory().unwrap();
+        db.add_overlay(Vendor::Intel, &overlay).unwrap();
+
+        let current = lookup_in(&db, "Intel(R) Pentium(R) Gold G6400 CPU @ 4.00GHz").unwrap().unwrap();
+        assert_eq!(current.socket.as_deref(), Some("LGA1200"));
+
+        let candidates = candidates_for_in(&db, &current).unwrap();
+        assert_eq!(names(&candidates), vec!["Pentium Gold G6600"]);
+        assert_eq!(candidates[0].summary(), "Base clock +200 MHz");
+    }
+
+    #[test]
+    fn without_socket() {
+        let db = CpuDatabase::in_memory().unwrap();
+        let mut current = lookup_in(&db, "AMD Ryzen 7 7800X3D 8-Core Processor").unwrap().unwrap();
+        current.socket = None;
+
+        assert!(candidates_for_in(&db, &current).unwrap().is_empty());
+    }
+}
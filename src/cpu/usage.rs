@@ -0,0 +1,350 @@
+//! CPU utilization from the time counters of Linux `/proc/stat`.
+//!
+//! The counters only grow, so utilization is the difference between two readings. A reading can
+//! also be parsed from recorded `/proc/stat` text with [`ProcStat::parse`].
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where procfs keeps the CPU time counters.
+const PROC_STAT: &str = "/proc/stat";
+
+/// Time a CPU spent in each state since boot, in clock ticks (usually 1/100 s). `guest` and
+/// `guest_nice` are also counted in `user` and `nice`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    /// Time the hypervisor ran other virtual machines while this one wanted to run
+    pub steal: u64,
+    pub guest: u64,
+    pub guest_nice: u64,
+}
+
+impl CpuTimes {
+    /// Parses the counters after the `cpu`/`cpu<N>` label. Older kernels have fewer columns,
+    /// the missing ones are 0.
+    fn parse(counters: &str) -> Option<Self> {
+        let mut values = counters.split_whitespace().map(|value| value.parse::<u64>().ok());
+        let mut next = || values.next().unwrap_or(Some(0));
+
+        Some(CpuTimes {
+            user: next()?,
+            nice: next()?,
+            system: next()?,
+            idle: next()?,
+            iowait: next()?,
+            irq: next()?,
+            softirq: next()?,
+            steal: next()?,
+            guest: next()?,
+            guest_nice: next()?,
+        })
+    }
+
+    /// Every tick, without `guest` and `guest_nice` which are already part of `user` and `nice`.
+    /// Saturates instead of overflowing on bogus counters, e.g. from a corrupted recording.
+    pub fn total(&self) -> u64 {
+        [self.user, self.nice, self.system, self.idle, self.iowait, self.irq, self.softirq, self.steal]
+            .into_iter()
+            .fold(0, u64::saturating_add)
+    }
+}
+
+/// One reading of `/proc/stat`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcStat {
+    /// The sum of every CPU, the `cpu` line
+    pub total: CpuTimes,
+    /// Every logical CPU and its counters, sorted by CPU
+    pub cpus: Vec<(usize, CpuTimes)>,
+}
+
+impl ProcStat {
+    /// Reads `/proc/stat`, `None` if it is not available, e.g. on other operating systems.
+    pub fn read() -> Option<Self> {
+        Self::read_from(PROC_STAT)
+    }
+
+    /// Reads a file with the format of `/proc/stat`, e.g. a recorded copy.
+    pub fn read_from(path: impl AsRef<Path>) -> Option<Self> {
+        Self::parse(&std::fs::read_to_string(path).ok()?)
+    }
+
+    /// Parses the `cpu` lines of `/proc/stat` text, `None` if it has no `cpu` line.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut total = None;
+        let mut cpus = Vec::new();
+
+        for line in text.lines() {
+            let Some((label, counters)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let Some(cpu) = label.strip_prefix("cpu") else {
+                continue;
+            };
+            let Some(times) = CpuTimes::parse(counters) else {
+                continue;
+            };
+
+            if cpu.is_empty() {
+                total = Some(times);
+            } else if let Ok(cpu) = cpu.parse() {
+                cpus.push((cpu, times));
+            }
+        }
+
+        cpus.sort_by_key(|(cpu, _)| *cpu);
+        Some(ProcStat { total: total?, cpus })
+    }
+}
+
+/// The share of time a CPU spent in each state between two readings, in percent of the elapsed
+/// ticks. `user` and `nice` include time spent running guests.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuUsage {
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+}
+
+impl CpuUsage {
+    /// The usage between the counters `before` and `after` of the same CPU. All zero if no tick
+    /// elapsed, e.g. for readings taken too close together.
+    pub fn between(before: &CpuTimes, after: &CpuTimes) -> Self {
+        let total = after.total().saturating_sub(before.total());
+        if total == 0 {
+            return CpuUsage::default();
+        }
+
+        let percent = |before: u64, after: u64| after.saturating_sub(before) as f64 * 100.0 / total as f64;
+        CpuUsage {
+            user: percent(before.user, after.user),
+            nice: percent(before.nice, after.nice),
+            system: percent(before.system, after.system),
+            idle: percent(before.idle, after.idle),
+            iowait: percent(before.iowait, after.iowait),
+            irq: percent(before.irq, after.irq),
+            softirq: percent(before.softirq, after.softirq),
+            steal: percent(before.steal, after.steal),
+        }
+    }
+
+    /// Time the CPU was running something, i.e. not idle, waiting for I/O or stolen.
+    pub fn busy(&self) -> f64 {
+        self.user + self.nice + self.system + self.irq + self.softirq
+    }
+}
+
+/// The usage of the CPUs over one interval, see [`CpuUsageSampler`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageSample {
+    /// Time since the sampler was created, at the end of the interval
+    pub elapsed: Duration,
+    /// The usage of all CPUs together
+    pub total: CpuUsage,
+    /// Every logical CPU that is in both readings and its usage
+    pub cpus: Vec<(usize, CpuUsage)>,
+}
+
+impl UsageSample {
+    /// The usage between two readings of `/proc/stat`.
+    pub fn between(before: &ProcStat, after: &ProcStat, elapsed: Duration) -> Self {
+        UsageSample {
+            elapsed,
+            total: CpuUsage::between(&before.total, &after.total),
+            cpus: after
+                .cpus
+                .iter()
+                .filter_map(|(cpu, times)| {
+                    let (_, previous) = before.cpus.iter().find(|(previous, _)| previous == cpu)?;
+                    Some((*cpu, CpuUsage::between(previous, times)))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Measures CPU usage over consecutive intervals. Every sample starts where the previous one ended,
+/// so continuous sampling misses no time. As an iterator, every call blocks for the interval and
+/// it ends if `/proc/stat` cannot be read, e.g.
+/// `CpuUsageSampler::new(Duration::from_secs(1)).take(10)` for ten one-second samples.
+#[derive(Debug, Clone)]
+pub struct CpuUsageSampler {
+    interval: Duration,
+    path: PathBuf,
+    previous: Option<ProcStat>,
+    started: Instant,
+}
+
+impl CpuUsageSampler {
+    /// Samples `/proc/stat` every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self::with_proc_stat(PROC_STAT, interval)
+    }
+
+    /// Samples the file at `path`, which has the format of `/proc/stat`, every `interval`.
+    pub fn with_proc_stat(path: impl AsRef<Path>, interval: Duration) -> Self {
+        CpuUsageSampler {
+            interval,
+            path: path.as_ref().to_path_buf(),
+            previous: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Waits for the interval and returns the usage since the previous sample, or since the call
+    /// for the first sample. `None` if `/proc/stat` cannot be read.
+    pub fn sample(&mut self) -> Option<UsageSample> {
+        let previous = match self.previous.take() {
+            Some(previous) => previous,
+            None => ProcStat::read_from(&self.path)?,
+        };
+
+        thread::sleep(self.interval);
+        let current = ProcStat::read_from(&self.path)?;
+        let sample = UsageSample::between(&previous, &current, self.started.elapsed());
+
+        self.previous = Some(current);
+        Some(sample)
+    }
+
+    /// Samples until `callback` returns `false` or `/proc/stat` cannot be read.
+    pub fn run(&mut self, mut callback: impl FnMut(&UsageSample) -> bool) {
+        while let Some(sample) = self.sample() {
+            if !callback(&sample) {
+                break;
+            }
+        }
+    }
+}
+
+impl Iterator for CpuUsageSampler {
+    type Item = UsageSample;
+
+    fn next(&mut self) -> Option<UsageSample> {
+        self.sample()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    const STAT: &str = "\
+cpu  1000 50 300 8000 100 20 30 0 0 0
+cpu1 600 0 150 3900 50 10 15 0 0 0
+cpu0 400 50 150 4100 50 10 15 0 0 0
+intr 123456 0 0
+ctxt 987654
+cpufreq 1 2 3
+";
+
+    fn times(user: u64, system: u64, idle: u64) -> CpuTimes {
+        CpuTimes { user, system, idle, ..CpuTimes::default() }
+    }
+
+    #[test]
+    fn parse_proc_stat() {
+        let stat = ProcStat::parse(STAT).unwrap();
+
+        assert_eq!(stat.total, CpuTimes { user: 1000, nice: 50, system: 300, idle: 8000, iowait: 100, irq: 20, softirq: 30, ..CpuTimes::default() });
+        assert_eq!(stat.total.total(), 9500);
+        assert_eq!(stat.cpus.iter().map(|(cpu, _)| *cpu).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(stat.cpus[0].1.nice, 50);
+
+        // Older kernels have no iowait, irq, softirq, steal and guest columns
+        let stat = ProcStat::parse("cpu 10 20 30 40\ncpu0 10 20 30 40\ncpu1 x y z\n").unwrap();
+        assert_eq!(stat.total, CpuTimes { user: 10, nice: 20, system: 30, idle: 40, ..CpuTimes::default() });
+        assert_eq!(stat.cpus.len(), 1);
+
+        // Guest time is already part of user time
+        let stat = ProcStat::parse("cpu 100 0 0 100 0 0 0 5 40 0\n").unwrap();
+        assert_eq!(stat.total.total(), 205);
+
+        assert_eq!(ProcStat::parse("intr 1 2 3\n"), None);
+    }
+
+    #[test]
+    fn usage_between_readings() {
+        let usage = CpuUsage::between(&times(100, 50, 850), &times(160, 70, 970));
+
+        assert_eq!(usage, CpuUsage { user: 30.0, system: 10.0, idle: 60.0, ..CpuUsage::default() });
+        assert_eq!(usage.busy(), 40.0);
+    }
+
+    #[test]
+    fn usage_without_elapsed_ticks() {
+        let before = times(100, 50, 850);
+
+        assert_eq!(CpuUsage::between(&before, &before), CpuUsage::default());
+        // Counters that went back, e.g. after a CPU was taken offline and back online
+        assert_eq!(CpuUsage::between(&before, &times(10, 5, 85)), CpuUsage::default());
+    }
+
+    #[test]
+    fn usage_with_wrapped_counter() {
+        // iowait can go back between readings, which counts as no time waiting
+        let before = CpuTimes { iowait: 200, ..times(100, 50, 650) };
+        let after = CpuTimes { iowait: 150, ..times(200, 50, 800) };
+
+        let usage = CpuUsage::between(&before, &after);
+        assert_eq!(usage, CpuUsage { user: 50.0, idle: 75.0, ..CpuUsage::default() });
+
+        // Counters at the end of their range saturate instead of overflowing
+        let full = CpuTimes { user: u64::MAX, idle: u64::MAX, ..CpuTimes::default() };
+        assert_eq!(full.total(), u64::MAX);
+        assert_eq!(CpuUsage::between(&full, &CpuTimes { system: 10, ..full }), CpuUsage::default());
+        assert_eq!(CpuUsage::between(&full, &times(10, 0, 10)), CpuUsage::default());
+    }
+
+    #[test]
+    fn sample_between_readings() {
+        let before = ProcStat { total: times(100, 0, 100), cpus: vec![(0, times(50, 0, 50)), (1, times(50, 0, 50))] };
+        // CPU 1 went offline and CPU 2 came online
+        let after = ProcStat { total: times(150, 0, 150), cpus: vec![(0, times(100, 0, 50)), (2, times(0, 0, 10))] };
+
+        let sample = UsageSample::between(&before, &after, Duration::from_secs(1));
+        assert_eq!(sample.total, CpuUsage { user: 50.0, idle: 50.0, ..CpuUsage::default() });
+        assert_eq!(sample.cpus, vec![(0, CpuUsage { user: 100.0, ..CpuUsage::default() })]);
+    }
+
+    #[test]
+    fn sampler() {
+        let dir = TempDir::new();
+        let path = dir.write("stat", "cpu 100 0 0 100\ncpu0 100 0 0 100\n");
+        let mut sampler = CpuUsageSampler::with_proc_stat(&path, Duration::ZERO);
+
+        // The first sample reads the file twice
+        assert_eq!(sampler.sample().unwrap().total, CpuUsage::default());
+
+        // The next sample starts where the previous one ended
+        std::fs::write(&path, "cpu 175 0 0 125\ncpu0 175 0 0 125\n").unwrap();
+        let sample = sampler.next().unwrap();
+        assert_eq!(sample.total, CpuUsage { user: 75.0, idle: 25.0, ..CpuUsage::default() });
+        assert_eq!(sample.cpus, vec![(0, sample.total)]);
+
+        let mut samples = 0;
+        sampler.run(|_| {
+            samples += 1;
+            samples < 3
+        });
+        assert_eq!(samples, 3);
+
+        std::fs::remove_file(&path).unwrap();
+        let mut sampler = CpuUsageSampler::with_proc_stat(&path, Duration::ZERO);
+        assert_eq!(sampler.sample(), None);
+    }
+}
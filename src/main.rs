@@ -1,12 +1,39 @@
-use env_logger::init;
-use hwisak_rs::cpu::amd::{eAMDData, AMDData};
-use hwisak_rs::cpu::{eCPUDetails, CPUDetails, Database, EnumCPUData};
-use hwisak_rs::cpu::intel::IntelData;
-use hwisak_rs::gpu::{BackendType, GPUDetails};
+use hwisak_rs::cpu::compare::{lookup_in, Comparison};
+use hwisak_rs::cpu::export::{export_database_in, write_specs, ExportFormat};
+use hwisak_rs::cpu::search::search_in;
+use hwisak_rs::cpu::upgrade::candidates_for_in;
+use hwisak_rs::cpu::database::CpuDatabase;
+use hwisak_rs::cpu::CPUDetails;
+use hwisak_rs::gpu::GPUDetails;
 use hwisak_rs::os::OSDetails;
 
+const USAGE: &str = r#"Usage:
+    hwisak-rs                            Prints the details of this machine
+    hwisak-rs compare "<cpu a>" "<cpu b>"  Compares two CPUs of the database
+    hwisak-rs upgrades ["<cpu>"]         Lists CPUs for the same socket that are faster than
+                                         the given CPU, or the CPU of this machine
+    hwisak-rs export <file> ["<search>"] Writes the CPUs matching the search, or every CPU, to
+                                         a .csv, .jsonl or .sql file"#;
+
 fn main() {
     hwisak_rs::init();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => print_machine_info(),
+        ["compare", a, b] => compare(a, b),
+        ["upgrades"] => upgrades(None),
+        ["upgrades", cpu] => upgrades(Some(cpu)),
+        ["export", file] => export(file, None),
+        ["export", file, text] => export(file, Some(text)),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn print_machine_info() {
     println!(r"
 
     Machine info using hwisak-rs library
@@ -23,4 +50,91 @@ fn main() {
              OSDetails::fetch(),
              GPUDetails::fetch(),
     );
-}
\ No newline at end of file
+}
+
+fn compare(a: &str, b: &str) {
+    let result = CpuDatabase::open_default().and_then(|db| Ok((lookup_in(&db, a)?, lookup_in(&db, b)?)));
+
+    match result {
+        Ok((Some(a), Some(b))) => println!("{}", Comparison::new(a, b)),
+        Ok((a_spec, b_spec)) => {
+            for (name, spec) in [(a, a_spec), (b, b_spec)] {
+                if spec.is_none() {
+                    eprintln!("No CPU matching \"{}\" in the database", name);
+                }
+            }
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("An error occurred while reading the CPU database: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn upgrades(cpu: Option<&str>) {
+    let result = CpuDatabase::open_default().and_then(|db| {
+        let current = match cpu {
+            Some(name) => lookup_in(&db, name)?,
+            None => CPUDetails::fetch().spec,
+        };
+        let candidates = match &current {
+            Some(spec) => candidates_for_in(&db, spec)?,
+            None => Vec::new(),
+        };
+        Ok((current, candidates))
+    });
+
+    match result {
+        Ok((None, _)) => {
+            eprintln!("No CPU matching \"{}\" in the database", cpu.unwrap_or("this machine's CPU"));
+            std::process::exit(1);
+        }
+        Ok((Some(current), candidates)) => {
+            let Some(socket) = &current.socket else {
+                println!("The socket of {} is not known, it is probably soldered", current.name);
+                return;
+            };
+
+            println!("{} ({}): {} upgrade(s)", current.name, socket, candidates.len());
+            for candidate in &candidates {
+                println!("  {}: {}", candidate.spec.name, candidate.summary());
+            }
+        }
+        Err(err) => {
+            eprintln!("An error occurred while reading the CPU database: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn export(file: &str, text: Option<&str>) {
+    let Some(format) = ExportFormat::from_path(file) else {
+        eprintln!("Unknown export format for \"{}\", use a .csv, .jsonl or .sql file", file);
+        std::process::exit(2);
+    };
+
+    let result = CpuDatabase::open_default().map_err(|e| e.to_string()).and_then(|db| {
+        let writer = std::io::BufWriter::new(std::fs::File::create(file).map_err(|e| e.to_string())?);
+        match text {
+            Some(text) => {
+                let specs = search_in(&db, text)
+                    .map_err(|e| e.to_string())?
+                    .iter()
+                    .filter_map(|hit| hit.details.spec())
+                    .collect::<Vec<_>>();
+                write_specs(&specs, format, writer)
+            }
+            None => export_database_in(&db, format, writer),
+        }
+        .map_err(|e| e.to_string())
+    });
+
+    match result {
+        Ok(count) => println!("Exported {} CPU(s) to {}", count, file),
+        Err(err) => {
+            eprintln!("An error occurred while exporting the CPU database: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
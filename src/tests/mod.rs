@@ -0,0 +1,67 @@
+//! Helpers shared by the unit tests.
+
+#[cfg(test)]
+pub(crate) use fixture::{source, TempDir};
+
+#[cfg(test)]
+mod fixture {
+    use std::borrow::Cow;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::cpu::history::Snapshot;
+    use crate::cpu::import::CsvSource;
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    /// A directory below the system temporary directory that is removed when dropped, e.g. for
+    /// recorded sysfs trees or overlay files.
+    pub(crate) struct TempDir {
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!("hwisak-test-{}-{}", std::process::id(), id));
+
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir { path }
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Writes `contents` to the file at `relative`, creating its directories.
+        pub(crate) fn write(&self, relative: impl AsRef<Path>, contents: &str) -> PathBuf {
+            let path = self.path.join(relative);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+
+        /// Creates the directory at `relative` and its parents, e.g. for sysfs links.
+        pub(crate) fn create_dir(&self, relative: impl AsRef<Path>) -> PathBuf {
+            let path = self.path.join(relative);
+            std::fs::create_dir_all(&path).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /// A CSV file with the snapshot of its `path`, e.g. `"v1_2/intel.csv"`. Overlays, with
+    /// `user_provided`, have no snapshot.
+    pub(crate) fn source(path: &'static str, text: &'static str, user_provided: bool) -> CsvSource<'static> {
+        CsvSource {
+            path: Cow::Borrowed(path),
+            text: Cow::Borrowed(text),
+            user_provided,
+            snapshot: if user_provided { Snapshot::Unknown } else { Snapshot::from_path(path) },
+        }
+    }
+}
@@ -0,0 +1,90 @@
+/// Returns the first number that appears anywhere in the string, e.g. `7` for `"TSMC 7nm FinFET"`.
+pub(crate) fn first_number(s: &str) -> Option<f64> {
+    let start = s.find(|c: char| c.is_ascii_digit())?;
+    leading_number(&s[start..])
+}
+
+/// Parses the number at the start of the string (after trimming), e.g. `65.0` for `"65W"`.
+pub(crate) fn leading_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let end = s
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_digit() || *c == '.'))
+        .map(|(i, _)| i)
+        .unwrap_or(s.len());
+
+    s[..end].parse::<f64>().ok()
+}
+
+/// Parses a size such as `"512KB"`, `"32 MB"` or `"12 MB Intel Smart Cache"` into bytes.
+/// Bare numbers are interpreted with `default_unit` (e.g. `1024 * 1024` for megabytes).
+pub(crate) fn parse_size_bytes(s: &str, default_unit: u64) -> Option<u64> {
+    let value = first_number(s)?;
+    let upper = s.to_uppercase();
+
+    let unit = if upper.contains("GB") || upper.contains("GIB") {
+        1024 * 1024 * 1024
+    } else if upper.contains("MB") || upper.contains("MIB") {
+        1024 * 1024
+    } else if upper.contains("KB") || upper.contains("KIB") {
+        1024
+    } else {
+        default_unit
+    };
+
+    Some((value * unit as f64).round() as u64)
+}
+
+/// Where sysfs is mounted on Linux.
+pub(crate) const SYSFS_ROOT: &str = "/sys";
+
+/// The CPU directory below a sysfs root, with one `cpu<N>` directory per logical CPU.
+pub(crate) const SYSFS_CPU_DIR: &str = "devices/system/cpu";
+
+/// The trimmed contents of a sysfs file, `None` if it does not exist or is empty.
+pub(crate) fn read_sysfs(path: impl AsRef<std::path::Path>) -> Option<String> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let contents = contents.trim();
+    (!contents.is_empty()).then(|| contents.to_string())
+}
+
+/// A sysfs file holding a single number, e.g. `topology/core_id`.
+pub(crate) fn read_sysfs_number<T: std::str::FromStr>(path: impl AsRef<std::path::Path>) -> Option<T> {
+    read_sysfs(path)?.parse().ok()
+}
+
+/// Parses a CPU list like `"0-3,8,10-11"` into `[0, 1, 2, 3, 8, 10, 11]`. Invalid ranges are
+/// skipped.
+pub(crate) fn parse_cpu_list(s: &str) -> Vec<usize> {
+    let mut cpus = s
+        .split(',')
+        .filter_map(|range| {
+            let range = range.trim();
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            Some(start.trim().parse::<usize>().ok()?..=end.trim().parse::<usize>().ok()?)
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+
+    cpus.sort_unstable();
+    cpus.dedup();
+    cpus
+}
+
+/// The logical CPUs below a sysfs CPU directory as `(id, path)`, sorted by id.
+pub(crate) fn sysfs_cpus(cpu_dir: &std::path::Path) -> Vec<(usize, std::path::PathBuf)> {
+    let Ok(entries) = std::fs::read_dir(cpu_dir) else {
+        return Vec::new();
+    };
+
+    let mut cpus = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry.file_name().to_str()?.strip_prefix("cpu")?.parse().ok()?;
+            Some((id, entry.path()))
+        })
+        .collect::<Vec<_>>();
+
+    cpus.sort_by_key(|(id, _)| *id);
+    cpus
+}